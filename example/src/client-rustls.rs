@@ -1,33 +1,15 @@
-use std::io::Cursor;
-
 use monoio::{
     io::{AsyncReadRentExt, AsyncWriteRent, AsyncWriteRentExt},
     net::TcpStream,
 };
 use monoio_rustls::TlsConnector;
-use rustls::{RootCertStore};
-use rustls::pki_types::CertificateDer;
-use rustls_pemfile::certs;
 
 #[monoio::main]
 async fn main() {
-    let mut root_store = RootCertStore::empty();
-    // Uncomment if you want to use webpki_roots provided CA certs.
-    // root_store.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
-    //     rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
-    //         ta.subject,
-    //         ta.spki,
-    //         ta.name_constraints,
-    //     )
-    // }));
-    root_store
-        .add(read_ca_certs())
-        .expect("unable to trust self-signed CA");
-    let config = rustls::ClientConfig::builder()
-        .with_root_certificates(root_store)
-        .with_no_client_auth();
-
-    let connector = TlsConnector::from(config);
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let connector =
+        TlsConnector::with_ca_file(concat!(env!("CARGO_MANIFEST_DIR"), "/certs/rootCA.crt"))
+            .expect("unable to trust self-signed CA");
     let stream = TcpStream::connect("127.0.0.1:50443").await.unwrap();
     println!("127.0.0.1:50443 connected");
 
@@ -47,9 +29,3 @@ async fn main() {
     );
     let _ = stream.shutdown().await;
 }
-
-fn read_ca_certs() -> CertificateDer<'static> {
-    let mut ca_cursor = Cursor::new(include_bytes!("../certs/rootCA.crt"));
-    let cert = certs(&mut ca_cursor).into_iter().next().unwrap().unwrap();
-    cert
-}
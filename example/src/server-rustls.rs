@@ -1,24 +1,21 @@
 //! An echo server with tls.
 
-use std::io::{self, Cursor};
+use std::io;
 
 use monoio::{
     io::{AsyncReadRent, AsyncWriteRentExt},
     net::{TcpListener, TcpStream},
 };
 use monoio_rustls::TlsAcceptor;
-use rustls::{ServerConfig};
-use rustls::pki_types::{CertificateDer, PrivateKeyDer};
-use rustls_pemfile::{certs, rsa_private_keys};
 
 #[monoio::main]
 async fn main() {
-    let (chain, key) = read_server_certs();
-    let config = ServerConfig::builder()
-        .with_no_client_auth()
-        .with_single_cert(chain, key)
-        .expect("invalid cert chain or key");
-    let tls_acceptor = TlsAcceptor::from(config);
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+    let tls_acceptor = TlsAcceptor::from_pem_files(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/certs/server.crt"),
+        concat!(env!("CARGO_MANIFEST_DIR"), "/certs/server.key"),
+    )
+    .expect("invalid cert chain or key");
 
     let listener = TcpListener::bind("127.0.0.1:50443").expect("unable to listen 127.0.0.1:50443");
     while let Ok((stream, addr)) = listener.accept().await {
@@ -65,20 +62,3 @@ async fn process_raw_stream(stream: TcpStream, tls_acceptor: TlsAcceptor) -> io:
     println!("Relay finished normally, {n} bytes processed");
     Ok(())
 }
-
-fn read_server_certs() -> (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) {
-    let mut ca_cursor = Cursor::new(include_bytes!("../certs/rootCA.crt"));
-    let ca_data = certs(&mut ca_cursor).into_iter().next().unwrap().unwrap();
-    let ca = CertificateDer::from(ca_data);
-
-    let mut crt_cursor = Cursor::new(include_bytes!("../certs/server.crt"));
-    let crt_data = certs(&mut crt_cursor).into_iter().next().unwrap().unwrap();
-    let crt = CertificateDer::from(crt_data);
-
-    let mut key_cursor = Cursor::new(include_bytes!("../certs/server.key"));
-    let key_data = rsa_private_keys(&mut key_cursor).into_iter().next().unwrap().unwrap();
-    let key = PrivateKeyDer::from(key_data);
-
-    let chain = vec![crt, ca];
-    (chain, key)
-}
@@ -0,0 +1,23 @@
+use std::env;
+
+/// OpenSSL 3.2.0, the first release retrying a flush which returned
+/// WouldBlock(openssl/openssl#20919), as `OPENSSL_VERSION_NUMBER`(0xMNN00PP0).
+const FLUSH_RETRY_VERSION: u64 = 0x3020_0000;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=DEP_OPENSSL_VERSION_NUMBER");
+
+    // Set by openssl-sys when the `openssl` feature is enabled. LibreSSL does
+    // not report it.
+    let openssl_version = env::var("DEP_OPENSSL_VERSION_NUMBER")
+        .ok()
+        .and_then(|version| u64::from_str_radix(&version, 16).ok());
+    // rust-openssl has to report the WouldBlock of a flush as a retry too
+    // (sfackler/rust-openssl#1922). That is not released, so builds carrying
+    // it opt in with `--cfg monoio_rust_openssl_flush_retry`.
+    let rust_openssl = env::var_os("CARGO_CFG_MONOIO_RUST_OPENSSL_FLUSH_RETRY").is_some();
+
+    if rust_openssl && openssl_version.is_some_and(|version| version >= FLUSH_RETRY_VERSION) {
+        println!("cargo:rustc-cfg=monoio_openssl_flush_would_block");
+    }
+}
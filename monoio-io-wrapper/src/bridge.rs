@@ -0,0 +1,274 @@
+use std::{cell::UnsafeCell, io, rc::Rc};
+#[cfg(feature = "poll-io")]
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+#[cfg(feature = "poll-io")]
+use monoio::io::{
+    poll_io::{AsyncRead, AsyncWrite},
+    IntoCompIo, IntoPollIo,
+};
+use monoio::io::{AsyncReadRent, AsyncWriteRent};
+
+use crate::{ReadBuffer, WriteBuffer};
+
+/// Whether OpenSSL can retry a flush returning WouldBlock, which needs both
+/// openssl/openssl#20919 and sfackler/rust-openssl#1922.
+///
+/// The build script checks the OpenSSL version reported by openssl-sys(with
+/// the `openssl` feature). The rust-openssl fix is not released, so builds
+/// carrying it opt in with `--cfg monoio_rust_openssl_flush_retry`.
+pub const OPENSSL_FLUSH_WOULD_BLOCK: bool = cfg!(monoio_openssl_flush_would_block);
+
+/// The sync io given to the tls library, which reads from and writes to the
+/// buffers shared with the async io.
+#[derive(Debug, Clone)]
+pub struct Buffers {
+    r_buffer: Rc<UnsafeCell<ReadBuffer>>,
+    w_buffer: Rc<UnsafeCell<WriteBuffer>>,
+    flush_would_block: bool,
+}
+
+/// An async io with the buffers shared with [`Buffers`].
+/// The tls library does sync io on the `Buffers`, and the data is moved
+/// between the buffers and the async io by this wrapper.
+#[derive(Debug)]
+pub struct IOWrapper<IO> {
+    io: IO,
+    r_buffer: Rc<UnsafeCell<ReadBuffer>>,
+    w_buffer: Rc<UnsafeCell<WriteBuffer>>,
+}
+
+impl<IO> IOWrapper<IO> {
+    pub fn new(io: IO, r_buffer: ReadBuffer, w_buffer: WriteBuffer) -> Self {
+        Self {
+            io,
+            r_buffer: Rc::new(UnsafeCell::new(r_buffer)),
+            w_buffer: Rc::new(UnsafeCell::new(w_buffer)),
+        }
+    }
+
+    pub fn new_with_buffer_size(io: IO, r: Option<usize>, w: Option<usize>) -> Self {
+        let r_buffer = match r {
+            Some(rb) => ReadBuffer::new(rb),
+            None => ReadBuffer::default(),
+        };
+        let w_buffer = match w {
+            Some(rb) => WriteBuffer::new(rb),
+            None => WriteBuffer::default(),
+        };
+
+        Self::new(io, r_buffer, w_buffer)
+    }
+
+    /// Create a new IOWrapper that uses unsafe I/O, reading into and writing
+    /// from the buffers of the tls library directly.
+    /// # Safety
+    /// Users must make sure the buffer ptr and len is valid until io finished.
+    /// So the Future cannot be dropped directly. Consider using CancellableIO.
+    /// The tls library must also keep the buffer passed to a read returning
+    /// WouldBlock until it reads again, e.g. OpenSSL must not have
+    /// SSL_MODE_RELEASE_BUFFERS set.
+    #[cfg(feature = "unsafe_io")]
+    pub unsafe fn new_unsafe(io: IO) -> Self {
+        Self::new(io, ReadBuffer::new_unsafe(), WriteBuffer::new_unsafe())
+    }
+
+    /// Create the sync io for the tls library.
+    /// Unless `flush_would_block` is set, `Buffers::flush` never returns
+    /// WouldBlock since some tls libraries cannot handle it, see
+    /// [`OPENSSL_FLUSH_WOULD_BLOCK`].
+    pub fn buffers(&self, flush_would_block: bool) -> Buffers {
+        Buffers {
+            r_buffer: self.r_buffer.clone(),
+            w_buffer: self.w_buffer.clone(),
+            flush_would_block,
+        }
+    }
+
+    pub fn get_ref(&self) -> &IO {
+        &self.io
+    }
+
+    pub fn get_mut(&mut self) -> &mut IO {
+        &mut self.io
+    }
+
+    pub fn into_parts(self) -> (IO, Rc<UnsafeCell<ReadBuffer>>, Rc<UnsafeCell<WriteBuffer>>) {
+        (self.io, self.r_buffer, self.w_buffer)
+    }
+
+    /// Read from the io into the read buffer.
+    /// # Safety
+    /// The captured dest of an unsafe buffer must be valid until io finished.
+    pub async unsafe fn do_read_io(&mut self) -> std::io::Result<usize>
+    where
+        IO: AsyncReadRent,
+    {
+        (*self.r_buffer.get()).do_io(&mut self.io).await
+    }
+
+    /// Write the write buffer into the io. Returns 0 if there is nothing to
+    /// write.
+    /// # Safety
+    /// The captured src of an unsafe buffer must be valid until io finished.
+    pub async unsafe fn do_write_io(&mut self) -> std::io::Result<usize>
+    where
+        IO: AsyncWriteRent,
+    {
+        match (*self.w_buffer.get()).do_io(&mut self.io).await {
+            // the unsafe buffer returns WouldBlock when no write has been
+            // captured, which means there is nothing to write.
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(0),
+            ret => ret,
+        }
+    }
+}
+
+impl<IO: AsyncReadRent> IOWrapper<IO> {
+    #[inline]
+    #[allow(clippy::await_holding_refcell_ref)]
+    pub async fn read_io(&mut self) -> io::Result<usize> {
+        unsafe { &mut *self.r_buffer.get() }
+            .do_io(&mut self.io)
+            .await
+    }
+}
+
+impl<IO: AsyncWriteRent> IOWrapper<IO> {
+    #[inline]
+    #[allow(clippy::await_holding_refcell_ref)]
+    pub async fn write_io(&mut self) -> io::Result<usize> {
+        unsafe { self.do_write_io() }.await
+    }
+
+    #[inline]
+    pub async fn flush_io(&mut self) -> io::Result<()> {
+        self.io.flush().await
+    }
+
+    #[inline]
+    pub async fn shutdown_io(&mut self) -> io::Result<()> {
+        self.io.shutdown().await
+    }
+}
+
+#[cfg(feature = "poll-io")]
+impl<IO: AsyncRead + Unpin> IOWrapper<IO> {
+    #[inline]
+    pub fn poll_read_io(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        unsafe { &mut *self.r_buffer.get() }.poll_io(&mut self.io, cx)
+    }
+}
+
+#[cfg(feature = "poll-io")]
+impl<IO: AsyncWrite + Unpin> IOWrapper<IO> {
+    #[inline]
+    pub fn poll_write_io(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        unsafe { &mut *self.w_buffer.get() }.poll_io(&mut self.io, cx)
+    }
+
+    #[inline]
+    pub fn poll_flush_io(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_io(cx))?;
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    #[inline]
+    pub fn poll_shutdown_io(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.poll_write_io(cx))?;
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(feature = "poll-io")]
+impl<IO: IntoPollIo> IntoPollIo for IOWrapper<IO> {
+    type PollIo = IOWrapper<IO::PollIo>;
+
+    fn try_into_poll_io(self) -> Result<Self::PollIo, (io::Error, Self)> {
+        // unsafe buffers capture the dest of the last std io, which cannot
+        // outlive a poll.
+        let safe = unsafe { (*self.r_buffer.get()).is_safe() && (*self.w_buffer.get()).is_safe() };
+        if !safe {
+            return Err((io::ErrorKind::Unsupported.into(), self));
+        }
+        let IOWrapper {
+            io,
+            r_buffer,
+            w_buffer,
+        } = self;
+        match io.try_into_poll_io() {
+            Ok(io) => Ok(IOWrapper {
+                io,
+                r_buffer,
+                w_buffer,
+            }),
+            Err((e, io)) => Err((
+                e,
+                IOWrapper {
+                    io,
+                    r_buffer,
+                    w_buffer,
+                },
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "poll-io")]
+impl<IO: IntoCompIo> IntoCompIo for IOWrapper<IO> {
+    type CompIo = IOWrapper<IO::CompIo>;
+
+    fn try_into_comp_io(self) -> Result<Self::CompIo, (io::Error, Self)> {
+        let IOWrapper {
+            io,
+            r_buffer,
+            w_buffer,
+        } = self;
+        match io.try_into_comp_io() {
+            Ok(io) => Ok(IOWrapper {
+                io,
+                r_buffer,
+                w_buffer,
+            }),
+            Err((e, io)) => Err((
+                e,
+                IOWrapper {
+                    io,
+                    r_buffer,
+                    w_buffer,
+                },
+            )),
+        }
+    }
+}
+
+impl io::Read for Buffers {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        unsafe { &mut *self.r_buffer.get() }.read(buf)
+    }
+}
+
+impl io::Write for Buffers {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        unsafe { &mut *self.w_buffer.get() }.write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        // Due to openssl and rust-openssl issue, in
+        // flush we cannot return WouldBlock unless both of them are fixed.
+        // Related PRs:
+        // https://github.com/openssl/openssl/pull/20919
+        // https://github.com/sfackler/rust-openssl/pull/1922
+        if self.flush_would_block {
+            unsafe { &mut *self.w_buffer.get() }.flush()
+        } else {
+            Ok(())
+        }
+    }
+}
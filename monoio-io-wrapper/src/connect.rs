@@ -0,0 +1,208 @@
+//! Connecting to a host with Happy Eyeballs(RFC 8305), shared by the TLS
+//! connectors.
+
+use std::{
+    future::{poll_fn, Future},
+    io,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    pin::Pin,
+    task::Poll,
+    time::Duration,
+};
+
+use monoio::{net::TcpStream, time::Sleep};
+
+/// How long to wait for a connection attempt before starting the next one,
+/// the value recommended by RFC 8305.
+const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+type Attempt = Pin<Box<dyn Future<Output = io::Result<TcpStream>>>>;
+
+/// Resolve `host:port` and connect to it with Happy Eyeballs, see
+/// [`connect_addrs`]. The host is a DNS name or an IP address, in brackets
+/// for IPv6. Returns the stream and the host, without brackets.
+///
+/// With the `sync` feature the name is resolved by the blocking resolver of
+/// std on the thread pool of `monoio::spawn_blocking`, so the runtime must
+/// have one attached(or use `BlockingStrategy::ExecuteLocal`). Without it,
+/// the resolver runs on the runtime thread, blocking every task until it
+/// returns; pass the addresses of an async resolver to [`connect_addrs`] to
+/// avoid it.
+pub async fn connect_host(addr: &str) -> io::Result<(TcpStream, &str)> {
+    let (host, port) = split_host_port(addr)?;
+    let addrs = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => resolve(host, port).await?,
+    };
+    let stream = connect_addrs(addrs).await?;
+    Ok((stream, host))
+}
+
+/// Connect to one of the addresses with Happy Eyeballs.
+///
+/// Addresses are tried in order, alternating between IPv6 and IPv4; a new
+/// attempt is started every 250ms or as soon as the previous one fails, and
+/// the first established connection wins. When every attempt fails, the
+/// last error is returned. The runtime must have timer enabled.
+pub async fn connect_addrs(addrs: impl IntoIterator<Item = SocketAddr>) -> io::Result<TcpStream> {
+    happy_eyeballs(interleave(addrs.into_iter().collect())).await
+}
+
+#[cfg(feature = "sync")]
+async fn resolve(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    let host = host.to_owned();
+    monoio::spawn_blocking(move || Ok((host, port).to_socket_addrs()?.collect()))
+        .await
+        .map_err(|_| io::Error::other("name resolution canceled"))?
+}
+
+#[cfg(not(feature = "sync"))]
+async fn resolve(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    Ok((host, port).to_socket_addrs()?.collect())
+}
+
+/// Split `host:port`, removing the brackets around IPv6 addresses, which
+/// are required.
+pub fn split_host_port(addr: &str) -> io::Result<(&str, u16)> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "expect host:port");
+    let (host, port) = addr.rsplit_once(':').ok_or_else(invalid)?;
+    let port = port.parse().map_err(|_| invalid())?;
+    let host = match host.strip_prefix('[') {
+        Some(host) => host.strip_suffix(']').ok_or_else(invalid)?,
+        // an IPv6 address must be in brackets.
+        None if host.contains(':') => return Err(invalid()),
+        None => host,
+    };
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    Ok((host, port))
+}
+
+/// Alternate address families, starting with the family of the first
+/// address.
+fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first() else {
+        return addrs;
+    };
+    let first_v6 = first.is_ipv6();
+    let (preferred, other): (Vec<_>, Vec<_>) =
+        addrs.into_iter().partition(|a| a.is_ipv6() == first_v6);
+    let mut preferred = preferred.into_iter();
+    let mut other = other.into_iter();
+    let mut sorted = Vec::with_capacity(preferred.len() + other.len());
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => return sorted,
+            (a, b) => sorted.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+enum Event {
+    Connected(TcpStream),
+    Failed(io::Error),
+    Timeout,
+}
+
+async fn happy_eyeballs(addrs: Vec<SocketAddr>) -> io::Result<TcpStream> {
+    let mut addrs = addrs.into_iter();
+    let mut attempts: Vec<Attempt> = Vec::new();
+    let mut delay: Option<Pin<Box<Sleep>>> = None;
+    let mut last_error = None;
+    loop {
+        if delay.is_none() {
+            if let Some(addr) = addrs.next() {
+                attempts.push(Box::pin(TcpStream::connect(addr)));
+                delay = Some(Box::pin(monoio::time::sleep(ATTEMPT_DELAY)));
+            } else if attempts.is_empty() {
+                return Err(last_error.unwrap_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, "no address resolved")
+                }));
+            }
+        }
+
+        let event = poll_fn(|cx| {
+            for i in 0..attempts.len() {
+                if let Poll::Ready(result) = attempts[i].as_mut().poll(cx) {
+                    drop(attempts.swap_remove(i));
+                    return Poll::Ready(match result {
+                        Ok(stream) => Event::Connected(stream),
+                        Err(e) => Event::Failed(e),
+                    });
+                }
+            }
+            match delay.as_mut().map(|sleep| sleep.as_mut().poll(cx)) {
+                Some(Poll::Ready(())) => Poll::Ready(Event::Timeout),
+                _ => Poll::Pending,
+            }
+        })
+        .await;
+        match event {
+            Event::Connected(stream) => return Ok(stream),
+            Event::Failed(e) => {
+                last_error = Some(e);
+                delay = None;
+            }
+            Event::Timeout => delay = None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    #[test]
+    fn split() {
+        assert_eq!(
+            split_host_port("example.com:443").unwrap(),
+            ("example.com", 443)
+        );
+        assert_eq!(split_host_port("127.0.0.1:80").unwrap(), ("127.0.0.1", 80));
+        assert_eq!(split_host_port("[::1]:8443").unwrap(), ("::1", 8443));
+        assert_eq!(
+            split_host_port("[fe80::1%eth0]:1").unwrap(),
+            ("fe80::1%eth0", 1)
+        );
+        for invalid in [
+            "example.com",
+            "example.com:",
+            "example.com:https",
+            "example.com:65536",
+            ":443",
+            "[]:443",
+            "::1:443",
+            "[::1:443",
+            "[::1]",
+        ] {
+            let e = split_host_port(invalid).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidInput, "{invalid}");
+        }
+    }
+
+    fn v4(last: u8) -> SocketAddr {
+        (Ipv4Addr::new(192, 0, 2, last), 443).into()
+    }
+
+    fn v6(last: u16) -> SocketAddr {
+        (Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, last), 443).into()
+    }
+
+    #[test]
+    fn interleaved() {
+        assert_eq!(interleave(vec![]), vec![]);
+        assert_eq!(interleave(vec![v4(1), v4(2)]), vec![v4(1), v4(2)]);
+        // the family of the first address comes first.
+        assert_eq!(
+            interleave(vec![v6(1), v6(2), v6(3), v4(1)]),
+            vec![v6(1), v4(1), v6(2), v6(3)]
+        );
+        assert_eq!(
+            interleave(vec![v4(1), v4(2), v6(1), v6(2), v6(3)]),
+            vec![v4(1), v6(1), v4(2), v6(2), v6(3)]
+        );
+    }
+}
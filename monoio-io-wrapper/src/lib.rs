@@ -1,11 +1,21 @@
 #![allow(clippy::unsafe_removed_from_name)]
 
+#[cfg(feature = "poll-io")]
+use std::task::{Context, Poll};
+
+#[cfg(feature = "poll-io")]
+use monoio::io::poll_io::{AsyncRead, AsyncWrite};
 use monoio::io::{AsyncReadRent, AsyncWriteRent};
 
+mod bridge;
+mod connect;
 mod safe_io;
 #[cfg(feature = "unsafe_io")]
 mod unsafe_io;
 
+pub use bridge::{Buffers, IOWrapper, OPENSSL_FLUSH_WOULD_BLOCK};
+pub use connect::{connect_addrs, connect_host, split_host_port};
+
 #[derive(Debug)]
 pub enum ReadBuffer {
     Safe(safe_io::SafeRead),
@@ -46,6 +56,22 @@ impl ReadBuffer {
         }
     }
 
+    /// Poll-based version of `do_io`.
+    /// Unsafe buffers are not supported since the dest cannot outlive a poll.
+    #[inline]
+    #[cfg(feature = "poll-io")]
+    pub fn poll_io<IO: AsyncRead + Unpin>(
+        &mut self,
+        io: &mut IO,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<usize>> {
+        match self {
+            Self::Safe(b) => b.poll_io(io, cx),
+            #[cfg(feature = "unsafe_io")]
+            Self::Unsafe(_) => Poll::Ready(Err(std::io::ErrorKind::Unsupported.into())),
+        }
+    }
+
     #[inline]
     #[cfg(feature = "unsafe_io")]
     pub fn is_safe(&self) -> bool {
@@ -107,6 +133,22 @@ impl WriteBuffer {
         }
     }
 
+    /// Poll-based version of `do_io`.
+    /// Unsafe buffers are not supported since the src cannot outlive a poll.
+    #[inline]
+    #[cfg(feature = "poll-io")]
+    pub fn poll_io<IO: AsyncWrite + Unpin>(
+        &mut self,
+        io: &mut IO,
+        cx: &mut Context<'_>,
+    ) -> Poll<std::io::Result<usize>> {
+        match self {
+            Self::Safe(buf) => buf.poll_io(io, cx),
+            #[cfg(feature = "unsafe_io")]
+            Self::Unsafe(_) => Poll::Ready(Err(std::io::ErrorKind::Unsupported.into())),
+        }
+    }
+
     #[inline]
     #[cfg(feature = "unsafe_io")]
     pub fn is_safe(&self) -> bool {
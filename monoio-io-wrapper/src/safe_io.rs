@@ -1,5 +1,9 @@
+#[cfg(feature = "poll-io")]
+use std::task::{Context, Poll};
 use std::{fmt::Debug, io, mem};
 
+#[cfg(feature = "poll-io")]
+use monoio::io::poll_io::{AsyncRead, AsyncWrite, ReadBuf};
 use monoio::{
     buf::{IoBuf, IoBufMut},
     io::{AsyncReadRent, AsyncWriteRent, AsyncWriteRentExt},
@@ -149,6 +153,47 @@ impl SafeRead {
             }
         }
     }
+
+    /// `poll_io` is the poll-based version of `do_io`, it reads from a poll-io
+    /// into inner buffer.
+    /// # Handle return value
+    /// _: the read result.
+    #[cfg(feature = "poll-io")]
+    pub fn poll_io<IO: AsyncRead + Unpin>(
+        &mut self,
+        io: &mut IO,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<usize>> {
+        // if there are some data inside the buffer, just return.
+        let buffer = self.buffer.as_mut().expect("buffer mut expected");
+        if !buffer.is_empty() {
+            return Poll::Ready(Ok(buffer.len()));
+        }
+
+        // read from raw io into the spare space.
+        let dst = unsafe { std::slice::from_raw_parts_mut(buffer.write_ptr(), buffer.available()) };
+        let mut read_buf = ReadBuf::new(dst);
+        let result = match std::pin::Pin::new(io).poll_read(cx, &mut read_buf) {
+            Poll::Ready(result) => result.map(|_| read_buf.filled().len()),
+            Poll::Pending => return Poll::Pending,
+        };
+        match result {
+            Ok(0) => {
+                self.status = ReadStatus::Eof;
+                Poll::Ready(Ok(0))
+            }
+            Ok(n) => {
+                unsafe { buffer.set_init(n) };
+                self.status = ReadStatus::Ok;
+                Poll::Ready(Ok(n))
+            }
+            Err(e) => {
+                let rerr = e.kind().into();
+                self.status = ReadStatus::Err(e);
+                Poll::Ready(Err(rerr))
+            }
+        }
+    }
 }
 
 impl io::Read for SafeRead {
@@ -242,6 +287,43 @@ impl SafeWrite {
             }
         }
     }
+
+    /// `poll_io` is the poll-based version of `do_io`, it writes from inner
+    /// buffer to a poll-io until the buffer is empty.
+    /// # Handle return value
+    /// _: the written length(note: on Pending the progress is kept inside the
+    /// buffer, calling it again will continue).
+    #[cfg(feature = "poll-io")]
+    pub fn poll_io<IO: AsyncWrite + Unpin>(
+        &mut self,
+        io: &mut IO,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<usize>> {
+        let buffer = self.buffer.as_mut().expect("buffer mut expected");
+        let mut written = 0;
+        while !buffer.is_empty() {
+            let src = unsafe { std::slice::from_raw_parts(buffer.read_ptr(), buffer.len()) };
+            match std::pin::Pin::new(&mut *io).poll_write(cx, src) {
+                Poll::Ready(Ok(0)) => {
+                    let e = io::Error::from(io::ErrorKind::WriteZero);
+                    let rerr = e.kind().into();
+                    self.status = WriteStatus::Err(e);
+                    return Poll::Ready(Err(rerr));
+                }
+                Poll::Ready(Ok(n)) => {
+                    buffer.advance(n);
+                    written += n;
+                }
+                Poll::Ready(Err(e)) => {
+                    let rerr = e.kind().into();
+                    self.status = WriteStatus::Err(e);
+                    return Poll::Ready(Err(rerr));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(written))
+    }
 }
 
 impl io::Write for SafeWrite {
@@ -0,0 +1,69 @@
+use std::io::{ErrorKind, Read, Write};
+
+use monoio::{
+    io::{AsyncReadRentExt, AsyncWriteRentExt},
+    net::{TcpListener, TcpStream},
+};
+use monoio_io_wrapper::{IOWrapper, OPENSSL_FLUSH_WOULD_BLOCK};
+
+#[test]
+fn flush_would_block_is_opt_in() {
+    assert_eq!(
+        OPENSSL_FLUSH_WOULD_BLOCK,
+        cfg!(monoio_openssl_flush_would_block)
+    );
+}
+
+#[test]
+fn flush_with_pending_data() {
+    let io = IOWrapper::new_with_buffer_size((), Some(16), Some(16));
+
+    let mut buffers = io.buffers(false);
+    assert_eq!(buffers.write(b"pending").unwrap(), 7);
+    // the pending data is hidden from the tls library.
+    buffers.flush().unwrap();
+
+    let mut buffers = io.buffers(true);
+    assert_eq!(buffers.flush().unwrap_err().kind(), ErrorKind::WouldBlock);
+}
+
+#[test]
+fn full_buffers_would_block() {
+    let io = IOWrapper::new_with_buffer_size((), Some(16), Some(4));
+    let mut buffers = io.buffers(false);
+    assert_eq!(buffers.write(b"too long").unwrap(), 4);
+    assert_eq!(
+        buffers.write(b"more").unwrap_err().kind(),
+        ErrorKind::WouldBlock
+    );
+    let mut buf = [0; 4];
+    assert_eq!(
+        buffers.read(&mut buf).unwrap_err().kind(),
+        ErrorKind::WouldBlock
+    );
+}
+
+#[monoio::test]
+async fn write_io_drains_buffers() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, server) = monoio::join!(TcpStream::connect(addr), listener.accept());
+    let mut io = IOWrapper::new_with_buffer_size(client.unwrap(), None, None);
+    let (mut server, _) = server.unwrap();
+
+    io.buffers(false).write_all(b"hello").unwrap();
+    assert_eq!(io.write_io().await.unwrap(), 5);
+    // nothing left to write.
+    assert_eq!(io.write_io().await.unwrap(), 0);
+    let (res, buf) = server.read_exact(vec![0; 5]).await;
+    res.unwrap();
+    assert_eq!(buf, b"hello");
+
+    // reads are buffered until the tls library takes them.
+    let (res, _) = server.write_all(b"world").await;
+    res.unwrap();
+    assert_eq!(io.read_io().await.unwrap(), 5);
+    let mut buf = [0; 5];
+    io.buffers(false).read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"world");
+}
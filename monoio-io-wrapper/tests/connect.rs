@@ -0,0 +1,65 @@
+use std::{io, net::SocketAddr};
+
+use monoio::net::TcpListener;
+use monoio_io_wrapper::{connect_addrs, connect_host};
+
+/// A loopback address nobody listens on. The listener of std is closed
+/// as soon as dropped.
+fn closed() -> SocketAddr {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap()
+}
+
+async fn resolve_and_connect() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    // localhost may resolve to ::1 first, where nobody listens.
+    for (addr, host) in [
+        (format!("localhost:{port}"), "localhost"),
+        (format!("127.0.0.1:{port}"), "127.0.0.1"),
+    ] {
+        let (stream, _) = monoio::join!(connect_host(&addr), listener.accept());
+        let (stream, name) = stream.unwrap();
+        assert_eq!(name, host);
+        assert_eq!(stream.peer_addr().unwrap().port(), port);
+    }
+}
+
+#[cfg(not(feature = "sync"))]
+#[monoio::test(timer_enabled = true)]
+async fn resolve_on_runtime_thread() {
+    resolve_and_connect().await;
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn resolve_on_thread_pool() {
+    monoio::RuntimeBuilder::<monoio::FusionDriver>::new()
+        .enable_timer()
+        .attach_thread_pool(Box::new(monoio::blocking::DefaultThreadPool::new(1)))
+        .build()
+        .unwrap()
+        .block_on(resolve_and_connect());
+}
+
+#[monoio::test]
+async fn invalid_host() {
+    for addr in ["localhost", "localhost:https", "::1:443"] {
+        let e = connect_host(addr).await.unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput, "{addr}");
+    }
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn addrs() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (stream, _) = monoio::join!(connect_addrs([closed(), closed(), addr]), listener.accept());
+    assert_eq!(stream.unwrap().peer_addr().unwrap(), addr);
+
+    // the last error is returned when every address fails.
+    let e = connect_addrs([closed(), closed()]).await.unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::ConnectionRefused);
+    let e = connect_addrs([]).await.unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::NotFound);
+}
@@ -0,0 +1,10 @@
+//! OpenSSL async job support.
+//!
+//! native-tls does not expose its `SSL` object, so `SSL_MODE_ASYNC` cannot be
+//! enabled for streams created by [`TlsConnector`](crate::TlsConnector) and
+//! [`TlsAcceptor`](crate::TlsAcceptor), and a handshake failing with
+//! `SSL_ERROR_WANT_ASYNC` could not be resumed since native-tls drops the
+//! stream. Use monoio-openssl, whose streams wait for the async jobs, or
+//! these helpers with code that builds the `SslContext` itself.
+
+pub use monoio_openssl::async_job::*;
@@ -1,11 +1,14 @@
 use std::fmt;
 
-use monoio::io::{AsyncReadRent, AsyncWriteRent};
-
-use crate::{
-    utils::{handshake, IOWrapper},
-    TlsError, TlsStream,
+use monoio::{
+    io::{AsyncReadRent, AsyncWriteRent},
+    net::TcpStream,
 };
+use monoio_io_wrapper::IOWrapper;
+
+#[cfg(feature = "unsafe_io")]
+use crate::utils::wrap_unsafe;
+use crate::{utils::handshake, TlsError, TlsStream};
 
 /// A wrapper around a `native_tls::TlsConnector`, providing an async `connect`
 /// method.
@@ -14,6 +17,8 @@ pub struct TlsConnector {
     inner: native_tls::TlsConnector,
     read_buffer: Option<usize>,
     write_buffer: Option<usize>,
+    #[cfg(feature = "unsafe_io")]
+    unsafe_io: bool,
 }
 
 impl TlsConnector {
@@ -33,10 +38,37 @@ impl TlsConnector {
     where
         S: AsyncReadRent + AsyncWriteRent,
     {
-        let io = IOWrapper::new_with_buffer_size(stream, self.read_buffer, self.write_buffer);
+        let io = self.wrap(stream);
         handshake(move |s_wrap| self.inner.connect(domain, s_wrap), io).await
     }
 
+    /// Resolve `host:port`, connect to it with Happy Eyeballs(RFC 8305) and
+    /// then do the handshake with the host as domain. IPv6 addresses must be
+    /// in brackets.
+    ///
+    /// See [`monoio_io_wrapper::connect_host`] for how the name is resolved
+    /// and the addresses are tried. The runtime must have timer enabled.
+    pub async fn connect_host(&self, addr: &str) -> Result<TlsStream<TcpStream>, TlsError> {
+        let (stream, host) = monoio_io_wrapper::connect_host(addr).await?;
+        self.connect(host, stream).await
+    }
+
+    /// Enable unsafe-io.
+    /// Once enabled, OpenSSL writes go directly to the socket and the write
+    /// buffer size is ignored. Reads are still buffered, since native-tls
+    /// does not allow keeping the OpenSSL read buffer alive during a pending
+    /// read; monoio-openssl can do both zero-copy.
+    /// # Safety
+    /// Users must make sure the buffer ptr and len is valid until io finished.
+    /// So the Future cannot be dropped directly. Consider using CancellableIO.
+    #[cfg(feature = "unsafe_io")]
+    pub unsafe fn unsafe_io(self, enabled: bool) -> Self {
+        Self {
+            unsafe_io: enabled,
+            ..self
+        }
+    }
+
     pub fn read_buffer(mut self, size: Option<usize>) -> Self {
         self.read_buffer = size;
         self
@@ -46,6 +78,16 @@ impl TlsConnector {
         self.write_buffer = size;
         self
     }
+
+    fn wrap<S>(&self, stream: S) -> IOWrapper<S> {
+        #[cfg(feature = "unsafe_io")]
+        if self.unsafe_io {
+            // # Safety
+            // Users already maked unsafe io.
+            return unsafe { wrap_unsafe(stream, self.read_buffer) };
+        }
+        IOWrapper::new_with_buffer_size(stream, self.read_buffer, self.write_buffer)
+    }
 }
 
 impl fmt::Debug for TlsConnector {
@@ -60,6 +102,8 @@ impl From<native_tls::TlsConnector> for TlsConnector {
             inner,
             read_buffer: None,
             write_buffer: None,
+            #[cfg(feature = "unsafe_io")]
+            unsafe_io: false,
         }
     }
 }
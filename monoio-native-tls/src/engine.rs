@@ -0,0 +1,194 @@
+use std::{
+    ffi::{c_uint, CString},
+    ops::BitOr,
+    sync::{Mutex, PoisonError},
+};
+
+use crate::{
+    ffi::{self, EngineFailure, EngineRef},
+    EngineError,
+};
+
+const LKCF_ENGINE: &str = "lkcf-engine";
+
+/// The engine registered by `init_with`, with the config it was loaded by.
+struct Registered {
+    config: EngineConfig,
+    active: ActiveEngine,
+    engine: EngineRef,
+}
+
+static REGISTERED: Mutex<Option<Registered>> = Mutex::new(None);
+
+/// Algorithm methods delegated to an engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EngineMethods(c_uint);
+
+impl EngineMethods {
+    pub const RSA: Self = Self(ffi::ENGINE_METHOD_RSA);
+    pub const DSA: Self = Self(ffi::ENGINE_METHOD_DSA);
+    pub const DH: Self = Self(ffi::ENGINE_METHOD_DH);
+    pub const RAND: Self = Self(ffi::ENGINE_METHOD_RAND);
+    pub const CIPHERS: Self = Self(ffi::ENGINE_METHOD_CIPHERS);
+    pub const DIGESTS: Self = Self(ffi::ENGINE_METHOD_DIGESTS);
+    pub const PKEY_METHS: Self = Self(ffi::ENGINE_METHOD_PKEY_METHS);
+    pub const PKEY_ASN1_METHS: Self = Self(ffi::ENGINE_METHOD_PKEY_ASN1_METHS);
+    pub const EC: Self = Self(ffi::ENGINE_METHOD_EC);
+    pub const ALL: Self = Self(ffi::ENGINE_METHOD_ALL);
+
+    #[inline]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for EngineMethods {
+    type Output = Self;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Configuration of the OpenSSL engine loaded by `init_with`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EngineConfig {
+    id: String,
+    methods: EngineMethods,
+    ctrl_cmds: Vec<(String, Option<String>)>,
+    fallback: bool,
+}
+
+impl Default for EngineConfig {
+    /// The `lkcf-engine` for all methods, falling back to software.
+    fn default() -> Self {
+        Self::new(LKCF_ENGINE)
+    }
+}
+
+impl EngineConfig {
+    /// Create a config of the engine with given id. By default all methods are
+    /// delegated to it, and it falls back to software if the engine is not
+    /// usable.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            methods: EngineMethods::ALL,
+            ctrl_cmds: Vec::new(),
+            fallback: true,
+        }
+    }
+
+    /// Set the algorithm methods delegated to the engine.
+    pub fn methods(mut self, methods: EngineMethods) -> Self {
+        self.methods = methods;
+        self
+    }
+
+    /// Add a control command. Commands are issued in order before the engine
+    /// is initialized.
+    pub fn ctrl_cmd(mut self, cmd: impl Into<String>, arg: Option<&str>) -> Self {
+        self.ctrl_cmds
+            .push((cmd.into(), arg.map(ToOwned::to_owned)));
+        self
+    }
+
+    /// Whether to fall back to software when the engine is not usable.
+    pub fn fallback_to_software(mut self, enabled: bool) -> Self {
+        self.fallback = enabled;
+        self
+    }
+}
+
+/// The crypto implementation in use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActiveEngine {
+    Engine { id: String, name: String },
+    Software,
+}
+
+/// Load and register an OpenSSL engine with the given config.
+///
+/// Calling it again with the same config returns the registered engine.
+/// With another config, the new engine replaces the registered one, which is
+/// unregistered from its methods and whose reference is released: methods the
+/// new config does not set are done in software again. If the new engine is
+/// not usable and falls back to software, the registered one is kept.
+pub fn init_with(config: EngineConfig) -> Result<ActiveEngine, EngineError> {
+    let mut registered = REGISTERED.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(current) = registered.as_ref().filter(|r| r.config == config) {
+        return Ok(current.active.clone());
+    }
+
+    let invalid = |e: std::ffi::NulError| EngineError::InvalidConfig(e.to_string());
+    let id = CString::new(config.id.as_str()).map_err(invalid)?;
+    let ctrl_cmds = config
+        .ctrl_cmds
+        .iter()
+        .map(|(cmd, arg)| {
+            let cmd = CString::new(cmd.as_str())?;
+            let arg = arg.as_deref().map(CString::new).transpose()?;
+            Ok((cmd, arg))
+        })
+        .collect::<Result<Vec<_>, std::ffi::NulError>>()
+        .map_err(invalid)?;
+    let ctrl_cmds_ref = ctrl_cmds
+        .iter()
+        .map(|(cmd, arg)| (cmd.as_c_str(), arg.as_deref()))
+        .collect::<Vec<_>>();
+
+    match ffi::load_engine(&id, &ctrl_cmds_ref, config.methods.0) {
+        Ok((engine, name)) => {
+            tracing::info!("engine: {} registered", config.id);
+            let active = ActiveEngine::Engine {
+                id: config.id.clone(),
+                name,
+            };
+            // the former engine stays the default of the methods the new
+            // config does not set, unless unregistered from them. The
+            // methods of the new config are kept if it is the same engine.
+            if let Some(former) = registered.as_ref() {
+                let mut methods = former.config.methods.0;
+                if former.engine == engine {
+                    methods &= !config.methods.0;
+                }
+                former.engine.unregister(methods);
+            }
+            // drops the reference to the former engine.
+            *registered = Some(Registered {
+                config,
+                active: active.clone(),
+                engine,
+            });
+            Ok(active)
+        }
+        Err(failure) => {
+            let err = match failure {
+                EngineFailure::NotFound => EngineError::NotFound(config.id),
+                EngineFailure::Ctrl(idx) => EngineError::Ctrl {
+                    id: config.id,
+                    cmd: config.ctrl_cmds[idx].0.clone(),
+                },
+                EngineFailure::Init => EngineError::Init(config.id),
+                EngineFailure::SetDefault => EngineError::SetDefault(config.id),
+            };
+            if !config.fallback {
+                return Err(err);
+            }
+            tracing::warn!("engine: {err}, fall back to software");
+            Ok(registered
+                .as_ref()
+                .map_or(ActiveEngine::Software, |r| r.active.clone()))
+        }
+    }
+}
+
+/// Report the crypto implementation registered by `init` or `init_with`.
+pub fn active_engine() -> ActiveEngine {
+    REGISTERED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .map_or(ActiveEngine::Software, |r| r.active.clone())
+}
@@ -14,7 +14,33 @@ impl From<TlsError> for io::Error {
     fn from(e: TlsError) -> Self {
         match e {
             TlsError::Io(e) => e,
-            TlsError::NativeTls(e) => io::Error::new(io::ErrorKind::Other, e),
+            TlsError::NativeTls(e) => io::Error::other(e),
         }
     }
 }
+
+#[cfg(feature = "qat")]
+#[derive(Error, Debug)]
+pub enum EngineError {
+    #[error("invalid engine config: {0}")]
+    InvalidConfig(String),
+    #[error("engine {0} not found")]
+    NotFound(String),
+    #[error("engine {id} rejected control command {cmd}")]
+    Ctrl { id: String, cmd: String },
+    #[error("engine {0} initialize failed")]
+    Init(String),
+    #[error("engine {0} cannot be set as default")]
+    SetDefault(String),
+}
+
+#[cfg(feature = "providers")]
+#[derive(Error, Debug)]
+pub enum ProviderError {
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("provider {0} load failed")]
+    Load(String),
+    #[error("invalid property query {0}")]
+    Properties(String),
+}
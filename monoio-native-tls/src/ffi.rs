@@ -1,30 +1,149 @@
-use openssl_sys::{c_char, c_int, ENGINE};
+use std::ffi::{c_char, c_int, c_uint, CStr};
+
+use openssl_sys::ENGINE;
+
+pub const ENGINE_METHOD_RSA: c_uint = 0x0001;
+pub const ENGINE_METHOD_DSA: c_uint = 0x0002;
+pub const ENGINE_METHOD_DH: c_uint = 0x0004;
+pub const ENGINE_METHOD_RAND: c_uint = 0x0008;
+pub const ENGINE_METHOD_CIPHERS: c_uint = 0x0040;
+pub const ENGINE_METHOD_DIGESTS: c_uint = 0x0080;
+pub const ENGINE_METHOD_PKEY_METHS: c_uint = 0x0200;
+pub const ENGINE_METHOD_PKEY_ASN1_METHS: c_uint = 0x0400;
+pub const ENGINE_METHOD_EC: c_uint = 0x0800;
+pub const ENGINE_METHOD_ALL: c_uint = 0xffff;
 
 extern "C" {
+    pub fn ENGINE_load_builtin_engines();
     pub fn ENGINE_init(engine: *mut ENGINE) -> c_int;
+    pub fn ENGINE_finish(engine: *mut ENGINE) -> c_int;
+    pub fn ENGINE_free(engine: *mut ENGINE) -> c_int;
     pub fn ENGINE_by_id(id: *const c_char) -> *mut ENGINE;
-    pub fn ENGINE_set_default(engine: *mut ENGINE, flags: c_int) -> c_int;
+    pub fn ENGINE_get_name(engine: *const ENGINE) -> *const c_char;
+    pub fn ENGINE_set_default(engine: *mut ENGINE, flags: c_uint) -> c_int;
+    pub fn ENGINE_unregister_RSA(engine: *mut ENGINE);
+    pub fn ENGINE_unregister_DSA(engine: *mut ENGINE);
+    pub fn ENGINE_unregister_DH(engine: *mut ENGINE);
+    pub fn ENGINE_unregister_RAND(engine: *mut ENGINE);
+    pub fn ENGINE_unregister_ciphers(engine: *mut ENGINE);
+    pub fn ENGINE_unregister_digests(engine: *mut ENGINE);
+    pub fn ENGINE_unregister_pkey_meths(engine: *mut ENGINE);
+    pub fn ENGINE_unregister_pkey_asn1_meths(engine: *mut ENGINE);
+    pub fn ENGINE_unregister_EC(engine: *mut ENGINE);
+    pub fn ENGINE_ctrl_cmd_string(
+        engine: *mut ENGINE,
+        cmd_name: *const c_char,
+        arg: *const c_char,
+        cmd_optional: c_int,
+    ) -> c_int;
 }
 
-pub fn init_openssl_engine(name: &std::ffi::CStr) {
+/// A functional reference to an engine, released on drop. References to the
+/// same engine are equal.
+#[derive(PartialEq, Eq)]
+pub struct EngineRef(*mut ENGINE);
+
+// the reference is only released once, ENGINE_finish is thread safe.
+unsafe impl Send for EngineRef {}
+
+impl EngineRef {
+    /// Stop using the engine for the given methods, for which it is no longer
+    /// the default either.
+    pub fn unregister(&self, methods: c_uint) {
+        let unregister: [(c_uint, unsafe extern "C" fn(*mut ENGINE)); 9] = [
+            (ENGINE_METHOD_RSA, ENGINE_unregister_RSA),
+            (ENGINE_METHOD_DSA, ENGINE_unregister_DSA),
+            (ENGINE_METHOD_DH, ENGINE_unregister_DH),
+            (ENGINE_METHOD_RAND, ENGINE_unregister_RAND),
+            (ENGINE_METHOD_CIPHERS, ENGINE_unregister_ciphers),
+            (ENGINE_METHOD_DIGESTS, ENGINE_unregister_digests),
+            (ENGINE_METHOD_PKEY_METHS, ENGINE_unregister_pkey_meths),
+            (
+                ENGINE_METHOD_PKEY_ASN1_METHS,
+                ENGINE_unregister_pkey_asn1_meths,
+            ),
+            (ENGINE_METHOD_EC, ENGINE_unregister_EC),
+        ];
+        for (method, f) in unregister {
+            if methods & method != 0 {
+                unsafe { f(self.0) };
+            }
+        }
+    }
+}
+
+impl Drop for EngineRef {
+    fn drop(&mut self) {
+        unsafe { ENGINE_finish(self.0) };
+    }
+}
+
+/// The reason why loading an engine failed.
+pub enum EngineFailure {
+    NotFound,
+    Ctrl(usize),
+    Init,
+    SetDefault,
+}
+
+/// Load the engine by id, apply control commands, initialize it and set it
+/// as the default implementation of the given methods.
+/// On success the functional reference and the engine name are returned.
+pub fn load_engine(
+    id: &CStr,
+    ctrl_cmds: &[(&CStr, Option<&CStr>)],
+    methods: c_uint,
+) -> Result<(EngineRef, String), EngineFailure> {
     openssl_sys::init();
 
+    let ret = unsafe { load_engine_inner(id, ctrl_cmds, methods) };
+    if ret.is_err() {
+        // do not leave the failure in the error queue of this thread, or it
+        // will be reported by the following tls operations.
+        unsafe { openssl_sys::ERR_clear_error() };
+    }
+    ret
+}
+
+unsafe fn load_engine_inner(
+    id: &CStr,
+    ctrl_cmds: &[(&CStr, Option<&CStr>)],
+    methods: c_uint,
+) -> Result<(EngineRef, String), EngineFailure> {
     unsafe {
-        let engine = ENGINE_by_id(name.as_ptr());
-        if engine as usize == 0 {
-            tracing::info!("engine: unknown");
-            return;
+        // builtin and dynamic engines must be loaded before being found by id.
+        ENGINE_load_builtin_engines();
+        let engine = ENGINE_by_id(id.as_ptr());
+        if engine.is_null() {
+            return Err(EngineFailure::NotFound);
+        }
+        // control commands are issued before init, which is what most engines
+        // expect for their configuration.
+        for (idx, (cmd, arg)) in ctrl_cmds.iter().enumerate() {
+            let arg = arg.map_or(std::ptr::null(), |a| a.as_ptr());
+            if ENGINE_ctrl_cmd_string(engine, cmd.as_ptr(), arg, 0) == 0 {
+                ENGINE_free(engine);
+                return Err(EngineFailure::Ctrl(idx));
+            }
         }
-        let rc = ENGINE_init(engine);
-        if rc == 0 {
-            tracing::error!("engine: initialize failed");
-            return;
+        if ENGINE_init(engine) == 0 {
+            ENGINE_free(engine);
+            return Err(EngineFailure::Init);
         }
-        tracing::info!("engine: {}", engine as usize);
-        let rc = ENGINE_set_default(engine, 0xffff);
-        if rc == 0 {
-            tracing::error!("engine: initialize failed");
+        if ENGINE_set_default(engine, methods) == 0 {
+            ENGINE_finish(engine);
+            ENGINE_free(engine);
+            return Err(EngineFailure::SetDefault);
         }
-        tracing::info!("engine: register successfully");
+        let name = ENGINE_get_name(engine);
+        let name = if name.is_null() {
+            id.to_string_lossy().into_owned()
+        } else {
+            CStr::from_ptr(name).to_string_lossy().into_owned()
+        };
+        // the structural reference is no longer needed, the functional one
+        // keeps the engine alive.
+        ENGINE_free(engine);
+        Ok((EngineRef(engine), name))
     }
 }
@@ -1,27 +1,47 @@
 mod client;
 mod error;
+mod reload;
 mod server;
 mod stream;
+#[cfg(feature = "poll-io")]
+mod stream_poll;
 mod utils;
 
 pub use client::TlsConnector;
 pub use error::TlsError;
+pub use monoio_io_wrapper::Buffers;
+pub use reload::{IdentityFiles, ReloadableTlsAcceptor};
 pub use server::TlsAcceptor;
 pub use stream::TlsStream;
 
+#[cfg(feature = "async-job")]
+pub mod async_job;
+#[cfg(feature = "qat")]
+mod engine;
 #[cfg(feature = "qat")]
 mod ffi;
+#[cfg(feature = "providers")]
+mod provider;
 
+#[cfg(feature = "qat")]
+pub use engine::{active_engine, init_with, ActiveEngine, EngineConfig, EngineMethods};
+#[cfg(feature = "qat")]
+pub use error::EngineError;
+#[cfg(feature = "providers")]
+pub use error::ProviderError;
+#[cfg(feature = "providers")]
+pub use provider::{LibContext, Provider};
+
+/// Register the default engine(`lkcf-engine` for all methods) once, falling
+/// back to software if it is not usable. Use `init_with` for more control.
 pub fn init() {
     #[cfg(feature = "qat")]
     static INIT_ONCE: std::sync::Once = std::sync::Once::new();
-    #[cfg(feature = "qat")]
-    const LKCF_ENGINE: &[u8] = b"lkcf-engine\0";
 
     #[cfg(feature = "qat")]
     INIT_ONCE.call_once(|| {
-        ffi::init_openssl_engine(unsafe {
-            std::ffi::CStr::from_bytes_with_nul_unchecked(LKCF_ENGINE)
-        })
+        if let Err(e) = init_with(EngineConfig::default()) {
+            tracing::error!("engine: {e}");
+        }
     });
 }
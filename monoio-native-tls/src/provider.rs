@@ -0,0 +1,122 @@
+use std::{
+    ffi::{c_char, c_int, CString},
+    ptr,
+};
+
+use crate::ProviderError;
+
+#[allow(non_camel_case_types)]
+enum OSSL_LIB_CTX {}
+#[allow(non_camel_case_types)]
+enum OSSL_PROVIDER {}
+#[allow(non_camel_case_types)]
+enum EVP_MD {}
+
+extern "C" {
+    fn OSSL_PROVIDER_load(ctx: *mut OSSL_LIB_CTX, name: *const c_char) -> *mut OSSL_PROVIDER;
+    fn OSSL_PROVIDER_unload(prov: *mut OSSL_PROVIDER) -> c_int;
+    fn OSSL_PROVIDER_available(ctx: *mut OSSL_LIB_CTX, name: *const c_char) -> c_int;
+    fn EVP_set_default_properties(ctx: *mut OSSL_LIB_CTX, propq: *const c_char) -> c_int;
+    fn EVP_MD_fetch(
+        ctx: *mut OSSL_LIB_CTX,
+        algorithm: *const c_char,
+        properties: *const c_char,
+    ) -> *mut EVP_MD;
+    fn EVP_MD_free(md: *mut EVP_MD);
+}
+
+/// The OpenSSL 3 default library context, which native-tls uses and
+/// providers are loaded into.
+///
+/// Note that once any provider is loaded explicitly, OpenSSL no longer loads
+/// the `default` provider automatically. Load it too unless the other
+/// providers cover all the algorithms in use.
+#[derive(Clone, Copy)]
+pub struct LibContext(());
+
+impl LibContext {
+    /// The default library context.
+    pub fn global() -> Self {
+        openssl_sys::init();
+        Self(())
+    }
+
+    /// Load the provider by name. It is unloaded when the returned `Provider`
+    /// is dropped.
+    pub fn load_provider(&self, name: &str) -> Result<Provider, ProviderError> {
+        let c_name = c_string(name)?;
+        let prov = unsafe { OSSL_PROVIDER_load(ptr::null_mut(), c_name.as_ptr()) };
+        if prov.is_null() {
+            unsafe { openssl_sys::ERR_clear_error() };
+            return Err(ProviderError::Load(name.to_owned()));
+        }
+        Ok(Provider {
+            ptr: prov,
+            name: name.to_owned(),
+        })
+    }
+
+    /// Set the default property query used when fetching algorithms, e.g.
+    /// `provider=qatprovider` or `?provider=qatprovider` to prefer it.
+    pub fn set_default_properties(&self, query: &str) -> Result<(), ProviderError> {
+        let c_query = c_string(query)?;
+        if unsafe { EVP_set_default_properties(ptr::null_mut(), c_query.as_ptr()) } == 0 {
+            unsafe { openssl_sys::ERR_clear_error() };
+            return Err(ProviderError::Properties(query.to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Whether the provider is loaded and available.
+    pub fn is_provider_available(&self, name: &str) -> bool {
+        match CString::new(name) {
+            Ok(c_name) => unsafe { OSSL_PROVIDER_available(ptr::null_mut(), c_name.as_ptr()) == 1 },
+            Err(_) => false,
+        }
+    }
+
+    /// Whether the digest can be fetched with the given property query(or
+    /// the default one if None).
+    pub fn is_digest_available(&self, algorithm: &str, properties: Option<&str>) -> bool {
+        let (Ok(c_algorithm), Ok(c_properties)) = (
+            CString::new(algorithm),
+            properties.map(CString::new).transpose(),
+        ) else {
+            return false;
+        };
+        let properties = c_properties.as_ref().map_or(ptr::null(), |p| p.as_ptr());
+        let md = unsafe { EVP_MD_fetch(ptr::null_mut(), c_algorithm.as_ptr(), properties) };
+        if md.is_null() {
+            unsafe { openssl_sys::ERR_clear_error() };
+            return false;
+        }
+        unsafe { EVP_MD_free(md) };
+        true
+    }
+}
+
+/// A loaded provider.
+pub struct Provider {
+    ptr: *mut OSSL_PROVIDER,
+    name: String,
+}
+
+// OSSL_PROVIDER is thread safe.
+unsafe impl Send for Provider {}
+unsafe impl Sync for Provider {}
+
+impl Provider {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for Provider {
+    fn drop(&mut self) {
+        unsafe { OSSL_PROVIDER_unload(self.ptr) };
+    }
+}
+
+fn c_string(s: &str) -> Result<CString, ProviderError> {
+    CString::new(s).map_err(|e| ProviderError::InvalidArgument(e.to_string()))
+}
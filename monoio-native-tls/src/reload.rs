@@ -0,0 +1,172 @@
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use monoio::io::{AsyncReadRent, AsyncWriteRent};
+use native_tls::{Identity, TlsAcceptorBuilder};
+
+use crate::{TlsAcceptor, TlsError, TlsStream};
+
+type Configure = dyn Fn(&mut TlsAcceptorBuilder) + Send + Sync;
+
+/// A `TlsAcceptor` handle whose identity can be replaced at runtime.
+///
+/// Clones share the identity, so a `reload` is seen by every accept loop.
+/// New handshakes use the latest identity, connections already accepted or
+/// in the middle of a handshake keep the old one. The buffer sizes are set
+/// per clone.
+#[derive(Clone)]
+pub struct ReloadableTlsAcceptor {
+    current: Arc<RwLock<native_tls::TlsAcceptor>>,
+    configure: Arc<Configure>,
+    read_buffer: Option<usize>,
+    write_buffer: Option<usize>,
+    #[cfg(feature = "unsafe_io")]
+    unsafe_io: bool,
+}
+
+impl ReloadableTlsAcceptor {
+    /// Create with the default `native_tls::TlsAcceptorBuilder` settings.
+    pub fn new(identity: Identity) -> Result<Self, TlsError> {
+        Self::with_builder(identity, |_| {})
+    }
+
+    /// Create with a function configuring the builder, e.g. protocol versions
+    /// or ALPN. It is applied again on every reload.
+    pub fn with_builder<F>(identity: Identity, configure: F) -> Result<Self, TlsError>
+    where
+        F: Fn(&mut TlsAcceptorBuilder) + Send + Sync + 'static,
+    {
+        let mut builder = native_tls::TlsAcceptor::builder(identity);
+        configure(&mut builder);
+        Ok(Self {
+            current: Arc::new(RwLock::new(builder.build()?)),
+            configure: Arc::new(configure),
+            read_buffer: None,
+            write_buffer: None,
+            #[cfg(feature = "unsafe_io")]
+            unsafe_io: false,
+        })
+    }
+
+    /// Replace the identity used by new handshakes.
+    /// On error the current identity is kept.
+    pub fn reload(&self, identity: Identity) -> Result<(), TlsError> {
+        let mut builder = native_tls::TlsAcceptor::builder(identity);
+        (self.configure)(&mut builder);
+        let inner = builder.build()?;
+
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = inner;
+        Ok(())
+    }
+
+    /// Returns the acceptor used by new handshakes.
+    pub fn current(&self) -> TlsAcceptor {
+        let inner = self
+            .current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let acceptor = TlsAcceptor::from(inner)
+            .read_buffer(self.read_buffer)
+            .write_buffer(self.write_buffer);
+        // # Safety
+        // Users already maked unsafe io.
+        #[cfg(feature = "unsafe_io")]
+        let acceptor = unsafe { acceptor.unsafe_io(self.unsafe_io) };
+        acceptor
+    }
+
+    /// Accepts a new client connection with the current identity.
+    pub async fn accept<S>(&self, stream: S) -> Result<TlsStream<S>, TlsError>
+    where
+        S: AsyncReadRent + AsyncWriteRent,
+    {
+        self.current().accept(stream).await
+    }
+
+    /// Reload the identity whenever the files change.
+    ///
+    /// Files are checked every `interval` by their modification time. If
+    /// loading fails(e.g. only the certificate has been replaced so far), the
+    /// current identity is kept and it is retried on the next check. The
+    /// returned future never completes, spawn it on a runtime with timer
+    /// enabled.
+    pub async fn watch(self, files: IdentityFiles, interval: Duration) {
+        let mut loaded = files.modified();
+        loop {
+            monoio::time::sleep(interval).await;
+            let modified = files.modified();
+            if modified == loaded {
+                continue;
+            }
+            if files.load().and_then(|id| self.reload(id)).is_ok() {
+                loaded = modified;
+            }
+        }
+    }
+
+    /// Enable unsafe-io for all handshakes, see [`TlsAcceptor::unsafe_io`].
+    /// # Safety
+    /// Users must make sure the buffer ptr and len is valid until io finished.
+    /// So the Future cannot be dropped directly. Consider using CancellableIO.
+    #[cfg(feature = "unsafe_io")]
+    pub unsafe fn unsafe_io(mut self, enabled: bool) -> Self {
+        self.unsafe_io = enabled;
+        self
+    }
+
+    pub fn read_buffer(mut self, size: Option<usize>) -> Self {
+        self.read_buffer = size;
+        self
+    }
+
+    pub fn write_buffer(mut self, size: Option<usize>) -> Self {
+        self.write_buffer = size;
+        self
+    }
+}
+
+impl fmt::Debug for ReloadableTlsAcceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReloadableTlsAcceptor").finish()
+    }
+}
+
+/// Files an `Identity` is loaded from.
+#[derive(Debug, Clone)]
+pub enum IdentityFiles {
+    /// A DER-formatted PKCS #12 archive and its password.
+    Pkcs12 { path: PathBuf, password: String },
+    /// A PEM-encoded certificate chain and a PEM-encoded PKCS #8 private key.
+    Pem { cert: PathBuf, key: PathBuf },
+}
+
+impl IdentityFiles {
+    /// Read the files and parse the identity.
+    pub fn load(&self) -> Result<Identity, TlsError> {
+        let identity = match self {
+            IdentityFiles::Pkcs12 { path, password } => {
+                Identity::from_pkcs12(&std::fs::read(path)?, password)?
+            }
+            IdentityFiles::Pem { cert, key } => {
+                Identity::from_pkcs8(&std::fs::read(cert)?, &std::fs::read(key)?)?
+            }
+        };
+        Ok(identity)
+    }
+
+    fn modified(&self) -> Vec<Option<SystemTime>> {
+        let paths = match self {
+            IdentityFiles::Pkcs12 { path, .. } => vec![path],
+            IdentityFiles::Pem { cert, key } => vec![cert, key],
+        };
+        paths
+            .into_iter()
+            .map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+            .collect()
+    }
+}
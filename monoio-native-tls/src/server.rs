@@ -1,11 +1,11 @@
 use std::fmt;
 
 use monoio::io::{AsyncReadRent, AsyncWriteRent};
+use monoio_io_wrapper::IOWrapper;
 
-use crate::{
-    utils::{handshake, IOWrapper},
-    TlsError, TlsStream,
-};
+#[cfg(feature = "unsafe_io")]
+use crate::utils::wrap_unsafe;
+use crate::{utils::handshake, TlsError, TlsStream};
 
 /// A wrapper around a `native_tls::TlsAcceptor`, providing an async `accept`
 /// method.
@@ -14,6 +14,8 @@ pub struct TlsAcceptor {
     inner: native_tls::TlsAcceptor,
     read_buffer: Option<usize>,
     write_buffer: Option<usize>,
+    #[cfg(feature = "unsafe_io")]
+    unsafe_io: bool,
 }
 
 impl TlsAcceptor {
@@ -31,10 +33,26 @@ impl TlsAcceptor {
     where
         S: AsyncReadRent + AsyncWriteRent,
     {
-        let io = IOWrapper::new_with_buffer_size(stream, self.read_buffer, self.write_buffer);
+        let io = self.wrap(stream);
         handshake(move |s_wrap| self.inner.accept(s_wrap), io).await
     }
 
+    /// Enable unsafe-io.
+    /// Once enabled, OpenSSL writes go directly to the socket and the write
+    /// buffer size is ignored. Reads are still buffered, since native-tls
+    /// does not allow keeping the OpenSSL read buffer alive during a pending
+    /// read; monoio-openssl can do both zero-copy.
+    /// # Safety
+    /// Users must make sure the buffer ptr and len is valid until io finished.
+    /// So the Future cannot be dropped directly. Consider using CancellableIO.
+    #[cfg(feature = "unsafe_io")]
+    pub unsafe fn unsafe_io(self, enabled: bool) -> Self {
+        Self {
+            unsafe_io: enabled,
+            ..self
+        }
+    }
+
     pub fn read_buffer(mut self, size: Option<usize>) -> Self {
         self.read_buffer = size;
         self
@@ -44,6 +62,16 @@ impl TlsAcceptor {
         self.write_buffer = size;
         self
     }
+
+    fn wrap<S>(&self, stream: S) -> IOWrapper<S> {
+        #[cfg(feature = "unsafe_io")]
+        if self.unsafe_io {
+            // # Safety
+            // Users already maked unsafe io.
+            return unsafe { wrap_unsafe(stream, self.read_buffer) };
+        }
+        IOWrapper::new_with_buffer_size(stream, self.read_buffer, self.write_buffer)
+    }
 }
 
 impl fmt::Debug for TlsAcceptor {
@@ -58,6 +86,8 @@ impl From<native_tls::TlsAcceptor> for TlsAcceptor {
             inner,
             read_buffer: None,
             write_buffer: None,
+            #[cfg(feature = "unsafe_io")]
+            unsafe_io: false,
         }
     }
 }
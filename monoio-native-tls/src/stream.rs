@@ -5,8 +5,7 @@ use monoio::{
     io::{AsyncReadRent, AsyncWriteRent, Split},
     BufResult,
 };
-
-use crate::utils::{Buffers, IOWrapper};
+use monoio_io_wrapper::{Buffers, IOWrapper};
 
 /// A wrapper around an underlying raw stream which implements the TLS or SSL
 /// protocol.
@@ -17,8 +16,8 @@ use crate::utils::{Buffers, IOWrapper};
 /// to a `TlsStream` are encrypted when passing through to `S`.
 #[derive(Debug)]
 pub struct TlsStream<S> {
-    tls: native_tls::TlsStream<Buffers>,
-    io: IOWrapper<S>,
+    pub(crate) tls: native_tls::TlsStream<Buffers>,
+    pub(crate) io: IOWrapper<S>,
 }
 
 impl<S> TlsStream<S> {
@@ -37,6 +36,22 @@ impl<S> TlsStream<S> {
     pub fn alpn_protocol(&self) -> Option<Vec<u8>> {
         self.tls.negotiated_alpn().ok().flatten()
     }
+
+    /// Returns the certificate of the peer, if available.
+    pub fn peer_certificate(&self) -> Result<Option<native_tls::Certificate>, native_tls::Error> {
+        self.tls.peer_certificate()
+    }
+
+    /// Returns the tls-server-end-point channel binding data as defined in
+    /// [RFC 5929](https://tools.ietf.org/html/rfc5929).
+    pub fn tls_server_end_point(&self) -> Result<Option<Vec<u8>>, native_tls::Error> {
+        self.tls.tls_server_end_point()
+    }
+
+    /// Returns a reference to the inner native-tls stream.
+    pub fn get_ref(&self) -> &native_tls::TlsStream<Buffers> {
+        &self.tls
+    }
 }
 
 unsafe impl<S: Split> Split for TlsStream<S> {}
@@ -125,7 +140,7 @@ impl<S: AsyncWriteRent> AsyncWriteRent for TlsStream<S> {
             match self.tls.flush() {
                 Ok(_) => {
                     unsafe { self.io.do_write_io() }.await?;
-                    return Ok(());
+                    return self.io.flush_io().await;
                 }
                 Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
                     unsafe { self.io.do_write_io() }.await?;
@@ -138,8 +153,21 @@ impl<S: AsyncWriteRent> AsyncWriteRent for TlsStream<S> {
     }
 
     async fn shutdown(&mut self) -> io::Result<()> {
-        self.tls.shutdown()?;
+        // send close_notify, we do not wait for the one from peer.
+        loop {
+            match self.tls.shutdown() {
+                Ok(_) => break,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    // nothing to write means close_notify has been sent and
+                    // it is waiting for the peer's one.
+                    if unsafe { self.io.do_write_io() }.await? == 0 {
+                        break;
+                    }
+                }
+                Err(e) => return Err(e),
+            }
+        }
         unsafe { self.io.do_write_io() }.await?;
-        Ok(())
+        self.io.shutdown_io().await
     }
 }
@@ -0,0 +1,131 @@
+//! Poll-io style interface for TlsStream.
+//! The TlsStream keeps its own read and write buffers, so it does not need the
+//! ownership passing workaround of monoio-compat.
+
+use std::{
+    io::{self, Read, Write},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use monoio::io::{
+    poll_io::{AsyncRead, AsyncWrite, ReadBuf},
+    IntoCompIo, IntoPollIo,
+};
+
+use crate::TlsStream;
+
+impl<S: IntoPollIo> IntoPollIo for TlsStream<S> {
+    type PollIo = TlsStream<S::PollIo>;
+
+    fn try_into_poll_io(self) -> Result<Self::PollIo, (io::Error, Self)> {
+        let TlsStream { tls, io } = self;
+        match io.try_into_poll_io() {
+            Ok(io) => Ok(TlsStream::new(tls, io)),
+            Err((e, io)) => Err((e, TlsStream::new(tls, io))),
+        }
+    }
+}
+
+impl<S: IntoCompIo> IntoCompIo for TlsStream<S> {
+    type CompIo = TlsStream<S::CompIo>;
+
+    fn try_into_comp_io(self) -> Result<Self::CompIo, (io::Error, Self)> {
+        let TlsStream { tls, io } = self;
+        match io.try_into_comp_io() {
+            Ok(io) => Ok(TlsStream::new(tls, io)),
+            Err((e, io)) => Err((e, TlsStream::new(tls, io))),
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for TlsStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            // read from native-tls to buffer
+            match this.tls.read(buf.initialize_unfilled()) {
+                Ok(n) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                // we need more data, read something.
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => (),
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+
+            // now we need data, read something into native-tls
+            if ready!(this.io.poll_read_io(cx))? == 0 {
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for TlsStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            // write slice to native-tls and buffer
+            match this.tls.write(buf) {
+                Ok(n) => {
+                    // the data is already buffered, so pending here does not lose it.
+                    if let Poll::Ready(Err(e)) = this.io.poll_write_io(cx) {
+                        return Poll::Ready(Err(e));
+                    }
+                    return Poll::Ready(Ok(n));
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    // nothing to write means openssl wants to read something(e.g.
+                    // renegotiation), which can never come after eof.
+                    if ready!(this.io.poll_write_io(cx))? == 0
+                        && ready!(this.io.poll_read_io(cx))? == 0
+                    {
+                        return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+                    }
+                }
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            match this.tls.flush() {
+                Ok(_) => return this.io.poll_flush_io(cx),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    ready!(this.io.poll_write_io(cx))?;
+                }
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        // send close_notify, we do not wait for the one from peer.
+        loop {
+            match this.tls.shutdown() {
+                Ok(_) => break,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    // nothing to write means close_notify has been sent and
+                    // it is waiting for the peer's one.
+                    if ready!(this.io.poll_write_io(cx))? == 0 {
+                        break;
+                    }
+                }
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+        this.io.poll_shutdown_io(cx)
+    }
+}
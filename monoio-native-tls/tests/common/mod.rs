@@ -0,0 +1,84 @@
+#![allow(dead_code)]
+
+use std::sync::OnceLock;
+
+use monoio::net::{TcpListener, TcpStream};
+use monoio_native_tls::{TlsAcceptor, TlsConnector, TlsStream};
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+
+/// A CA and a `localhost` server certificate issued by it, in PEM.
+pub struct Pki {
+    pub ca: String,
+    pub cert: String,
+    pub key: String,
+}
+
+pub fn pki() -> &'static Pki {
+    static PKI: OnceLock<Pki> = OnceLock::new();
+    PKI.get_or_init(|| generate("localhost"))
+}
+
+/// Generate a new CA and a leaf certificate for `name`.
+pub fn generate(name: &str) -> Pki {
+    let ca_key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "monoio-tls test CA");
+    let ca = params.self_signed(&ca_key).unwrap();
+
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+    params.distinguished_name.push(DnType::CommonName, name);
+    let cert = params.signed_by(&key, &ca, &ca_key).unwrap();
+    Pki {
+        ca: ca.pem(),
+        cert: cert.pem(),
+        key: key.serialize_pem(),
+    }
+}
+
+pub fn identity(pki: &Pki) -> native_tls::Identity {
+    native_tls::Identity::from_pkcs8(pki.cert.as_bytes(), pki.key.as_bytes()).unwrap()
+}
+
+pub fn acceptor() -> TlsAcceptor {
+    native_tls::TlsAcceptor::new(identity(pki()))
+        .unwrap()
+        .into()
+}
+
+pub fn connector() -> TlsConnector {
+    connector_for(pki())
+}
+
+pub fn connector_for(pki: &Pki) -> TlsConnector {
+    native_tls::TlsConnector::builder()
+        .add_root_certificate(native_tls::Certificate::from_pem(pki.ca.as_bytes()).unwrap())
+        .disable_built_in_roots(true)
+        .build()
+        .unwrap()
+        .into()
+}
+
+/// Returns both ends of a loopback tcp connection.
+pub async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, server) = monoio::join!(TcpStream::connect(addr), listener.accept());
+    (client.unwrap(), server.unwrap().0)
+}
+
+/// Returns both ends of a tls connection over loopback tcp.
+pub async fn tls_pair(
+    connector: &TlsConnector,
+    acceptor: &TlsAcceptor,
+) -> (TlsStream<TcpStream>, TlsStream<TcpStream>) {
+    let (client, server) = tcp_pair().await;
+    let (client, server) = monoio::join!(
+        connector.connect("localhost", client),
+        acceptor.accept(server)
+    );
+    (client.unwrap(), server.unwrap())
+}
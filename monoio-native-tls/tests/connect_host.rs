@@ -0,0 +1,36 @@
+mod common;
+
+use std::io;
+
+use monoio_native_tls::TlsError;
+
+// resolving `localhost` with the `sync` feature needs a thread pool.
+#[cfg(not(feature = "sync"))]
+#[monoio::test(timer_enabled = true)]
+async fn connect_host() {
+    use monoio::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let acceptor = common::acceptor();
+    let connector = common::connector();
+    let addr = format!("localhost:{port}");
+    let server = async {
+        let (stream, _) = listener.accept().await.unwrap();
+        acceptor.accept(stream).await.unwrap()
+    };
+    let (client, _server) = monoio::join!(connector.connect_host(&addr), server);
+    client.unwrap();
+}
+
+#[monoio::test]
+async fn invalid_addr() {
+    let connector = common::connector();
+    for addr in ["localhost", "[::1]443"] {
+        let e = connector.connect_host(addr).await.unwrap_err();
+        assert!(
+            matches!(&e, TlsError::Io(e) if e.kind() == io::ErrorKind::InvalidInput),
+            "{addr}: {e:?}"
+        );
+    }
+}
@@ -0,0 +1,95 @@
+#![cfg(feature = "qat")]
+
+use std::{
+    ffi::c_void,
+    sync::{Mutex, PoisonError},
+};
+
+use monoio_native_tls::{
+    active_engine, init_with, ActiveEngine, EngineConfig, EngineError, EngineMethods,
+};
+
+extern "C" {
+    fn ENGINE_get_default_RAND() -> *mut c_void;
+    fn ENGINE_finish(engine: *mut c_void) -> i32;
+}
+
+/// The registered engine is global, so the tests do not run concurrently.
+static ENGINE: Mutex<()> = Mutex::new(());
+
+/// Whether an engine is the default for random numbers.
+fn rand_engine() -> bool {
+    let engine = unsafe { ENGINE_get_default_RAND() };
+    if engine.is_null() {
+        return false;
+    }
+    unsafe { ENGINE_finish(engine) };
+    true
+}
+
+#[test]
+fn init_with_engines() {
+    let _guard = ENGINE.lock().unwrap_or_else(PoisonError::into_inner);
+    // rdrand_engine may have registered one before.
+    let before = active_engine();
+
+    // unknown engines.
+    let missing = EngineConfig::new("no-such-engine");
+    assert_eq!(init_with(missing.clone()).unwrap(), before);
+    assert!(matches!(
+        init_with(missing.fallback_to_software(false)),
+        Err(EngineError::NotFound(id)) if id == "no-such-engine"
+    ));
+    assert!(matches!(
+        init_with(EngineConfig::new("nul\0engine")),
+        Err(EngineError::InvalidConfig(_))
+    ));
+
+    // the dynamic engine rejects unknown commands.
+    let bogus = EngineConfig::new("dynamic")
+        .ctrl_cmd("NO_SUCH_CMD", Some("1"))
+        .fallback_to_software(false);
+    assert!(matches!(
+        init_with(bogus),
+        Err(EngineError::Ctrl { id, cmd }) if id == "dynamic" && cmd == "NO_SUCH_CMD"
+    ));
+    assert_eq!(active_engine(), before);
+}
+
+#[test]
+#[ignore = "needs the rdrand engine of OpenSSL and a CPU with RDRAND"]
+fn rdrand_engine() {
+    let _guard = ENGINE.lock().unwrap_or_else(PoisonError::into_inner);
+    let rdrand = EngineConfig::new("rdrand")
+        .methods(EngineMethods::RAND)
+        .fallback_to_software(false);
+    let active = init_with(rdrand.clone()).unwrap();
+    assert!(matches!(&active, ActiveEngine::Engine { id, .. } if id == "rdrand"));
+    assert_eq!(active_engine(), active);
+    assert!(rand_engine());
+    // the same config returns the registered engine.
+    assert_eq!(init_with(rdrand.clone()).unwrap(), active);
+
+    // a failing engine with fallback keeps the registered one.
+    assert_eq!(
+        init_with(EngineConfig::new("no-such-engine")).unwrap(),
+        active
+    );
+    assert_eq!(active_engine(), active);
+    assert!(rand_engine());
+
+    // another config replaces it.
+    let all = EngineConfig::new("rdrand").fallback_to_software(false);
+    assert_eq!(init_with(all).unwrap(), active);
+    assert_eq!(init_with(rdrand).unwrap(), active);
+    assert_eq!(active_engine(), active);
+    assert!(rand_engine());
+
+    // random numbers are generated in software again once the new config no
+    // longer sets them.
+    let digests = EngineConfig::new("rdrand")
+        .methods(EngineMethods::DIGESTS)
+        .fallback_to_software(false);
+    assert_eq!(init_with(digests).unwrap(), active);
+    assert!(!rand_engine());
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use monoio::io::{AsyncReadRentExt, AsyncWriteRentExt};
+
+#[monoio::test]
+async fn peer_certificate() {
+    let (client, server) = common::tls_pair(&common::connector(), &common::acceptor()).await;
+
+    let cert = client.peer_certificate().unwrap().unwrap();
+    let expected = native_tls::Certificate::from_pem(common::pki().cert.as_bytes()).unwrap();
+    assert_eq!(cert.to_der().unwrap(), expected.to_der().unwrap());
+    // the client sends no certificate.
+    assert!(server.peer_certificate().unwrap().is_none());
+}
+
+#[monoio::test]
+async fn tls_server_end_point() {
+    let (client, server) = common::tls_pair(&common::connector(), &common::acceptor()).await;
+
+    // both sides bind to the certificate of the server, hashed with SHA-256
+    // as the signature of an ECDSA P-256 certificate uses it.
+    let binding = client.tls_server_end_point().unwrap().unwrap();
+    let cert = native_tls::Certificate::from_pem(common::pki().cert.as_bytes()).unwrap();
+    assert_eq!(binding, openssl::sha::sha256(&cert.to_der().unwrap()));
+    assert_eq!(server.tls_server_end_point().unwrap(), Some(binding));
+}
+
+#[monoio::test]
+async fn get_ref() {
+    let (mut client, mut server) =
+        common::tls_pair(&common::connector(), &common::acceptor()).await;
+    assert_eq!(
+        client
+            .get_ref()
+            .peer_certificate()
+            .unwrap()
+            .unwrap()
+            .to_der()
+            .unwrap(),
+        client
+            .peer_certificate()
+            .unwrap()
+            .unwrap()
+            .to_der()
+            .unwrap()
+    );
+
+    // the inner stream is only borrowed, the stream still works.
+    let (res, _) = client.write_all(b"ping").await;
+    res.unwrap();
+    let (res, buf) = server.read_exact(vec![0; 4]).await;
+    res.unwrap();
+    assert_eq!(buf, b"ping");
+}
+
+#[cfg(feature = "alpn")]
+#[monoio::test]
+async fn alpn_protocol() {
+    let connector = native_tls::TlsConnector::builder()
+        .add_root_certificate(
+            native_tls::Certificate::from_pem(common::pki().ca.as_bytes()).unwrap(),
+        )
+        .request_alpns(&["h2"])
+        .build()
+        .unwrap()
+        .into();
+    let (client, _server) = common::tls_pair(&connector, &common::acceptor()).await;
+    // native-tls cannot select protocols on the server side.
+    assert_eq!(client.alpn_protocol(), None);
+}
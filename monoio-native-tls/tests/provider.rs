@@ -0,0 +1,59 @@
+#![cfg(feature = "providers")]
+
+use monoio_native_tls::{LibContext, ProviderError};
+
+// Providers are loaded into the process wide context, so the cases run in
+// order in one test.
+#[test]
+fn load_providers() {
+    let ctx = LibContext::global();
+
+    let default = ctx.load_provider("default").unwrap();
+    assert_eq!(default.name(), "default");
+    assert!(ctx.is_provider_available("default"));
+    assert!(ctx.is_digest_available("SHA2-256", None));
+    assert!(ctx.is_digest_available("SHA2-256", Some("provider=default")));
+
+    // md4 only lives in the legacy provider.
+    assert!(!ctx.is_digest_available("MD4", Some("provider=legacy")));
+    let legacy = ctx.load_provider("legacy").unwrap();
+    assert!(ctx.is_provider_available("legacy"));
+    assert!(ctx.is_digest_available("MD4", Some("provider=legacy")));
+    assert!(!ctx.is_digest_available("SHA2-256", Some("provider=legacy")));
+
+    // the null provider offers nothing.
+    let null = ctx.load_provider("null").unwrap();
+    assert!(ctx.is_provider_available("null"));
+    assert!(!ctx.is_digest_available("SHA2-256", Some("provider=null")));
+
+    // default properties apply to fetches without a query.
+    ctx.set_default_properties("provider=legacy").unwrap();
+    assert!(!ctx.is_digest_available("SHA2-256", None));
+    ctx.set_default_properties("?provider=legacy").unwrap();
+    assert!(ctx.is_digest_available("SHA2-256", None));
+    ctx.set_default_properties("").unwrap();
+
+    // unloaded on drop.
+    drop(legacy);
+    assert!(!ctx.is_provider_available("legacy"));
+    assert!(!ctx.is_digest_available("MD4", None));
+    drop(null);
+    assert!(!ctx.is_provider_available("null"));
+    drop(default);
+
+    // errors.
+    assert!(matches!(
+        ctx.load_provider("no-such-provider"),
+        Err(ProviderError::Load(name)) if name == "no-such-provider"
+    ));
+    assert!(matches!(
+        ctx.load_provider("nul\0provider"),
+        Err(ProviderError::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        ctx.set_default_properties("provider=="),
+        Err(ProviderError::Properties(_))
+    ));
+    assert!(!ctx.is_provider_available("nul\0provider"));
+    assert!(!ctx.is_digest_available("nul\0md", None));
+}
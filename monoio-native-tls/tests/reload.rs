@@ -0,0 +1,185 @@
+mod common;
+
+use std::path::PathBuf;
+
+use monoio::io::{AsyncReadRent, AsyncWriteRentExt};
+use monoio_native_tls::{IdentityFiles, ReloadableTlsAcceptor, TlsConnector};
+use openssl::{pkcs12::Pkcs12, pkey::PKey, x509::X509};
+
+async fn peer_cert(connector: &TlsConnector, acceptor: &ReloadableTlsAcceptor) -> Option<Vec<u8>> {
+    let (client, server) = common::tcp_pair().await;
+    let (client, server) = monoio::join!(
+        connector.connect("localhost", client),
+        acceptor.accept(server)
+    );
+    let (client, _server) = (client.ok()?, server.ok()?);
+    Some(
+        client
+            .peer_certificate()
+            .unwrap()
+            .unwrap()
+            .to_der()
+            .unwrap(),
+    )
+}
+
+fn der(pem: &str) -> Vec<u8> {
+    X509::from_pem(pem.as_bytes()).unwrap().to_der().unwrap()
+}
+
+/// A fresh directory under the system temp dir.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("monoio-native-tls-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[monoio::test]
+async fn reload_switches_identity() {
+    let (old, new) = (common::pki(), common::generate("localhost"));
+    let acceptor = ReloadableTlsAcceptor::new(common::identity(old)).unwrap();
+    let clone = acceptor.clone();
+    let (old_connector, new_connector) = (common::connector_for(old), common::connector_for(&new));
+
+    assert_eq!(
+        peer_cert(&old_connector, &acceptor).await,
+        Some(der(&old.cert))
+    );
+    assert_eq!(peer_cert(&new_connector, &acceptor).await, None);
+
+    acceptor.reload(common::identity(&new)).unwrap();
+    assert_eq!(
+        peer_cert(&new_connector, &acceptor).await,
+        Some(der(&new.cert))
+    );
+    // clones share the identity.
+    assert_eq!(
+        peer_cert(&new_connector, &clone).await,
+        Some(der(&new.cert))
+    );
+    assert_eq!(peer_cert(&old_connector, &clone).await, None);
+}
+
+#[monoio::test]
+async fn accepted_connections_keep_identity() {
+    let (old, new) = (common::pki(), common::generate("localhost"));
+    let acceptor = ReloadableTlsAcceptor::new(common::identity(old)).unwrap();
+    let (mut client, mut server) =
+        common::tls_pair(&common::connector_for(old), &acceptor.current()).await;
+
+    acceptor.reload(common::identity(&new)).unwrap();
+    let (r, _) = client.write_all(b"ping").await;
+    r.unwrap();
+    let (r, buf) = server.read(vec![0; 16]).await;
+    assert_eq!(&buf[..r.unwrap()], b"ping");
+    assert_eq!(
+        client
+            .peer_certificate()
+            .unwrap()
+            .unwrap()
+            .to_der()
+            .unwrap(),
+        der(&old.cert)
+    );
+}
+
+#[monoio::test]
+async fn buffers_are_per_clone() {
+    let (old, new) = (common::pki(), common::generate("localhost"));
+    let acceptor = ReloadableTlsAcceptor::new(common::identity(old)).unwrap();
+    let small = acceptor
+        .clone()
+        .read_buffer(Some(1024))
+        .write_buffer(Some(1024));
+
+    // a reload after setting the buffers applies to the clone too.
+    acceptor.reload(common::identity(&new)).unwrap();
+    let (mut client, mut server) =
+        common::tls_pair(&common::connector_for(&new), &small.current()).await;
+
+    let data: Vec<u8> = (0..1 << 16).map(|i| i as u8).collect();
+    let write = async {
+        let (r, _) = server.write_all(data.clone()).await;
+        r.unwrap();
+    };
+    let read = async {
+        let mut received = Vec::new();
+        while received.len() < data.len() {
+            let (r, buf) = client.read(Vec::with_capacity(4096)).await;
+            assert_ne!(r.unwrap(), 0);
+            received.extend_from_slice(&buf);
+        }
+        received
+    };
+    let ((), received) = monoio::join!(write, read);
+    assert!(received == data);
+}
+
+#[monoio::test]
+async fn with_builder_reapplies_configure() {
+    let (old, new) = (common::pki(), common::generate("localhost"));
+    let acceptor = ReloadableTlsAcceptor::with_builder(common::identity(old), |builder| {
+        builder.min_protocol_version(Some(native_tls::Protocol::Tlsv12));
+    })
+    .unwrap();
+    acceptor.reload(common::identity(&new)).unwrap();
+
+    // a client limited to TLS 1.1 is still rejected after the reload.
+    let connector: TlsConnector = native_tls::TlsConnector::builder()
+        .add_root_certificate(native_tls::Certificate::from_pem(new.ca.as_bytes()).unwrap())
+        .disable_built_in_roots(true)
+        .max_protocol_version(Some(native_tls::Protocol::Tlsv11))
+        .build()
+        .unwrap()
+        .into();
+    assert_eq!(peer_cert(&connector, &acceptor).await, None);
+    assert_eq!(
+        peer_cert(&common::connector_for(&new), &acceptor).await,
+        Some(der(&new.cert))
+    );
+}
+
+#[test]
+fn identity_files_load() {
+    let pki = common::pki();
+    let dir = temp_dir("identity-files");
+
+    let (cert, key) = (dir.join("cert.pem"), dir.join("key.pem"));
+    std::fs::write(&cert, &pki.cert).unwrap();
+    std::fs::write(&key, &pki.key).unwrap();
+    let pem = IdentityFiles::Pem {
+        cert: cert.clone(),
+        key: key.clone(),
+    };
+    pem.load().unwrap();
+
+    let pkcs12 = Pkcs12::builder()
+        .name("localhost")
+        .pkey(&PKey::private_key_from_pem(pki.key.as_bytes()).unwrap())
+        .cert(&X509::from_pem(pki.cert.as_bytes()).unwrap())
+        .build2("secret")
+        .unwrap();
+    let path = dir.join("identity.p12");
+    std::fs::write(&path, pkcs12.to_der().unwrap()).unwrap();
+    IdentityFiles::Pkcs12 {
+        path: path.clone(),
+        password: "secret".to_string(),
+    }
+    .load()
+    .unwrap();
+    assert!(IdentityFiles::Pkcs12 {
+        path,
+        password: "wrong".to_string(),
+    }
+    .load()
+    .is_err());
+
+    // missing or malformed files.
+    std::fs::remove_file(&key).unwrap();
+    assert!(pem.load().is_err());
+    std::fs::write(&key, "not a key").unwrap();
+    assert!(pem.load().is_err());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use monoio::io::{AsyncReadRent, AsyncReadRentExt, AsyncWriteRent, AsyncWriteRentExt};
+
+#[monoio::test]
+async fn shutdown_sends_close_notify_and_fin() {
+    let (mut client, mut server) =
+        common::tls_pair(&common::connector(), &common::acceptor()).await;
+
+    let (res, _) = client.write_all(b"bye").await;
+    res.unwrap();
+    client.shutdown().await.unwrap();
+
+    let (res, buf) = server.read_exact(vec![0; 3]).await;
+    res.unwrap();
+    assert_eq!(buf, b"bye");
+    // close_notify ends the tls stream cleanly.
+    let (res, _) = server.read(vec![0; 16]).await;
+    assert_eq!(res.unwrap(), 0);
+    // and the transport has been shut down after it.
+    let mut tcp = server.into_inner();
+    let (res, _) = tcp.read(vec![0; 16]).await;
+    assert_eq!(res.unwrap(), 0);
+}
+
+#[monoio::test]
+async fn shutdown_is_idempotent() {
+    let (mut client, _server) = common::tls_pair(&common::connector(), &common::acceptor()).await;
+    client.shutdown().await.unwrap();
+    client.shutdown().await.unwrap();
+}
+
+#[monoio::test]
+async fn flush_writes_pending_data() {
+    let connector = common::connector().write_buffer(Some(64 * 1024));
+    let (mut client, mut server) = common::tls_pair(&connector, &common::acceptor()).await;
+
+    let data = vec![7; 32 * 1024];
+    let (res, _) = client.write_all(data.clone()).await;
+    res.unwrap();
+    client.flush().await.unwrap();
+    let (res, buf) = server.read_exact(vec![0; data.len()]).await;
+    res.unwrap();
+    assert!(buf == data);
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "unsafe_io")]
+
+mod common;
+
+use monoio::io::{AsyncReadRent, AsyncReadRentExt, AsyncWriteRent, AsyncWriteRentExt};
+
+#[monoio::test]
+async fn echo_large() {
+    let connector = unsafe { common::connector().unsafe_io(true) };
+    let acceptor = unsafe { common::acceptor().unsafe_io(true) };
+    let (mut client, mut server) = common::tls_pair(&connector, &acceptor).await;
+
+    // several records in both directions, with reads split across them.
+    let data: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+    let write = async {
+        let (res, _) = client.write_all(data.clone()).await;
+        res.unwrap();
+        client.flush().await.unwrap();
+        let (res, buf) = client.read_exact(vec![0; data.len()]).await;
+        res.unwrap();
+        buf
+    };
+    let echo = async {
+        let mut received = 0;
+        while received < data.len() {
+            let (res, buf) = server.read(vec![0; 1000]).await;
+            let n = res.unwrap();
+            assert!(n > 0);
+            received += n;
+            let (res, _) = server.write_all(buf).await;
+            res.unwrap();
+        }
+        server.flush().await.unwrap();
+    };
+    let (echoed, ()) = monoio::join!(write, echo);
+    assert!(echoed == data);
+
+    client.shutdown().await.unwrap();
+    let (res, _) = server.read(vec![0; 16]).await;
+    assert_eq!(res.unwrap(), 0);
+}
+
+#[monoio::test]
+async fn unsafe_client_safe_server() {
+    let connector = unsafe { common::connector().unsafe_io(true) };
+    let (mut client, mut server) = common::tls_pair(&connector, &common::acceptor()).await;
+
+    let (res, _) = server.write_all(b"hello").await;
+    res.unwrap();
+    let (res, buf) = client.read_exact(vec![0; 5]).await;
+    res.unwrap();
+    assert_eq!(buf, b"hello");
+}
+
+#[monoio::test]
+async fn reloadable() {
+    let acceptor =
+        monoio_native_tls::ReloadableTlsAcceptor::new(common::identity(common::pki())).unwrap();
+    let acceptor = unsafe { acceptor.unsafe_io(true) };
+    let connector = common::connector();
+    let (client, server) = common::tcp_pair().await;
+    let (client, server) = monoio::join!(
+        connector.connect("localhost", client),
+        acceptor.accept(server)
+    );
+    let (mut client, mut server) = (client.unwrap(), server.unwrap());
+
+    let (res, _) = server.write_all(b"hello").await;
+    res.unwrap();
+    server.flush().await.unwrap();
+    let (res, buf) = client.read_exact(vec![0; 5]).await;
+    res.unwrap();
+    assert_eq!(buf, b"hello");
+}
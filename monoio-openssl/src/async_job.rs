@@ -0,0 +1,139 @@
+//! OpenSSL async job support.
+//!
+//! With `SSL_MODE_ASYNC` enabled, OpenSSL runs the crypto operations of an
+//! engine(e.g. QAT or `dasync`) inside async jobs. Instead of blocking the
+//! thread while the accelerator is busy, the ssl call returns
+//! `SSL_ERROR_WANT_ASYNC` and the wait fds of the job become readable once the
+//! result is ready. [`wait_async_job`] registers these fds with the monoio
+//! driver, so other tasks on the same core can run in the meantime. The ssl
+//! call must then be retried with the same arguments.
+//!
+//! The handshake of [`SslConnector`](crate::SslConnector) and
+//! [`SslAcceptor`](crate::SslAcceptor) and the reads, writes and shutdown of
+//! [`SslStream`](crate::SslStream) wait and retry by themselves. The poll-io
+//! interface does not, it returns the error instead.
+
+use std::{
+    ffi::{c_int, c_long},
+    future::Future,
+    io,
+    os::{
+        fd::{BorrowedFd, RawFd},
+        unix::net::UnixStream as StdUnixStream,
+    },
+    pin::Pin,
+    ptr,
+    task::{Context, Poll},
+};
+
+use foreign_types::ForeignTypeRef;
+use monoio::net::UnixStream;
+use openssl::ssl::{self, ErrorCode, SslContextBuilder, SslMode, SslRef};
+use openssl_sys::SSL;
+
+const SSL_MODE_ASYNC: c_long = 0x0000_0100;
+const SSL_CTRL_MODE: c_int = 33;
+const SSL_ERROR_WANT_ASYNC: c_int = 9;
+const SSL_ERROR_WANT_ASYNC_JOB: c_int = 10;
+
+extern "C" {
+    fn SSL_waiting_for_async(ssl: *mut SSL) -> c_int;
+    fn SSL_get_all_async_fds(ssl: *mut SSL, fds: *mut RawFd, numfds: *mut usize) -> c_int;
+}
+
+/// Enable `SSL_MODE_ASYNC` for all ssl objects created from the context.
+pub fn enable_async_mode(builder: &mut SslContextBuilder) {
+    builder.set_mode(SslMode::from_bits_retain(SSL_MODE_ASYNC as _));
+}
+
+/// Enable `SSL_MODE_ASYNC` for a single ssl object.
+pub fn set_async_mode(ssl: &mut SslRef) {
+    unsafe { openssl_sys::SSL_ctrl(ssl.as_ptr(), SSL_CTRL_MODE, SSL_MODE_ASYNC, ptr::null_mut()) };
+}
+
+/// Whether the error means the call should be retried after
+/// [`wait_async_job`].
+/// `SSL_ERROR_WANT_ASYNC_JOB` (the async job pool is exhausted) is included.
+pub fn is_want_async(err: &ssl::Error) -> bool {
+    let code = err.code();
+    code == ErrorCode::from_raw(SSL_ERROR_WANT_ASYNC)
+        || code == ErrorCode::from_raw(SSL_ERROR_WANT_ASYNC_JOB)
+}
+
+/// Same as [`is_want_async`], for errors converted by `SslStream`'s
+/// `Read`/`Write` implementations.
+pub fn is_want_async_io(err: &io::Error) -> bool {
+    err.get_ref()
+        .and_then(|e| e.downcast_ref::<ssl::Error>())
+        .is_some_and(is_want_async)
+}
+
+/// Whether an async job of the ssl object is paused.
+pub fn waiting_for_async(ssl: &SslRef) -> bool {
+    unsafe { SSL_waiting_for_async(ssl.as_ptr()) == 1 }
+}
+
+/// Wait until the paused async job of the ssl object can make progress.
+///
+/// If the job has no wait fds(e.g. the engine does not provide any, or the
+/// error was `SSL_ERROR_WANT_ASYNC_JOB`), it yields once so the caller retries
+/// after other tasks have run.
+pub async fn wait_async_job(ssl: &SslRef) -> io::Result<()> {
+    let fds = async_fds(ssl);
+    if fds.is_empty() {
+        return YieldNow(false).await;
+    }
+
+    // monoio has no api to wait on an arbitrary fd, so wrap a duplicate of the
+    // fd into a UnixStream which is only used for readiness. The fd is owned
+    // by the engine and must not be closed by us.
+    let mut streams = Vec::with_capacity(fds.len());
+    for fd in fds {
+        let dup = unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?;
+        streams.push(UnixStream::from_std(StdUnixStream::from(dup))?);
+    }
+
+    let mut waits: Vec<_> = streams
+        .iter()
+        .map(|s| Box::pin(s.readable(false)))
+        .collect();
+    std::future::poll_fn(|cx| {
+        // any ready fd means the job should be resumed.
+        for wait in waits.iter_mut() {
+            if let Poll::Ready(r) = wait.as_mut().poll(cx) {
+                return Poll::Ready(r);
+            }
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+fn async_fds(ssl: &SslRef) -> Vec<RawFd> {
+    // it fails when there is no job context yet, which means no fds.
+    let mut num = 0;
+    if unsafe { SSL_get_all_async_fds(ssl.as_ptr(), ptr::null_mut(), &mut num) } != 1 {
+        return Vec::new();
+    }
+    let mut fds = vec![0; num];
+    if num > 0 && unsafe { SSL_get_all_async_fds(ssl.as_ptr(), fds.as_mut_ptr(), &mut num) } != 1 {
+        return Vec::new();
+    }
+    fds.truncate(num);
+    fds
+}
+
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.0 {
+            return Poll::Ready(Ok(()));
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
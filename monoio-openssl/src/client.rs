@@ -0,0 +1,117 @@
+use std::fmt;
+
+use monoio::io::{AsyncReadRent, AsyncWriteRent};
+use monoio_io_wrapper::IOWrapper;
+use openssl::{
+    error::ErrorStack,
+    ssl::{ConnectConfiguration, Ssl, SslRef},
+};
+
+#[cfg(feature = "unsafe_io")]
+use crate::utils::keep_read_buffer;
+use crate::{utils::handshake, SslStream, TlsError};
+
+/// A wrapper around an `openssl::ssl::SslConnector`, providing async `connect`
+/// methods.
+///
+/// Callbacks and other context level options are set on the
+/// `openssl::ssl::SslConnectorBuilder` before converting it into this type.
+#[derive(Clone)]
+pub struct SslConnector {
+    inner: openssl::ssl::SslConnector,
+    read_buffer: Option<usize>,
+    write_buffer: Option<usize>,
+    #[cfg(feature = "unsafe_io")]
+    unsafe_io: bool,
+}
+
+impl SslConnector {
+    /// Connects the provided stream with this connector, assuming the provided
+    /// domain.
+    ///
+    /// SNI and hostname verification are enabled as configured by
+    /// `openssl::ssl::SslConnector`.
+    pub async fn connect<S>(&self, domain: &str, stream: S) -> Result<SslStream<S>, TlsError>
+    where
+        S: AsyncReadRent + AsyncWriteRent,
+    {
+        let ssl = self.inner.configure()?.into_ssl(domain)?;
+        self.connect_ssl(ssl, stream).await
+    }
+
+    /// Connects the provided stream with a `Ssl` configured by the caller,
+    /// e.g. one created by [`configure`](Self::configure) with a session
+    /// to resume.
+    pub async fn connect_ssl<S>(&self, mut ssl: Ssl, stream: S) -> Result<SslStream<S>, TlsError>
+    where
+        S: AsyncReadRent + AsyncWriteRent,
+    {
+        let io = self.wrap(&mut ssl, stream);
+        handshake(move |s_wrap| ssl.connect(s_wrap), io).await
+    }
+
+    /// Returns a structure allowing for configuration of a single TLS session
+    /// before connection.
+    pub fn configure(&self) -> Result<ConnectConfiguration, ErrorStack> {
+        self.inner.configure()
+    }
+
+    /// Returns a reference to the inner `openssl::ssl::SslConnector`.
+    pub fn get_ref(&self) -> &openssl::ssl::SslConnector {
+        &self.inner
+    }
+
+    /// Enable unsafe-io.
+    /// Once enabled, OpenSSL reads and writes go directly to the socket and
+    /// the buffer sizes are ignored. SSL_MODE_RELEASE_BUFFERS is cleared on
+    /// each `Ssl`, since the read buffer must outlive a pending read.
+    /// # Safety
+    /// Users must make sure the buffer ptr and len is valid until io finished.
+    /// So the Future cannot be dropped directly. Consider using CancellableIO.
+    #[cfg(feature = "unsafe_io")]
+    pub unsafe fn unsafe_io(self, enabled: bool) -> Self {
+        Self {
+            unsafe_io: enabled,
+            ..self
+        }
+    }
+
+    pub fn read_buffer(mut self, size: Option<usize>) -> Self {
+        self.read_buffer = size;
+        self
+    }
+
+    pub fn write_buffer(mut self, size: Option<usize>) -> Self {
+        self.write_buffer = size;
+        self
+    }
+
+    fn wrap<S>(&self, _ssl: &mut SslRef, stream: S) -> IOWrapper<S> {
+        #[cfg(feature = "unsafe_io")]
+        if self.unsafe_io {
+            keep_read_buffer(_ssl);
+            // # Safety
+            // Users already maked unsafe io.
+            return unsafe { IOWrapper::new_unsafe(stream) };
+        }
+        IOWrapper::new_with_buffer_size(stream, self.read_buffer, self.write_buffer)
+    }
+}
+
+impl fmt::Debug for SslConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SslConnector").finish()
+    }
+}
+
+impl From<openssl::ssl::SslConnector> for SslConnector {
+    fn from(inner: openssl::ssl::SslConnector) -> SslConnector {
+        SslConnector {
+            inner,
+            read_buffer: None,
+            write_buffer: None,
+            #[cfg(feature = "unsafe_io")]
+            unsafe_io: false,
+        }
+    }
+}
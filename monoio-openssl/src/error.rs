@@ -0,0 +1,27 @@
+use std::io;
+
+use openssl::{error::ErrorStack, ssl};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TlsError {
+    #[error("io error")]
+    Io(#[from] std::io::Error),
+    #[error("openssl error")]
+    Ssl(#[from] ssl::Error),
+    #[error("openssl error stack")]
+    ErrorStack(#[from] ErrorStack),
+}
+
+impl From<TlsError> for io::Error {
+    fn from(e: TlsError) -> Self {
+        match e {
+            TlsError::Io(e) => e,
+            TlsError::Ssl(e) => match e.into_io_error() {
+                Ok(e) => e,
+                Err(e) => io::Error::other(e),
+            },
+            TlsError::ErrorStack(e) => io::Error::other(e),
+        }
+    }
+}
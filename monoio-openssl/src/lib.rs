@@ -0,0 +1,15 @@
+#[cfg(feature = "async-job")]
+pub mod async_job;
+mod client;
+mod error;
+mod server;
+mod stream;
+#[cfg(feature = "poll-io")]
+mod stream_poll;
+mod utils;
+
+pub use client::SslConnector;
+pub use error::TlsError;
+pub use monoio_io_wrapper::Buffers;
+pub use server::SslAcceptor;
+pub use stream::SslStream;
@@ -0,0 +1,108 @@
+use std::fmt;
+
+use monoio::io::{AsyncReadRent, AsyncWriteRent};
+use monoio_io_wrapper::IOWrapper;
+use openssl::ssl::{Ssl, SslContextRef, SslRef};
+
+#[cfg(feature = "unsafe_io")]
+use crate::utils::keep_read_buffer;
+use crate::{utils::handshake, SslStream, TlsError};
+
+/// A wrapper around an `openssl::ssl::SslAcceptor`, providing async `accept`
+/// methods.
+///
+/// Callbacks like ALPN selection, SNI, session tickets and client certificate
+/// verification are set on the `openssl::ssl::SslAcceptorBuilder` before
+/// converting it into this type.
+#[derive(Clone)]
+pub struct SslAcceptor {
+    inner: openssl::ssl::SslAcceptor,
+    read_buffer: Option<usize>,
+    write_buffer: Option<usize>,
+    #[cfg(feature = "unsafe_io")]
+    unsafe_io: bool,
+}
+
+impl SslAcceptor {
+    /// Accepts a new client connection with the provided stream.
+    pub async fn accept<S>(&self, stream: S) -> Result<SslStream<S>, TlsError>
+    where
+        S: AsyncReadRent + AsyncWriteRent,
+    {
+        let ssl = Ssl::new(self.inner.context())?;
+        self.accept_ssl(ssl, stream).await
+    }
+
+    /// Accepts a new client connection with a `Ssl` configured by the caller.
+    pub async fn accept_ssl<S>(&self, mut ssl: Ssl, stream: S) -> Result<SslStream<S>, TlsError>
+    where
+        S: AsyncReadRent + AsyncWriteRent,
+    {
+        let io = self.wrap(&mut ssl, stream);
+        handshake(move |s_wrap| ssl.accept(s_wrap), io).await
+    }
+
+    /// Returns the `SslContext` used by this acceptor.
+    pub fn context(&self) -> &SslContextRef {
+        self.inner.context()
+    }
+
+    /// Returns a reference to the inner `openssl::ssl::SslAcceptor`.
+    pub fn get_ref(&self) -> &openssl::ssl::SslAcceptor {
+        &self.inner
+    }
+
+    /// Enable unsafe-io.
+    /// Once enabled, OpenSSL reads and writes go directly to the socket and
+    /// the buffer sizes are ignored. SSL_MODE_RELEASE_BUFFERS is cleared on
+    /// each `Ssl`, since the read buffer must outlive a pending read.
+    /// # Safety
+    /// Users must make sure the buffer ptr and len is valid until io finished.
+    /// So the Future cannot be dropped directly. Consider using CancellableIO.
+    #[cfg(feature = "unsafe_io")]
+    pub unsafe fn unsafe_io(self, enabled: bool) -> Self {
+        Self {
+            unsafe_io: enabled,
+            ..self
+        }
+    }
+
+    pub fn read_buffer(mut self, size: Option<usize>) -> Self {
+        self.read_buffer = size;
+        self
+    }
+
+    pub fn write_buffer(mut self, size: Option<usize>) -> Self {
+        self.write_buffer = size;
+        self
+    }
+
+    fn wrap<S>(&self, _ssl: &mut SslRef, stream: S) -> IOWrapper<S> {
+        #[cfg(feature = "unsafe_io")]
+        if self.unsafe_io {
+            keep_read_buffer(_ssl);
+            // # Safety
+            // Users already maked unsafe io.
+            return unsafe { IOWrapper::new_unsafe(stream) };
+        }
+        IOWrapper::new_with_buffer_size(stream, self.read_buffer, self.write_buffer)
+    }
+}
+
+impl fmt::Debug for SslAcceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SslAcceptor").finish()
+    }
+}
+
+impl From<openssl::ssl::SslAcceptor> for SslAcceptor {
+    fn from(inner: openssl::ssl::SslAcceptor) -> SslAcceptor {
+        SslAcceptor {
+            inner,
+            read_buffer: None,
+            write_buffer: None,
+            #[cfg(feature = "unsafe_io")]
+            unsafe_io: false,
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::io::{self, Read, Write};
+
+use monoio::{
+    buf::{IoBuf, IoBufMut, IoVecBuf, IoVecBufMut, RawBuf},
+    io::{AsyncReadRent, AsyncWriteRent, Split},
+    BufResult,
+};
+use monoio_io_wrapper::{Buffers, IOWrapper};
+use openssl::{
+    error::ErrorStack,
+    ssl::{self, ErrorCode, SslRef},
+};
+
+#[cfg(feature = "async-job")]
+use crate::async_job;
+
+/// A wrapper around an underlying raw stream which implements the TLS or SSL
+/// protocol.
+///
+/// A `SslStream<S>` represents a handshake that has been completed successfully
+/// and both the server and the client are ready for receiving and sending
+/// data. Bytes read from a `SslStream` are decrypted from `S` and bytes written
+/// to a `SslStream` are encrypted when passing through to `S`.
+#[derive(Debug)]
+pub struct SslStream<S> {
+    pub(crate) tls: ssl::SslStream<Buffers>,
+    pub(crate) io: IOWrapper<S>,
+}
+
+impl<S> SslStream<S> {
+    pub(crate) fn new(tls_stream: ssl::SslStream<Buffers>, io: IOWrapper<S>) -> Self {
+        Self {
+            tls: tls_stream,
+            io,
+        }
+    }
+
+    /// Returns a shared reference to the `Ssl` object associated with this
+    /// stream.
+    pub fn ssl(&self) -> &SslRef {
+        self.tls.ssl()
+    }
+
+    /// Derives keying material for application use as defined in
+    /// [RFC 5705](https://tools.ietf.org/html/rfc5705).
+    pub fn export_keying_material(
+        &self,
+        out: &mut [u8],
+        label: &str,
+        context: Option<&[u8]>,
+    ) -> Result<(), ErrorStack> {
+        self.tls.ssl().export_keying_material(out, label, context)
+    }
+
+    /// Returns a shared reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        self.io.get_ref()
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Reading from or writing to it directly will corrupt the TLS session.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.io.get_mut()
+    }
+
+    pub fn into_inner(self) -> S {
+        self.io.into_parts().0
+    }
+}
+
+pub(crate) fn ssl_io_error(e: ssl::Error) -> io::Error {
+    e.into_io_error().unwrap_or_else(io::Error::other)
+}
+
+unsafe impl<S: Split> Split for SslStream<S> {}
+
+impl<S: AsyncReadRent> AsyncReadRent for SslStream<S> {
+    #[allow(clippy::await_holding_refcell_ref)]
+    async fn read<T: IoBufMut>(&mut self, mut buf: T) -> BufResult<usize, T> {
+        let slice = unsafe { std::slice::from_raw_parts_mut(buf.write_ptr(), buf.bytes_total()) };
+
+        loop {
+            // read from openssl to buffer
+            match self.tls.read(slice) {
+                Ok(n) => {
+                    unsafe { buf.set_init(n) };
+                    return (Ok(n), buf);
+                }
+                // we need more data, read something.
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => (),
+                #[cfg(feature = "async-job")]
+                Err(ref err) if async_job::is_want_async_io(err) => {
+                    if let Err(e) = async_job::wait_async_job(self.tls.ssl()).await {
+                        return (Err(e), buf);
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    return (Err(e), buf);
+                }
+            }
+
+            // now we need data, read something into openssl
+            match unsafe { self.io.do_read_io() }.await {
+                Ok(0) => {
+                    return (Ok(0), buf);
+                }
+                Ok(_) => (),
+                Err(e) => {
+                    return (Err(e), buf);
+                }
+            };
+        }
+    }
+
+    async fn readv<T: IoVecBufMut>(&mut self, mut buf: T) -> BufResult<usize, T> {
+        let n = match unsafe { RawBuf::new_from_iovec_mut(&mut buf) } {
+            Some(raw_buf) => self.read(raw_buf).await.0,
+            None => Ok(0),
+        };
+        if let Ok(n) = n {
+            unsafe { buf.set_init(n) };
+        }
+        (n, buf)
+    }
+}
+
+impl<S: AsyncWriteRent> AsyncWriteRent for SslStream<S> {
+    #[allow(clippy::await_holding_refcell_ref)]
+    async fn write<T: IoBuf>(&mut self, buf: T) -> BufResult<usize, T> {
+        // construct slice
+        let slice = unsafe { std::slice::from_raw_parts(buf.read_ptr(), buf.bytes_init()) };
+
+        loop {
+            // write slice to openssl and buffer
+            let maybe_n = match self.tls.write(slice) {
+                Ok(n) => Some(n),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => None,
+                #[cfg(feature = "async-job")]
+                Err(e) if async_job::is_want_async_io(&e) => {
+                    if let Err(e) = async_job::wait_async_job(self.tls.ssl()).await {
+                        return (Err(e), buf);
+                    }
+                    continue;
+                }
+                Err(e) => return (Err(e), buf),
+            };
+
+            // write from buffer to connection
+            if let Err(e) = unsafe { self.io.do_write_io() }.await {
+                return (Err(e), buf);
+            }
+
+            if let Some(n) = maybe_n {
+                return (Ok(n), buf);
+            }
+        }
+    }
+
+    // TODO: use real writev
+    async fn writev<T: IoVecBuf>(&mut self, buf_vec: T) -> BufResult<usize, T> {
+        let n = match unsafe { RawBuf::new_from_iovec(&buf_vec) } {
+            Some(raw_buf) => self.write(raw_buf).await.0,
+            None => Ok(0),
+        };
+        (n, buf_vec)
+    }
+
+    #[allow(clippy::await_holding_refcell_ref)]
+    async fn flush(&mut self) -> io::Result<()> {
+        loop {
+            match self.tls.flush() {
+                Ok(_) => {
+                    unsafe { self.io.do_write_io() }.await?;
+                    return self.io.flush_io().await;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    unsafe { self.io.do_write_io() }.await?;
+                }
+                Err(e) => {
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    async fn shutdown(&mut self) -> io::Result<()> {
+        // send close_notify, we do not wait for the one from peer.
+        loop {
+            match self.tls.shutdown() {
+                Ok(_) => break,
+                Err(e) if e.code() == ErrorCode::ZERO_RETURN => break,
+                #[cfg(feature = "async-job")]
+                Err(e) if async_job::is_want_async(&e) => {
+                    async_job::wait_async_job(self.tls.ssl()).await?;
+                }
+                Err(e) => match ssl_io_error(e) {
+                    e if e.kind() == io::ErrorKind::WouldBlock => {
+                        // nothing to write means close_notify has been sent
+                        // and it is waiting for the peer's one.
+                        if unsafe { self.io.do_write_io() }.await? == 0 {
+                            break;
+                        }
+                    }
+                    e => return Err(e),
+                },
+            }
+        }
+        unsafe { self.io.do_write_io() }.await?;
+        self.io.shutdown_io().await
+    }
+}
@@ -0,0 +1,69 @@
+#[cfg(feature = "unsafe_io")]
+use std::{
+    ffi::{c_int, c_long},
+    ptr,
+};
+
+#[cfg(feature = "unsafe_io")]
+use foreign_types::ForeignTypeRef;
+use monoio::io::{AsyncReadRent, AsyncWriteRent};
+use monoio_io_wrapper::{Buffers, IOWrapper, OPENSSL_FLUSH_WOULD_BLOCK};
+use openssl::ssl::{self, HandshakeError};
+
+#[cfg(feature = "async-job")]
+use crate::async_job;
+use crate::{SslStream, TlsError};
+
+/// With SSL_MODE_RELEASE_BUFFERS(set by rust-openssl) OpenSSL frees its read
+/// buffer when a read would block with nothing buffered, so the ptr captured
+/// by the unsafe read buffer would dangle.
+#[cfg(feature = "unsafe_io")]
+pub(crate) fn keep_read_buffer(ssl: &mut ssl::SslRef) {
+    // SSL_clear_mode is a macro.
+    const SSL_CTRL_CLEAR_MODE: c_int = 78;
+    unsafe {
+        openssl_sys::SSL_ctrl(
+            ssl.as_ptr(),
+            SSL_CTRL_CLEAR_MODE,
+            openssl_sys::SSL_MODE_RELEASE_BUFFERS as c_long,
+            ptr::null_mut(),
+        );
+    }
+}
+
+pub(crate) async fn handshake<F, S>(f: F, mut io: IOWrapper<S>) -> Result<SslStream<S>, TlsError>
+where
+    F: FnOnce(Buffers) -> Result<ssl::SslStream<Buffers>, HandshakeError<Buffers>>,
+    S: AsyncReadRent + AsyncWriteRent,
+{
+    let mut result = f(io.buffers(OPENSSL_FLUSH_WOULD_BLOCK));
+    loop {
+        let mid = match result {
+            Ok(tls) => {
+                io.write_io().await?;
+                return Ok(SslStream::new(tls, io));
+            }
+            Err(HandshakeError::WouldBlock(mid)) => {
+                if io.write_io().await? == 0 {
+                    io.read_io().await?;
+                }
+                mid
+            }
+            // rust-openssl reports WANT_ASYNC as a failure, but the handshake
+            // can be resumed once the async job is done.
+            #[cfg(feature = "async-job")]
+            Err(HandshakeError::Failure(mid)) if async_job::is_want_async(mid.error()) => {
+                io.write_io().await?;
+                async_job::wait_async_job(mid.ssl()).await?;
+                mid
+            }
+            Err(HandshakeError::Failure(s)) => {
+                // try to send the alert, e.g. no_application_protocol.
+                let _ = io.write_io().await;
+                return Err(s.into_error().into());
+            }
+            Err(HandshakeError::SetupFailure(e)) => return Err(e.into()),
+        };
+        result = mid.handshake();
+    }
+}
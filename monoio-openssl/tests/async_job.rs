@@ -0,0 +1,108 @@
+#![cfg(feature = "async-job")]
+
+mod common;
+
+use std::{
+    ffi::{c_char, c_int, c_uint, c_void},
+    io,
+};
+
+use monoio::{
+    io::{AsyncReadRent, AsyncWriteRent, AsyncWriteRentExt},
+    net::TcpStream,
+};
+use monoio_openssl::{async_job, SslStream};
+use openssl::ssl::SslVersion;
+
+extern "C" {
+    fn ENGINE_by_id(id: *const c_char) -> *mut c_void;
+    fn ENGINE_init(e: *mut c_void) -> c_int;
+    fn ENGINE_set_default(e: *mut c_void, flags: c_uint) -> c_int;
+    fn ENGINE_free(e: *mut c_void) -> c_int;
+}
+
+const ENGINE_METHOD_DIGESTS: c_uint = 0x0080;
+
+async fn echo(client: &mut SslStream<TcpStream>, server: &mut SslStream<TcpStream>) {
+    let (r, _) = client.write_all(b"ping").await;
+    r.unwrap();
+    let (r, buf) = server.read(vec![0; 16]).await;
+    assert_eq!(&buf[..r.unwrap()], b"ping");
+    let (r, _) = server.write_all(b"pong").await;
+    r.unwrap();
+    let (r, buf) = client.read(vec![0; 16]).await;
+    assert_eq!(&buf[..r.unwrap()], b"pong");
+}
+
+#[test]
+fn want_async_errors() {
+    assert!(!async_job::is_want_async_io(&io::Error::other("other")));
+    assert!(!async_job::is_want_async_io(
+        &io::ErrorKind::WouldBlock.into()
+    ));
+}
+
+// Without an async engine the jobs never pause, but the streams must work the
+// same with the mode enabled.
+#[monoio::test]
+async fn async_mode_without_engine() {
+    let mut acceptor = common::acceptor_builder();
+    async_job::enable_async_mode(&mut acceptor);
+    let mut connector = common::connector_builder();
+    async_job::enable_async_mode(&mut connector);
+    let (acceptor, connector) = (acceptor.build().into(), connector.build().into());
+
+    let (mut client, mut server) = common::tls_pair(&connector, &acceptor).await;
+    echo(&mut client, &mut server).await;
+    assert!(!async_job::waiting_for_async(client.ssl()));
+    assert!(!async_job::waiting_for_async(server.ssl()));
+}
+
+/// Register OpenSSL's `dasync` test engine, whose SHA-1 pauses the async job
+/// on every call. It is not installed by all distributions.
+fn load_dasync() -> bool {
+    unsafe {
+        let e = ENGINE_by_id(c"dasync".as_ptr());
+        if e.is_null() {
+            // drain the error queue.
+            let _ = openssl::error::ErrorStack::get();
+            return false;
+        }
+        let ok = ENGINE_init(e) == 1 && ENGINE_set_default(e, ENGINE_METHOD_DIGESTS) == 1;
+        ENGINE_free(e);
+        ok
+    }
+}
+
+#[monoio::test]
+async fn dasync_engine() {
+    if !load_dasync() {
+        eprintln!("dasync engine unavailable, skipped");
+        return;
+    }
+
+    // a cipher suite with SHA-1 MAC, so both the handshake and the records
+    // pause.
+    let mut acceptor = common::acceptor_builder();
+    acceptor
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    acceptor.set_min_proto_version(None).unwrap();
+    acceptor.set_cipher_list("ECDHE-ECDSA-AES128-SHA").unwrap();
+    async_job::enable_async_mode(&mut acceptor);
+    let mut connector = common::connector_builder();
+    connector
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .unwrap();
+    connector.set_cipher_list("ECDHE-ECDSA-AES128-SHA").unwrap();
+    async_job::enable_async_mode(&mut connector);
+    let (acceptor, connector) = (acceptor.build().into(), connector.build().into());
+
+    let (mut client, mut server) = common::tls_pair(&connector, &acceptor).await;
+    assert_eq!(
+        client.ssl().current_cipher().unwrap().name(),
+        "ECDHE-ECDSA-AES128-SHA"
+    );
+    echo(&mut client, &mut server).await;
+    client.shutdown().await.unwrap();
+}
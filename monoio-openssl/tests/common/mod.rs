@@ -0,0 +1,87 @@
+#![allow(dead_code)]
+
+use std::sync::OnceLock;
+
+use monoio::net::{TcpListener, TcpStream};
+use monoio_openssl::{SslAcceptor, SslConnector, SslStream};
+use openssl::{
+    pkey::{PKey, Private},
+    ssl::{SslAcceptorBuilder, SslConnectorBuilder, SslMethod},
+    x509::X509,
+};
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+
+/// A CA and a `localhost` server certificate issued by it.
+pub struct Pki {
+    pub ca: X509,
+    pub cert: X509,
+    pub key: PKey<Private>,
+}
+
+pub fn pki() -> &'static Pki {
+    static PKI: OnceLock<Pki> = OnceLock::new();
+    PKI.get_or_init(|| {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "monoio-tls test CA");
+        let ca = params.self_signed(&ca_key).unwrap();
+
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "localhost");
+        let cert = params.signed_by(&key, &ca, &ca_key).unwrap();
+        Pki {
+            ca: X509::from_der(ca.der()).unwrap(),
+            cert: X509::from_der(cert.der()).unwrap(),
+            key: PKey::private_key_from_der(&key.serialize_der()).unwrap(),
+        }
+    })
+}
+
+pub fn acceptor_builder() -> SslAcceptorBuilder {
+    let pki = pki();
+    let mut builder = openssl::ssl::SslAcceptor::mozilla_modern_v5(SslMethod::tls()).unwrap();
+    builder.set_certificate(&pki.cert).unwrap();
+    builder.set_private_key(&pki.key).unwrap();
+    builder
+}
+
+pub fn connector_builder() -> SslConnectorBuilder {
+    let mut builder = openssl::ssl::SslConnector::builder(SslMethod::tls()).unwrap();
+    builder.cert_store_mut().add_cert(pki().ca.clone()).unwrap();
+    builder
+}
+
+pub fn acceptor() -> SslAcceptor {
+    acceptor_builder().build().into()
+}
+
+pub fn connector() -> SslConnector {
+    connector_builder().build().into()
+}
+
+/// Returns both ends of a loopback tcp connection.
+pub async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, server) = monoio::join!(TcpStream::connect(addr), listener.accept());
+    (client.unwrap(), server.unwrap().0)
+}
+
+/// Returns both ends of a tls connection over loopback tcp.
+pub async fn tls_pair(
+    connector: &SslConnector,
+    acceptor: &SslAcceptor,
+) -> (SslStream<TcpStream>, SslStream<TcpStream>) {
+    let (client, server) = tcp_pair().await;
+    let (client, server) = monoio::join!(
+        connector.connect("localhost", client),
+        acceptor.accept(server)
+    );
+    (client.unwrap(), server.unwrap())
+}
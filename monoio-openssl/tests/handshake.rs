@@ -0,0 +1,195 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use monoio::io::{AsyncReadRent, AsyncWriteRent, AsyncWriteRentExt};
+use monoio_openssl::{SslAcceptor, SslConnector, TlsError};
+use openssl::ssl::{select_next_proto, AlpnError, NameType, SniError, SslMethod};
+
+#[monoio::test]
+async fn echo() {
+    let (mut client, mut server) =
+        common::tls_pair(&common::connector(), &common::acceptor()).await;
+
+    let (r, _) = client.write_all(b"ping").await;
+    r.unwrap();
+    let (r, buf) = server.read(vec![0; 16]).await;
+    assert_eq!(&buf[..r.unwrap()], b"ping");
+    let (r, _) = server.write_all(b"pong").await;
+    r.unwrap();
+    let (r, buf) = client.read(vec![0; 16]).await;
+    assert_eq!(&buf[..r.unwrap()], b"pong");
+
+    client.shutdown().await.unwrap();
+    let (r, _) = server.read(vec![0; 16]).await;
+    assert_eq!(r.unwrap(), 0);
+}
+
+#[monoio::test]
+async fn untrusted_server() {
+    let connector: SslConnector = openssl::ssl::SslConnector::builder(SslMethod::tls())
+        .unwrap()
+        .build()
+        .into();
+    let (client, server) = common::tcp_pair().await;
+    let acceptor = common::acceptor();
+    let (client, server) = monoio::join!(
+        connector.connect("localhost", client),
+        acceptor.accept(server)
+    );
+    assert!(matches!(client, Err(TlsError::Ssl(_))));
+    assert!(server.is_err());
+}
+
+#[monoio::test]
+async fn hostname_mismatch() {
+    let (client, server) = common::tcp_pair().await;
+    let (connector, acceptor) = (common::connector(), common::acceptor());
+    let (client, server) = monoio::join!(
+        connector.connect("example.com", client),
+        acceptor.accept(server)
+    );
+    assert!(matches!(client, Err(TlsError::Ssl(_))));
+    assert!(server.is_err());
+}
+
+#[monoio::test]
+async fn alpn_select_callback() {
+    let mut acceptor = common::acceptor_builder();
+    acceptor.set_alpn_select_callback(|_, client| {
+        select_next_proto(b"\x02h2\x08http/1.1", client).ok_or(AlpnError::NOACK)
+    });
+    let acceptor: SslAcceptor = acceptor.build().into();
+    let mut connector = common::connector_builder();
+    connector
+        .set_alpn_protos(b"\x06spdy/1\x08http/1.1")
+        .unwrap();
+    let connector: SslConnector = connector.build().into();
+
+    let (client, server) = common::tls_pair(&connector, &acceptor).await;
+    assert_eq!(
+        client.ssl().selected_alpn_protocol(),
+        Some(&b"http/1.1"[..])
+    );
+    assert_eq!(
+        server.ssl().selected_alpn_protocol(),
+        Some(&b"http/1.1"[..])
+    );
+}
+
+#[monoio::test]
+async fn alpn_no_overlap() {
+    let mut acceptor = common::acceptor_builder();
+    acceptor.set_alpn_select_callback(|_, client| {
+        select_next_proto(b"\x02h2", client).ok_or(AlpnError::ALERT_FATAL)
+    });
+    let acceptor: SslAcceptor = acceptor.build().into();
+    let mut connector = common::connector_builder();
+    connector.set_alpn_protos(b"\x08http/1.1").unwrap();
+    let connector: SslConnector = connector.build().into();
+
+    let (client, server) = common::tcp_pair().await;
+    let (client, server) = monoio::join!(
+        connector.connect("localhost", client),
+        acceptor.accept(server)
+    );
+    // the server sends no_application_protocol before failing.
+    assert!(server.is_err());
+    assert!(matches!(client, Err(TlsError::Ssl(_))));
+}
+
+#[monoio::test]
+async fn sni_callback() {
+    // the initial context has no certificate, the callback switches to the
+    // one with the localhost certificate.
+    let target = common::acceptor_builder().build().into_context();
+    let seen = Arc::new(AtomicBool::new(false));
+    let mut acceptor = openssl::ssl::SslAcceptor::mozilla_modern_v5(SslMethod::tls()).unwrap();
+    let seen_cb = seen.clone();
+    acceptor.set_servername_callback(move |ssl, _| {
+        if ssl.servername(NameType::HOST_NAME) != Some("localhost") {
+            return Err(SniError::ALERT_FATAL);
+        }
+        seen_cb.store(true, Ordering::Relaxed);
+        ssl.set_ssl_context(&target)
+            .map_err(|_| SniError::ALERT_FATAL)
+    });
+    let acceptor: SslAcceptor = acceptor.build().into();
+
+    let (client, server) = common::tls_pair(&common::connector(), &acceptor).await;
+    assert!(seen.load(Ordering::Relaxed));
+    assert_eq!(
+        server.ssl().servername(NameType::HOST_NAME),
+        Some("localhost")
+    );
+    assert_eq!(
+        client.ssl().peer_certificate().unwrap().to_der().unwrap(),
+        common::pki().cert.to_der().unwrap()
+    );
+
+    // unknown names are rejected.
+    let (client, server) = common::tcp_pair().await;
+    let connector = common::connector();
+    let mut ssl = connector.configure().unwrap();
+    ssl.set_verify_hostname(false);
+    let ssl = ssl.into_ssl("example.com").unwrap();
+    let (client, server) =
+        monoio::join!(connector.connect_ssl(ssl, client), acceptor.accept(server));
+    assert!(client.is_err());
+    assert!(server.is_err());
+}
+
+#[monoio::test]
+async fn export_keying_material() {
+    let (client, server) = common::tls_pair(&common::connector(), &common::acceptor()).await;
+    let mut client_key = [0; 32];
+    let mut server_key = [0; 32];
+    client
+        .export_keying_material(&mut client_key, "EXPORTER-monoio-test", Some(b"ctx"))
+        .unwrap();
+    server
+        .export_keying_material(&mut server_key, "EXPORTER-monoio-test", Some(b"ctx"))
+        .unwrap();
+    assert_eq!(client_key, server_key);
+    assert_ne!(client_key, [0; 32]);
+
+    // another label derives another key.
+    let mut other = [0; 32];
+    client
+        .export_keying_material(&mut other, "EXPORTER-monoio-other", Some(b"ctx"))
+        .unwrap();
+    assert_ne!(client_key, other);
+}
+
+#[monoio::test]
+async fn configured_buffers() {
+    let connector = common::connector()
+        .read_buffer(Some(1024))
+        .write_buffer(Some(1024));
+    let acceptor = common::acceptor()
+        .read_buffer(Some(1024))
+        .write_buffer(Some(1024));
+    let (mut client, mut server) = common::tls_pair(&connector, &acceptor).await;
+
+    let data: Vec<u8> = (0..1 << 16).map(|i| i as u8).collect();
+    let write = async {
+        let (r, _) = client.write_all(data.clone()).await;
+        r.unwrap();
+        client.shutdown().await.unwrap();
+    };
+    let read = async {
+        let mut received = Vec::new();
+        loop {
+            let (r, buf) = server.read(Vec::with_capacity(4096)).await;
+            if r.unwrap() == 0 {
+                break received;
+            }
+            received.extend_from_slice(&buf);
+        }
+    };
+    let ((), received) = monoio::join!(write, read);
+    assert!(received == data);
+}
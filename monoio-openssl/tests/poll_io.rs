@@ -0,0 +1,67 @@
+#![cfg(feature = "poll-io")]
+
+mod common;
+
+use std::time::Duration;
+
+use monoio::io::IntoPollIo;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[monoio::test]
+async fn echo() {
+    let (client, server) = common::tls_pair(&common::connector(), &common::acceptor()).await;
+    let mut client = client.into_poll_io().unwrap();
+    let mut server = server.into_poll_io().unwrap();
+
+    client.write_all(b"ping").await.unwrap();
+    client.flush().await.unwrap();
+    let mut buf = [0; 4];
+    server.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+
+    server.write_all(b"pong").await.unwrap();
+    server.flush().await.unwrap();
+    client.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"pong");
+
+    client.shutdown().await.unwrap();
+    assert_eq!(server.read(&mut buf).await.unwrap(), 0);
+}
+
+#[monoio::test]
+async fn large_write() {
+    let (client, server) = common::tls_pair(&common::connector(), &common::acceptor()).await;
+    let mut client = client.into_poll_io().unwrap();
+    let mut server = server.into_poll_io().unwrap();
+
+    let data: Vec<u8> = (0..1 << 20).map(|i| i as u8).collect();
+    let write = async {
+        client.write_all(&data).await.unwrap();
+        client.shutdown().await.unwrap();
+    };
+    let read = async {
+        let mut received = Vec::new();
+        server.read_to_end(&mut received).await.unwrap();
+        received
+    };
+    let ((), received) = monoio::join!(write, read);
+    assert!(received == data);
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn write_after_peer_closed() {
+    let (client, server) = common::tls_pair(&common::connector(), &common::acceptor()).await;
+    let mut client = client.into_poll_io().unwrap();
+    drop(server);
+
+    let write = async {
+        loop {
+            client.write_all(&[0; 16384]).await?;
+            client.flush().await?;
+        }
+    };
+    let result: std::io::Result<()> = monoio::time::timeout(Duration::from_secs(5), write)
+        .await
+        .expect("write should fail instead of spinning");
+    assert!(result.is_err());
+}
@@ -0,0 +1,234 @@
+use std::{any::Any, fmt, future::Future, net::IpAddr, pin::Pin};
+
+use rustls::{
+    crypto::cipher::{MessageEncrypter, OutboundChunks, OutboundPlainMessage},
+    pki_types::CertificateDer,
+    AlertDescription, ConnectionTrafficSecrets, ContentType, ProtocolVersion, ServerConnection,
+    SupportedCipherSuite,
+};
+
+use crate::x509::CertInfo;
+
+pub(crate) type Extension = Box<dyn Any + Send + Sync>;
+pub(crate) type AuthorizeFn =
+    dyn Fn(&PeerIdentity) -> Pin<Box<dyn Future<Output = Result<Extension, Deny>>>> + Send + Sync;
+
+/// The client as seen by the authorize hook of `TlsAcceptor`, after its
+/// certificate chain has been verified.
+///
+/// Names are read from the end-entity certificate. A client that sent no
+/// certificate(client auth is optional or disabled) has none.
+#[derive(Debug, Clone, Default)]
+pub struct PeerIdentity {
+    certificates: Vec<CertificateDer<'static>>,
+    server_name: Option<String>,
+    alpn_protocol: Option<Vec<u8>>,
+    dns_names: Vec<String>,
+    uris: Vec<String>,
+    ip_addresses: Vec<IpAddr>,
+    emails: Vec<String>,
+    common_names: Vec<String>,
+    organizations: Vec<String>,
+    organizational_units: Vec<String>,
+}
+
+impl PeerIdentity {
+    pub(crate) fn new(conn: &ServerConnection) -> Self {
+        let certificates = conn
+            .peer_certificates()
+            .map(|certs| certs.iter().map(|c| c.clone().into_owned()).collect())
+            .unwrap_or_default();
+        let mut identity = Self {
+            certificates,
+            server_name: conn.server_name().map(ToOwned::to_owned),
+            alpn_protocol: conn.alpn_protocol().map(ToOwned::to_owned),
+            ..Default::default()
+        };
+        let Some(info) = identity
+            .certificates
+            .first()
+            .and_then(|c| CertInfo::parse(c))
+        else {
+            return identity;
+        };
+        for (tag, value) in info.subject_alt_names() {
+            match tag {
+                0x81 => identity.emails.extend(ia5(value)),
+                0x82 => identity.dns_names.extend(ia5(value)),
+                0x86 => identity.uris.extend(ia5(value)),
+                0x87 => identity.ip_addresses.extend(ip(value)),
+                _ => {}
+            }
+        }
+        let attributes = |oid: &[u8]| -> Vec<String> {
+            info.subject_attributes(oid)
+                .into_iter()
+                .filter_map(|(tag, value)| directory_string(tag, value))
+                .collect()
+        };
+        identity.common_names = attributes(&[0x55, 0x04, 0x03]);
+        identity.organizations = attributes(&[0x55, 0x04, 0x0a]);
+        identity.organizational_units = attributes(&[0x55, 0x04, 0x0b]);
+        identity
+    }
+
+    /// The certificate chain sent by the client, end-entity first.
+    pub fn certificates(&self) -> &[CertificateDer<'static>] {
+        &self.certificates
+    }
+
+    pub fn end_entity(&self) -> Option<&CertificateDer<'static>> {
+        self.certificates.first()
+    }
+
+    /// The SNI sent by the client.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
+    }
+
+    /// DNS names of the subjectAltName extension.
+    pub fn dns_names(&self) -> &[String] {
+        &self.dns_names
+    }
+
+    /// URIs of the subjectAltName extension.
+    pub fn uris(&self) -> &[String] {
+        &self.uris
+    }
+
+    /// IP addresses of the subjectAltName extension.
+    pub fn ip_addresses(&self) -> &[IpAddr] {
+        &self.ip_addresses
+    }
+
+    /// Email addresses of the subjectAltName extension.
+    pub fn emails(&self) -> &[String] {
+        &self.emails
+    }
+
+    /// The SPIFFE ID, i.e. the `spiffe://` URI, if the certificate has
+    /// exactly one as the SPIFFE X.509-SVID spec requires.
+    pub fn spiffe_id(&self) -> Option<&str> {
+        match self.uris.as_slice() {
+            [uri] if uri.starts_with("spiffe://") => Some(uri),
+            _ => None,
+        }
+    }
+
+    /// Subject common names(CN).
+    pub fn common_names(&self) -> &[String] {
+        &self.common_names
+    }
+
+    /// Subject organizations(O).
+    pub fn organizations(&self) -> &[String] {
+        &self.organizations
+    }
+
+    /// Subject organizational units(OU).
+    pub fn organizational_units(&self) -> &[String] {
+        &self.organizational_units
+    }
+}
+
+/// Rejection returned by the authorize hook of `TlsAcceptor`.
+///
+/// The client gets an `access_denied` alert, and `accept` returns
+/// `TlsError::Denied` with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deny {
+    reason: String,
+}
+
+impl Deny {
+    /// The reason is reported to the caller of `accept` only, it is not sent
+    /// to the client.
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+        }
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl fmt::Display for Deny {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "access denied: {}", self.reason)
+    }
+}
+
+impl std::error::Error for Deny {}
+
+/// Encrypt an `access_denied` alert with the traffic secrets of the finished
+/// handshake. rustls has no API to send alerts once the handshake is done,
+/// so the record is built here and written to the io directly.
+///
+/// Returns `None` when the secrets were not extractable or the AEAD is
+/// unknown, then the connection is just closed.
+pub(crate) fn access_denied_record(conn: ServerConnection) -> Option<Vec<u8>> {
+    let suite = conn.negotiated_cipher_suite()?;
+    let (seq, secrets) = conn.dangerous_extract_secrets().ok()?.tx;
+    let (key, iv) = match secrets {
+        ConnectionTrafficSecrets::Aes128Gcm { key, iv }
+        | ConnectionTrafficSecrets::Aes256Gcm { key, iv }
+        | ConnectionTrafficSecrets::Chacha20Poly1305 { key, iv } => (key, iv),
+        _ => return None,
+    };
+    let mut encrypter: Box<dyn MessageEncrypter> = match suite {
+        SupportedCipherSuite::Tls13(suite) => suite.aead_alg.encrypter(key, iv),
+        #[cfg(feature = "tls12")]
+        SupportedCipherSuite::Tls12(suite) => {
+            // the extracted iv is the fixed part followed by the explicit one.
+            let fixed = suite.aead_alg.key_block_shape().fixed_iv_len;
+            let (fixed, explicit) = iv.as_ref().split_at(fixed);
+            suite.aead_alg.encrypter(key, fixed, explicit)
+        }
+        #[allow(unreachable_patterns)]
+        _ => return None,
+    };
+    let alert = [2, u8::from(AlertDescription::AccessDenied)];
+    let message = OutboundPlainMessage {
+        typ: ContentType::Alert,
+        version: ProtocolVersion::TLSv1_2,
+        payload: OutboundChunks::Single(&alert),
+    };
+    encrypter.encrypt(message, seq).ok().map(|m| m.encode())
+}
+
+fn ia5(value: &[u8]) -> Option<String> {
+    std::str::from_utf8(value)
+        .ok()
+        .filter(|s| s.is_ascii())
+        .map(ToOwned::to_owned)
+}
+
+fn ip(value: &[u8]) -> Option<IpAddr> {
+    match value.len() {
+        4 => Some(<[u8; 4]>::try_from(value).ok()?.into()),
+        16 => Some(<[u8; 16]>::try_from(value).ok()?.into()),
+        _ => None,
+    }
+}
+
+/// Decode the string types allowed in DirectoryString.
+fn directory_string(tag: u8, value: &[u8]) -> Option<String> {
+    match tag {
+        // UTF8String, PrintableString, TeletexString, IA5String
+        0x0c | 0x13 | 0x14 | 0x16 => std::str::from_utf8(value).ok().map(ToOwned::to_owned),
+        // BMPString
+        0x1e => {
+            let units = value
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]));
+            char::decode_utf16(units).collect::<Result<_, _>>().ok()
+        }
+        _ => None,
+    }
+}
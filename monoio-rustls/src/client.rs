@@ -1,9 +1,36 @@
-use std::sync::Arc;
+#[cfg(feature = "pem")]
+use std::path::Path;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    future::{poll_fn, Future},
+    io, mem, pin,
+    sync::{Arc, Mutex, PoisonError},
+};
 
-use monoio::io::{AsyncReadRent, AsyncWriteRent, OwnedReadHalf, OwnedWriteHalf};
-use rustls::{pki_types::ServerName, ClientConfig, ClientConnection};
+use monoio::{
+    io::{AsyncReadRent, AsyncWriteRent, OwnedReadHalf, OwnedWriteHalf},
+    net::TcpStream,
+};
+#[cfg(any(feature = "pem", feature = "webpki-roots", feature = "native-roots"))]
+use rustls::{client::WebPkiServerVerifier, crypto::CryptoProvider, RootCertStore};
+use rustls::{
+    client::{
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        Resumption,
+    },
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    ClientConfig, ClientConnection, DigitallySignedStruct, SignatureScheme,
+};
 
-use crate::{stream::Stream, TlsError};
+#[cfg(feature = "pem")]
+use crate::{
+    pem::{default_provider, load_root_store},
+    PemError,
+};
+use crate::{
+    stream::Stream, ConnectOptions, CtVerifier, DaneVerifier, TlsError, TlsaRecord, VerifiedSct,
+};
 
 /// A wrapper around an underlying raw stream which implements the TLS protocol.
 pub type TlsStream<IO> = Stream<IO, ClientConnection>;
@@ -16,14 +43,58 @@ pub type TlsStreamWriteHalf<IO> = OwnedWriteHalf<TlsStream<IO>>;
 #[derive(Clone)]
 pub struct TlsConnector {
     inner: Arc<ClientConfig>,
+    /// Record the stapled OCSP responses.
+    capture_ocsp: bool,
+    ct: Option<Arc<CtVerifier>>,
+    /// The verifier of the config, if set by the connector, without the
+    /// recorder on top of it.
+    verifier: Option<Arc<dyn ServerCertVerifier>>,
+    /// Configs derived for `connect_with`, shared by clones until the config
+    /// is changed.
+    derived: Arc<Mutex<HashMap<ConnectOptions, Arc<ClientConfig>>>>,
     #[cfg(feature = "unsafe_io")]
     unsafe_io: bool,
 }
 
+/// Derived configs are dropped all at once when there are too many.
+const MAX_DERIVED: usize = 64;
+
+/// Common ALPN protocol lists, see [`TlsConnector::alpn_protocols`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlpnPreset {
+    /// `http/1.1` only.
+    Http11,
+    /// `h2` only.
+    H2,
+    /// `h2`, falling back to `http/1.1`.
+    H2Http11,
+}
+
+impl AlpnPreset {
+    /// Returns the protocol names in order of preference.
+    pub fn protocols(self) -> &'static [&'static [u8]] {
+        match self {
+            AlpnPreset::Http11 => &[b"http/1.1"],
+            AlpnPreset::H2 => &[b"h2"],
+            AlpnPreset::H2Http11 => &[b"h2", b"http/1.1"],
+        }
+    }
+}
+
+impl From<AlpnPreset> for Vec<Vec<u8>> {
+    fn from(preset: AlpnPreset) -> Self {
+        preset.protocols().iter().map(|p| p.to_vec()).collect()
+    }
+}
+
 impl From<Arc<ClientConfig>> for TlsConnector {
     fn from(inner: Arc<ClientConfig>) -> TlsConnector {
         TlsConnector {
             inner,
+            capture_ocsp: false,
+            ct: None,
+            verifier: None,
+            derived: Default::default(),
             #[cfg(feature = "unsafe_io")]
             unsafe_io: false,
         }
@@ -34,6 +105,10 @@ impl From<ClientConfig> for TlsConnector {
     fn from(inner: ClientConfig) -> TlsConnector {
         TlsConnector {
             inner: Arc::new(inner),
+            capture_ocsp: false,
+            ct: None,
+            verifier: None,
+            derived: Default::default(),
             #[cfg(feature = "unsafe_io")]
             unsafe_io: false,
         }
@@ -41,6 +116,77 @@ impl From<ClientConfig> for TlsConnector {
 }
 
 impl TlsConnector {
+    /// Create a connector without client auth trusting the PEM CA
+    /// certificates in the file. Those not valid now are skipped, it fails
+    /// only if none is left.
+    ///
+    /// The process-level default `CryptoProvider` is used, so it must be
+    /// installed before.
+    #[cfg(feature = "pem")]
+    pub fn with_ca_file(path: impl AsRef<Path>) -> Result<Self, PemError> {
+        let provider = default_provider()?.clone();
+        let roots = load_root_store(path.as_ref())?;
+        Ok(webpki_connector(provider, roots)?)
+    }
+
+    /// Set the ALPN protocols offered to servers, e.g.
+    /// `connector.alpn_protocols(AlpnPreset::H2Http11)`. It replaces the ones
+    /// of the wrapped `ClientConfig`, which is cloned if shared.
+    pub fn alpn_protocols(mut self, protocols: impl Into<Vec<Vec<u8>>>) -> Self {
+        Arc::make_mut(&mut self.inner).alpn_protocols = protocols.into();
+        self.derived = Default::default();
+        self
+    }
+
+    /// Replace the server certificate verifier of the wrapped `ClientConfig`,
+    /// e.g. with a [`SpkiPinVerifier`](crate::SpkiPinVerifier). The config is
+    /// cloned if shared.
+    ///
+    /// OCSP capture and Certificate Transparency stay enabled on top of it,
+    /// whichever order the methods are called in.
+    pub fn server_cert_verifier(mut self, verifier: Arc<dyn ServerCertVerifier>) -> Self {
+        let recorder = self.recorder(verifier.clone());
+        Arc::make_mut(&mut self.inner)
+            .dangerous()
+            .set_certificate_verifier(recorder);
+        self.verifier = Some(verifier);
+        self.derived = Default::default();
+        self
+    }
+
+    /// Verify servers with the verifier and record the OCSP responses they
+    /// staple, see [`ocsp_response`](crate::ClientTlsStream::ocsp_response).
+    ///
+    /// rustls neither exposes the stapled response nor the verifier of a
+    /// `ClientConfig`, so the verifier to wrap must be passed, e.g. a
+    /// `WebPkiServerVerifier` built from the same roots. The response is not
+    /// checked.
+    pub fn capture_ocsp(mut self, verifier: Arc<dyn ServerCertVerifier>) -> Self {
+        self.capture_ocsp = true;
+        self.server_cert_verifier(verifier)
+    }
+
+    /// Enforce Certificate Transparency with the verifier, see
+    /// [`scts`](crate::ClientTlsStream::scts) for the verified SCTs.
+    ///
+    /// The verifier wrapped by the `CtVerifier` replaces the one of the
+    /// connector, and the SCTs are checked on top of whichever verifier is
+    /// set afterwards.
+    pub fn certificate_transparency(mut self, verifier: Arc<CtVerifier>) -> Self {
+        let inner = verifier.inner().clone();
+        self.ct = Some(verifier);
+        self.server_cert_verifier(inner)
+    }
+
+    /// Accept any server certificate, see
+    /// [`AcceptAnyServerCert`](crate::AcceptAnyServerCert). For local tests
+    /// only.
+    #[cfg(feature = "dangerous")]
+    pub fn danger_accept_invalid_certs(self) -> Self {
+        let provider = self.inner.crypto_provider().clone();
+        self.server_cert_verifier(Arc::new(crate::AcceptAnyServerCert::new(provider)))
+    }
+
     /// Enable unsafe-io.
     /// # Safety
     /// Users must make sure the buffer ptr and len is valid until io finished.
@@ -61,7 +207,89 @@ impl TlsConnector {
     where
         IO: AsyncReadRent + AsyncWriteRent,
     {
-        let session = ClientConnection::new(self.inner.clone(), domain)?;
+        self.connect_config(self.inner.clone(), domain, stream)
+            .await
+    }
+
+    /// Resolve `host:port`, connect to it with Happy Eyeballs(RFC 8305) and
+    /// then do the handshake. The server name is the host, a DNS name or an
+    /// IP address, in brackets for IPv6.
+    ///
+    /// See [`monoio_io_wrapper::connect_host`] for how the name is resolved
+    /// and the addresses are tried. The runtime must have timer enabled.
+    pub async fn connect_host(&self, addr: &str) -> Result<TlsStream<TcpStream>, TlsError> {
+        let (stream, host) = monoio_io_wrapper::connect_host(addr).await?;
+        let domain = ServerName::try_from(host.to_owned())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.connect(domain, stream).await
+    }
+
+    /// Connect with the options overriding the config for this connection.
+    /// `domain` is sent as SNI, and verified unless
+    /// [`ConnectOptions::verify_name`] is set.
+    ///
+    /// The derived config is cached per option set with its own session
+    /// cache, so sessions are resumed across connections with the same
+    /// options only, never with other options nor by `connect`.
+    pub async fn connect_with<IO>(
+        &self,
+        domain: ServerName<'static>,
+        stream: IO,
+        options: &ConnectOptions,
+    ) -> Result<TlsStream<IO>, TlsError>
+    where
+        IO: AsyncReadRent + AsyncWriteRent,
+    {
+        let config = self.derived_config(options)?;
+        self.connect_config(config, domain, stream).await
+    }
+
+    fn derived_config(&self, options: &ConnectOptions) -> Result<Arc<ClientConfig>, TlsError> {
+        if options.is_empty() {
+            return Ok(self.inner.clone());
+        }
+        let mut derived = self.derived.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(config) = derived.get(options) {
+            return Ok(config.clone());
+        }
+        let (mut config, verifier) = options.apply(&self.inner, self.verifier.as_ref())?;
+        if let Some(verifier) = verifier {
+            config
+                .dangerous()
+                .set_certificate_verifier(self.recorder(verifier));
+        }
+        let config = Arc::new(config);
+        if derived.len() >= MAX_DERIVED {
+            derived.clear();
+        }
+        derived.insert(options.clone(), config.clone());
+        Ok(config)
+    }
+
+    /// Wrap the verifier with a recorder if something is recorded. The
+    /// recorder must be built once per config, since rustls only resumes
+    /// sessions verified by the same verifier.
+    fn recorder(&self, verifier: Arc<dyn ServerCertVerifier>) -> Arc<dyn ServerCertVerifier> {
+        if !self.capture_ocsp && self.ct.is_none() {
+            return verifier;
+        }
+        Arc::new(Recorder {
+            inner: verifier,
+            ct: self.ct.clone(),
+            capture_ocsp: self.capture_ocsp,
+        })
+    }
+
+    async fn connect_config<IO>(
+        &self,
+        config: Arc<ClientConfig>,
+        domain: ServerName<'static>,
+        stream: IO,
+    ) -> Result<TlsStream<IO>, TlsError>
+    where
+        IO: AsyncReadRent + AsyncWriteRent,
+    {
+        let session = ClientConnection::new(config, domain)?;
         #[cfg(feature = "unsafe_io")]
         let mut stream = if self.unsafe_io {
             // # Safety
@@ -72,7 +300,159 @@ impl TlsConnector {
         };
         #[cfg(not(feature = "unsafe_io"))]
         let mut stream = Stream::new(stream, session);
-        stream.handshake().await?;
+        if !self.capture_ocsp && self.ct.is_none() {
+            stream.handshake().await?;
+            return Ok(stream);
+        }
+
+        let (result, recorded) = recording(stream.handshake()).await;
+        result?;
+        // resumed handshakes do not verify the certificate again.
+        let recorded = recorded.filter(|r| r.ocsp_response.is_some() || !r.scts.is_empty());
+        if let Some(recorded) = recorded {
+            stream.extension = Some(Box::new(recorded));
+        }
         Ok(stream)
     }
+
+    /// Connect authenticating the server with the TLSA records of this
+    /// connection only, see [`DaneVerifier`]; the verifier of the config is
+    /// not used and Certificate Transparency is not enforced. PKIX-TA and
+    /// PKIX-EE records are unusable, as RFC 7672 requires for SMTP.
+    ///
+    /// Sessions are not resumed, since resumption would skip the check
+    /// against the records.
+    pub async fn connect_with_tlsa<IO>(
+        &self,
+        domain: ServerName<'static>,
+        stream: IO,
+        records: impl IntoIterator<Item = TlsaRecord>,
+    ) -> Result<TlsStream<IO>, TlsError>
+    where
+        IO: AsyncReadRent + AsyncWriteRent,
+    {
+        let verifier = DaneVerifier::new(records, self.inner.crypto_provider().clone())?;
+        let mut config = (*self.inner).clone();
+        config.resumption = Resumption::disabled();
+        let connector = TlsConnector {
+            inner: Arc::new(config),
+            ct: None,
+            ..self.clone()
+        }
+        .server_cert_verifier(Arc::new(verifier));
+        connector.connect(domain, stream).await
+    }
+}
+
+/// Create a connector verifying servers with WebPKI against the roots, with
+/// the verifier known to the connector.
+#[cfg(any(feature = "pem", feature = "webpki-roots", feature = "native-roots"))]
+pub(crate) fn webpki_connector(
+    provider: Arc<CryptoProvider>,
+    roots: RootCertStore,
+) -> Result<TlsConnector, rustls::Error> {
+    let verifier = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .build()
+        .map_err(|e| rustls::Error::General(e.to_string()))?;
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier.clone())
+        .with_no_client_auth();
+    Ok(TlsConnector::from(config).server_cert_verifier(verifier))
+}
+
+/// What the verifiers recorded about the server, kept in the stream
+/// extension.
+#[derive(Debug)]
+pub(crate) struct Recorded {
+    pub(crate) ocsp_response: Option<Vec<u8>>,
+    pub(crate) scts: Vec<VerifiedSct>,
+}
+
+thread_local! {
+    /// The slot of the connection whose handshake is polled on this thread,
+    /// filled by the `Recorder`.
+    static RECORDING: RefCell<Option<Option<Recorded>>> = const { RefCell::new(None) };
+}
+
+/// Poll the handshake with its own slot in place, so that concurrent
+/// connections sharing a recorder never see the records of each other. The
+/// certificate is verified synchronously while the handshake is polled.
+async fn recording<F: Future>(handshake: F) -> (F::Output, Option<Recorded>) {
+    let mut handshake = pin::pin!(handshake);
+    let mut slot = Some(None);
+    let output = poll_fn(|cx| {
+        RECORDING.with(|current| mem::swap(&mut *current.borrow_mut(), &mut slot));
+        let poll = handshake.as_mut().poll(cx);
+        RECORDING.with(|current| mem::swap(&mut *current.borrow_mut(), &mut slot));
+        poll
+    })
+    .await;
+    (output, slot.flatten())
+}
+
+/// Wraps the verifier of a config to enforce Certificate Transparency and
+/// record what the server sent, since rustls does not expose the stapled
+/// OCSP response on the connection.
+#[derive(Debug)]
+struct Recorder {
+    inner: Arc<dyn ServerCertVerifier>,
+    ct: Option<Arc<CtVerifier>>,
+    capture_ocsp: bool,
+}
+impl ServerCertVerifier for Recorder {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        let scts = match &self.ct {
+            Some(ct) => ct.check(end_entity, intermediates, ocsp_response, now)?,
+            None => Vec::new(),
+        };
+        let ocsp_response =
+            (self.capture_ocsp && !ocsp_response.is_empty()).then(|| ocsp_response.to_vec());
+        RECORDING.with(|current| {
+            if let Some(slot) = current.borrow_mut().as_mut() {
+                *slot = Some(Recorded {
+                    ocsp_response,
+                    scts,
+                });
+            }
+        });
+        Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
 }
@@ -0,0 +1,359 @@
+use std::io::{self, Cursor};
+
+use monoio::io::{AsyncReadRent, AsyncWriteRent, PrefixedReadIo};
+
+use crate::{
+    codec::{split, vector},
+    server::TlsStream,
+    ClientHelloError, TlsAcceptor, TlsError,
+};
+
+/// The largest ClientHello accepted, the limit of rustls for handshake
+/// messages.
+const MAX_HELLO: usize = 0xffff;
+const MAX_FRAGMENT: usize = 16384;
+
+const CONTENT_TYPE_HANDSHAKE: u8 = 22;
+const HANDSHAKE_TYPE_CLIENT_HELLO: u8 = 1;
+const EXTENSION_SERVER_NAME: u16 = 0;
+const EXTENSION_ALPN: u16 = 16;
+
+/// An io replaying the bytes read by [`PeekedHello`] before reading on.
+pub type PeekedIo<IO> = PrefixedReadIo<IO, Cursor<Vec<u8>>>;
+
+/// A connection whose ClientHello has been read, without a TLS session, so
+/// it can be routed by SNI or ALPN: terminated here with
+/// [`accept`](Self::accept), or passed through to an upstream with
+/// [`into_parts`](Self::into_parts).
+#[derive(Debug)]
+pub struct PeekedHello<IO> {
+    io: IO,
+    buffered: Vec<u8>,
+    server_name: Option<String>,
+    alpn_protocols: Vec<Vec<u8>>,
+}
+
+impl<IO: AsyncReadRent> PeekedHello<IO> {
+    /// Read records from `io` until the ClientHello is complete, it may span
+    /// several of them.
+    ///
+    /// Anything else than a ClientHello is reported as an
+    /// `io::ErrorKind::InvalidData` error wrapping a [`ClientHelloError`].
+    pub async fn read(mut io: IO) -> io::Result<Self> {
+        let mut buffered = Vec::new();
+        loop {
+            if let Some(hello) = parse_records(&buffered)? {
+                let (server_name, alpn_protocols) =
+                    parse_hello(&hello).ok_or(ClientHelloError::Malformed)?;
+                return Ok(Self {
+                    io,
+                    buffered,
+                    server_name,
+                    alpn_protocols,
+                });
+            }
+            let (res, chunk) = io.read(Vec::with_capacity(4096)).await;
+            if res? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            buffered.extend_from_slice(&chunk);
+        }
+    }
+}
+
+impl<IO> PeekedHello<IO> {
+    /// The host name sent in the SNI extension.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// The protocols offered in the ALPN extension, in the client order.
+    pub fn alpn_protocols(&self) -> &[Vec<u8>] {
+        &self.alpn_protocols
+    }
+
+    /// The bytes read so far: the records of the ClientHello, and whatever
+    /// followed them in the last read.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffered
+    }
+
+    /// Returns the io and the buffered bytes, which must be written to the
+    /// upstream before splicing the io to it.
+    pub fn into_parts(self) -> (IO, Vec<u8>) {
+        (self.io, self.buffered)
+    }
+
+    /// Terminate TLS here, doing the handshake with `acceptor` as if the
+    /// ClientHello had not been read.
+    pub async fn accept(self, acceptor: &TlsAcceptor) -> Result<TlsStream<PeekedIo<IO>>, TlsError>
+    where
+        IO: AsyncReadRent + AsyncWriteRent,
+    {
+        let io = PrefixedReadIo::new(self.io, Cursor::new(self.buffered));
+        acceptor.accept(io).await
+    }
+}
+
+/// Join the fragments of the handshake records at the start of `buf`,
+/// returning the ClientHello body once complete.
+fn parse_records(buf: &[u8]) -> Result<Option<Vec<u8>>, ClientHelloError> {
+    let mut handshake = Vec::new();
+    let mut rest = buf;
+    loop {
+        // ContentType, ProtocolVersion, length
+        let Some((header, after)) = split::<5>(rest) else {
+            return Ok(None);
+        };
+        if header[0] != CONTENT_TYPE_HANDSHAKE || header[1] != 3 {
+            return Err(if handshake.is_empty() {
+                ClientHelloError::NotTls
+            } else {
+                ClientHelloError::Malformed
+            });
+        }
+        let len = u16::from_be_bytes([header[3], header[4]]) as usize;
+        if len == 0 || len > MAX_FRAGMENT {
+            return Err(ClientHelloError::Malformed);
+        }
+        let Some((fragment, after)) = after.split_at_checked(len) else {
+            return Ok(None);
+        };
+        handshake.extend_from_slice(fragment);
+        rest = after;
+
+        // HandshakeType, uint24 length
+        if let Some((header, body)) = split::<4>(&handshake) {
+            if header[0] != HANDSHAKE_TYPE_CLIENT_HELLO {
+                return Err(ClientHelloError::NotTls);
+            }
+            let len = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+            if len > MAX_HELLO {
+                return Err(ClientHelloError::TooLarge);
+            }
+            if body.len() >= len {
+                return Ok(Some(body[..len].to_vec()));
+            }
+        }
+    }
+}
+
+/// Returns the SNI host name and the ALPN protocols of a ClientHello body.
+fn parse_hello(hello: &[u8]) -> Option<(Option<String>, Vec<Vec<u8>>)> {
+    // legacy_version, random
+    let (_, rest) = split::<34>(hello)?;
+    let (_session_id, rest) = vector(rest, 1)?;
+    let (_cipher_suites, rest) = vector(rest, 2)?;
+    let (_compression_methods, rest) = vector(rest, 1)?;
+    let mut server_name = None;
+    let mut alpn_protocols = Vec::new();
+    if rest.is_empty() {
+        return Some((server_name, alpn_protocols));
+    }
+    let (mut extensions, rest) = vector(rest, 2)?;
+    if !rest.is_empty() {
+        return None;
+    }
+    while !extensions.is_empty() {
+        let (kind, rest) = split::<2>(extensions)?;
+        let (data, rest) = vector(rest, 2)?;
+        extensions = rest;
+        match u16::from_be_bytes(kind) {
+            EXTENSION_SERVER_NAME => server_name = parse_server_name(data)?,
+            EXTENSION_ALPN => alpn_protocols = parse_alpn(data)?,
+            _ => {}
+        }
+    }
+    Some((server_name, alpn_protocols))
+}
+
+/// The host_name entry of a ServerNameList, see RFC 6066 section 3.
+fn parse_server_name(data: &[u8]) -> Option<Option<String>> {
+    let (mut list, rest) = vector(data, 2)?;
+    if !rest.is_empty() {
+        return None;
+    }
+    while !list.is_empty() {
+        let (&name_type, rest) = list.split_first()?;
+        let (name, rest) = vector(rest, 2)?;
+        list = rest;
+        if name_type == 0 {
+            let name = std::str::from_utf8(name).ok().filter(|n| n.is_ascii())?;
+            return Some(Some(name.to_owned()));
+        }
+    }
+    Some(None)
+}
+
+/// A ProtocolNameList, see RFC 7301 section 3.1.
+fn parse_alpn(data: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let (mut list, rest) = vector(data, 2)?;
+    if !rest.is_empty() {
+        return None;
+    }
+    let mut protocols = Vec::new();
+    while !list.is_empty() {
+        let (protocol, rest) = vector(list, 1)?;
+        list = rest;
+        protocols.push(protocol.to_vec());
+    }
+    Some(protocols)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vec_u8(value: &[u8]) -> Vec<u8> {
+        [&[value.len() as u8][..], value].concat()
+    }
+
+    fn vec_u16(value: &[u8]) -> Vec<u8> {
+        [&(value.len() as u16).to_be_bytes()[..], value].concat()
+    }
+
+    fn extension(kind: u16, data: &[u8]) -> Vec<u8> {
+        [&kind.to_be_bytes()[..], &vec_u16(data)].concat()
+    }
+
+    fn sni(entries: &[(u8, &[u8])]) -> Vec<u8> {
+        let list: Vec<u8> = entries
+            .iter()
+            .flat_map(|(kind, name)| [&[*kind][..], &vec_u16(name)].concat())
+            .collect();
+        extension(EXTENSION_SERVER_NAME, &vec_u16(&list))
+    }
+
+    fn alpn(protocols: &[&[u8]]) -> Vec<u8> {
+        let list: Vec<u8> = protocols.iter().flat_map(|p| vec_u8(p)).collect();
+        extension(EXTENSION_ALPN, &vec_u16(&list))
+    }
+
+    /// A ClientHello body with the extensions, `None` for none at all.
+    fn hello(extensions: Option<&[u8]>) -> Vec<u8> {
+        let mut hello = vec![3, 3];
+        hello.extend_from_slice(&[0xaa; 32]);
+        hello.extend(vec_u8(&[0x55; 32]));
+        hello.extend(vec_u16(&[0x13, 0x01, 0x13, 0x02]));
+        hello.extend(vec_u8(&[0]));
+        if let Some(extensions) = extensions {
+            hello.extend(vec_u16(extensions));
+        }
+        hello
+    }
+
+    /// The handshake message of the body in records of at most `fragment`
+    /// bytes.
+    fn records(body: &[u8], fragment: usize) -> Vec<u8> {
+        let mut message = vec![HANDSHAKE_TYPE_CLIENT_HELLO];
+        message.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        message.extend_from_slice(body);
+        message
+            .chunks(fragment)
+            .flat_map(|chunk| [&[CONTENT_TYPE_HANDSHAKE, 3, 1][..], &vec_u16(chunk)].concat())
+            .collect()
+    }
+
+    #[test]
+    fn server_name_and_alpn() {
+        let extensions = [
+            extension(0x002b, &[2, 3, 4]),
+            sni(&[(0, b"example.com")]),
+            alpn(&[b"h2", b"http/1.1"]),
+        ]
+        .concat();
+        let (name, protocols) = parse_hello(&hello(Some(&extensions))).unwrap();
+        assert_eq!(name.as_deref(), Some("example.com"));
+        assert_eq!(protocols, [b"h2".to_vec(), b"http/1.1".to_vec()]);
+
+        assert_eq!(parse_hello(&hello(None)), Some((None, vec![])));
+        assert_eq!(parse_hello(&hello(Some(&[]))), Some((None, vec![])));
+        // only host_name entries are names.
+        let extensions = sni(&[(1, b"\x01\x02"), (0, b"example.com")]);
+        let (name, _) = parse_hello(&hello(Some(&extensions))).unwrap();
+        assert_eq!(name.as_deref(), Some("example.com"));
+        let (name, _) = parse_hello(&hello(Some(&sni(&[(1, b"x")])))).unwrap();
+        assert_eq!(name, None);
+
+        for extensions in [
+            sni(&[(0, "exämple.com".as_bytes())]),
+            // a list longer than its extension.
+            extension(EXTENSION_ALPN, &[0, 4, 2, b'h', b'2']),
+            extension(EXTENSION_SERVER_NAME, &[0, 0, 0]),
+            // a truncated extension header.
+            vec![0, 0x10, 0],
+        ] {
+            assert_eq!(parse_hello(&hello(Some(&extensions))), None);
+        }
+        // bytes after the extensions, and a truncated body.
+        let mut trailing = hello(Some(&alpn(&[b"h2"])));
+        trailing.push(0);
+        assert_eq!(parse_hello(&trailing), None);
+        assert_eq!(parse_hello(&hello(None)[..40]), None);
+    }
+
+    #[test]
+    fn fragmented() {
+        let body = hello(Some(&sni(&[(0, b"example.com")])));
+        for fragment in [1, 7, 40, MAX_FRAGMENT] {
+            let buf = records(&body, fragment);
+            assert_eq!(parse_records(&buf), Ok(Some(body.clone())), "{fragment}");
+            for len in 0..buf.len() {
+                assert_eq!(parse_records(&buf[..len]), Ok(None), "{fragment} {len}");
+            }
+            // what follows the ClientHello is not read.
+            let buf = [buf, vec![23, 3, 3, 0, 1, 0]].concat();
+            assert_eq!(parse_records(&buf), Ok(Some(body.clone())));
+        }
+    }
+
+    #[test]
+    fn not_tls() {
+        let body = hello(None);
+        for buf in [
+            b"GET / HTTP/1.1\r\n".to_vec(),
+            b"SSH-2.0-OpenSSH_9.6\r\n".to_vec(),
+            // an alert, an SSLv2 hello and a ServerHello.
+            vec![21, 3, 1, 0, 2, 2, 40],
+            vec![0x80, 0x2e, 1, 3, 1],
+            [&[22, 3, 3, 0, 4, 2, 0, 0, 0][..]].concat(),
+        ] {
+            assert_eq!(
+                parse_records(&buf),
+                Err(ClientHelloError::NotTls),
+                "{buf:?}"
+            );
+        }
+
+        // another record in the middle of the ClientHello.
+        let buf = records(&body, 20);
+        let buf = [&buf[..25], &[21, 3, 3, 0, 2, 1, 0], &buf[25..]].concat();
+        assert_eq!(parse_records(&buf), Err(ClientHelloError::Malformed));
+        for header in [[22, 3, 1, 0, 0], [22, 3, 1, 0x40, 0x01]] {
+            assert_eq!(parse_records(&header), Err(ClientHelloError::Malformed));
+        }
+    }
+
+    #[test]
+    fn oversized() {
+        let header = [
+            22,
+            3,
+            1,
+            0,
+            4,
+            HANDSHAKE_TYPE_CLIENT_HELLO,
+            0x01,
+            0x00,
+            0x00,
+        ];
+        assert_eq!(parse_records(&header), Err(ClientHelloError::TooLarge));
+        // the largest one is accepted, spanning several records.
+        let body = hello(Some(&extension(
+            0xfe0d,
+            &vec![0; MAX_HELLO - hello(Some(&[])).len() - 4],
+        )));
+        assert_eq!(body.len(), MAX_HELLO);
+        assert_eq!(parse_records(&records(&body, MAX_FRAGMENT)), Ok(Some(body)));
+    }
+}
@@ -0,0 +1,34 @@
+//! Readers of the TLS presentation language, see RFC 8446 section 3.
+
+/// Split the first `N` bytes.
+pub(crate) fn split<const N: usize>(input: &[u8]) -> Option<([u8; N], &[u8])> {
+    let (bytes, rest) = input.split_first_chunk::<N>()?;
+    Some((*bytes, rest))
+}
+
+/// Split a TLS vector with a length prefix of `n` bytes.
+pub(crate) fn vector(input: &[u8], n: usize) -> Option<(&[u8], &[u8])> {
+    let (len, input) = input.split_at_checked(n)?;
+    let len = len.iter().fold(0, |len, &b| (len << 8) | b as usize);
+    input.split_at_checked(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectors() {
+        assert_eq!(split::<2>(&[1, 2, 3]), Some(([1, 2], &[3][..])));
+        assert_eq!(split::<4>(&[1, 2, 3]), None);
+
+        assert_eq!(vector(&[2, 7, 8, 9], 1), Some((&[7, 8][..], &[9][..])));
+        assert_eq!(vector(&[0, 0, 9], 2), Some((&[][..], &[9][..])));
+        let long = [&[0, 1, 0][..], &[7; 0x100]].concat();
+        assert_eq!(vector(&long, 3), Some((&[7; 0x100][..], &[][..])));
+        // truncated length or value.
+        assert_eq!(vector(&[0], 2), None);
+        assert_eq!(vector(&[3, 7, 8], 1), None);
+        assert_eq!(vector(&[0, 1, 0, 7], 3), None);
+    }
+}
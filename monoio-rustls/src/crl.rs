@@ -0,0 +1,350 @@
+use std::{
+    error::Error,
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+    time::Duration,
+};
+
+use rustls::{
+    client::danger::HandshakeSignatureValid,
+    crypto::CryptoProvider,
+    pki_types::{pem::PemObject, CertificateDer, CertificateRevocationListDer, UnixTime},
+    server::{
+        danger::{ClientCertVerified, ClientCertVerifier},
+        ClientCertVerifierBuilder, WebPkiClientVerifier,
+    },
+    CertificateError, DigitallySignedStruct, DistinguishedName, OtherError, RootCertStore,
+    SignatureScheme,
+};
+use webpki::{CertRevocationList, OwnedCertRevocationList};
+
+use crate::{pem::load_root_store, x509::CertInfo, CrlError, TlsError};
+
+type Configure = dyn Fn(ClientCertVerifierBuilder) -> ClientCertVerifierBuilder + Send + Sync;
+type ErrorCallback = dyn Fn(&CrlError) + Send + Sync;
+
+/// A `ClientCertVerifier` checking client certificates against a CA bundle
+/// and the CRLs in a directory, which can be reloaded without rebuilding the
+/// `ServerConfig`.
+///
+/// Every file in the directory is read as PEM(`X509 CRL` sections) or DER.
+/// The CA bundle is loaded once; only the CRLs are reloaded. Keep one CRL per
+/// issuer, only the first one matching a certificate is consulted.
+///
+/// A revoked client is rejected with a [`CertRevoked`] carrying the serial
+/// number and the reason code of the CRL entry, see
+/// [`CertRevoked::from_tls_error`].
+pub struct CrlClientVerifier {
+    crl_dir: PathBuf,
+    roots: Arc<RootCertStore>,
+    provider: Arc<CryptoProvider>,
+    configure: Box<Configure>,
+    root_hint_subjects: Vec<DistinguishedName>,
+    current: RwLock<Arc<Loaded>>,
+    on_error: Option<Box<ErrorCallback>>,
+}
+
+struct Loaded {
+    verifier: Arc<dyn ClientCertVerifier>,
+    crls: Vec<CertRevocationList<'static>>,
+}
+
+impl CrlClientVerifier {
+    /// Create with the default `WebPkiClientVerifier` settings: client auth
+    /// is mandatory, and every certificate of the chain must be covered by a
+    /// CRL. If the directory holds no CRL at all, revocation is not checked.
+    pub fn new(
+        ca_path: impl AsRef<Path>,
+        crl_dir: impl Into<PathBuf>,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Self, CrlError> {
+        Self::with_builder(ca_path, crl_dir, provider, |builder| builder)
+    }
+
+    /// Create with a function configuring the builder, e.g.
+    /// `only_check_end_entity_revocation` or
+    /// `allow_unknown_revocation_status`. It is applied again on every
+    /// reload.
+    pub fn with_builder<F>(
+        ca_path: impl AsRef<Path>,
+        crl_dir: impl Into<PathBuf>,
+        provider: Arc<CryptoProvider>,
+        configure: F,
+    ) -> Result<Self, CrlError>
+    where
+        F: Fn(ClientCertVerifierBuilder) -> ClientCertVerifierBuilder + Send + Sync + 'static,
+    {
+        let crl_dir = crl_dir.into();
+        let roots = Arc::new(load_root_store(ca_path.as_ref())?);
+        let loaded = load(&crl_dir, &roots, &provider, &configure)?;
+        Ok(Self {
+            crl_dir,
+            roots,
+            provider,
+            configure: Box::new(configure),
+            root_hint_subjects: loaded.verifier.root_hint_subjects().to_vec(),
+            current: RwLock::new(Arc::new(loaded)),
+            on_error: None,
+        })
+    }
+
+    /// Set the callback to report errors of reloads done by
+    /// [`watch`](Self::watch). The current CRLs are kept on error.
+    pub fn on_error<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&CrlError) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Box::new(f));
+        self
+    }
+
+    /// Load the CRLs again and swap them in if they are all valid.
+    /// On error the current CRLs are kept.
+    pub fn reload(&self) -> Result<(), CrlError> {
+        let loaded = self.load()?;
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(loaded);
+        Ok(())
+    }
+
+    /// Reload the CRLs every `interval`. Failed reloads are reported to the
+    /// error callback. The returned future never completes, spawn it on a
+    /// runtime with timer enabled.
+    pub async fn watch(self: Arc<Self>, interval: Duration) {
+        loop {
+            monoio::time::sleep(interval).await;
+            if let Err(e) = self.reload() {
+                if let Some(on_error) = &self.on_error {
+                    on_error(&e);
+                }
+            }
+        }
+    }
+
+    fn load(&self) -> Result<Loaded, CrlError> {
+        load(&self.crl_dir, &self.roots, &self.provider, &self.configure)
+    }
+
+    fn current(&self) -> Arc<Loaded> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl Loaded {
+    /// Find the CRL entry of the first revoked certificate of the chain.
+    fn revoked(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+    ) -> Option<CertRevoked> {
+        std::iter::once(end_entity)
+            .chain(intermediates)
+            .enumerate()
+            .find_map(|(depth, cert)| {
+                let info = CertInfo::parse(cert)?;
+                self.crls
+                    .iter()
+                    .filter(|crl| crl.issuer() == info.issuer_value())
+                    .find_map(|crl| crl.find_serial(info.serial).ok().flatten())
+                    .map(|entry| CertRevoked {
+                        depth,
+                        serial: entry.serial_number.to_vec(),
+                        reason: entry
+                            .reason_code
+                            .map_or(RevocationReason::Unspecified, |r| (r as u8).into()),
+                    })
+            })
+    }
+}
+
+impl ClientCertVerifier for CrlClientVerifier {
+    fn offer_client_auth(&self) -> bool {
+        self.current().verifier.offer_client_auth()
+    }
+
+    fn client_auth_mandatory(&self) -> bool {
+        self.current().verifier.client_auth_mandatory()
+    }
+
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &self.root_hint_subjects
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        let current = self.current();
+        match current
+            .verifier
+            .verify_client_cert(end_entity, intermediates, now)
+        {
+            Err(rustls::Error::InvalidCertificate(CertificateError::Revoked)) => {
+                Err(match current.revoked(end_entity, intermediates) {
+                    Some(revoked) => CertificateError::Other(OtherError(Arc::new(revoked))).into(),
+                    None => CertificateError::Revoked.into(),
+                })
+            }
+            result => result,
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.current()
+            .verifier
+            .verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.current()
+            .verifier
+            .verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.current().verifier.supported_verify_schemes()
+    }
+}
+
+impl fmt::Debug for CrlClientVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CrlClientVerifier")
+            .field("crl_dir", &self.crl_dir)
+            .field("crls", &self.current().crls.len())
+            .finish()
+    }
+}
+
+/// The handshake error of a client whose certificate is revoked.
+///
+/// It is reported as `CertificateError::Other`, so the client receives a
+/// `certificate_unknown` alert rather than `certificate_revoked`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertRevoked {
+    /// Position of the revoked certificate in the chain, 0 is the leaf.
+    pub depth: usize,
+    /// The serial number of the revoked certificate.
+    pub serial: Vec<u8>,
+    /// The reason code of the CRL entry, `Unspecified` if absent.
+    pub reason: RevocationReason,
+}
+
+impl CertRevoked {
+    /// Returns the revocation carried by the error returned from
+    /// `TlsAcceptor::accept`, if any.
+    pub fn from_tls_error(e: &TlsError) -> Option<&CertRevoked> {
+        e.verifier_error()?.downcast_ref()
+    }
+}
+
+impl fmt::Display for CertRevoked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "certificate at depth {} is revoked: ", self.depth)?;
+        fmt::Debug::fmt(&self.reason, f)
+    }
+}
+
+impl Error for CertRevoked {}
+
+/// The reason code of a CRL entry, see RFC 5280 section 5.3.1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevocationReason {
+    Unspecified,
+    KeyCompromise,
+    CaCompromise,
+    AffiliationChanged,
+    Superseded,
+    CessationOfOperation,
+    CertificateHold,
+    RemoveFromCrl,
+    PrivilegeWithdrawn,
+    AaCompromise,
+}
+
+impl From<u8> for RevocationReason {
+    fn from(code: u8) -> Self {
+        match code {
+            1 => Self::KeyCompromise,
+            2 => Self::CaCompromise,
+            3 => Self::AffiliationChanged,
+            4 => Self::Superseded,
+            5 => Self::CessationOfOperation,
+            6 => Self::CertificateHold,
+            8 => Self::RemoveFromCrl,
+            9 => Self::PrivilegeWithdrawn,
+            10 => Self::AaCompromise,
+            _ => Self::Unspecified,
+        }
+    }
+}
+
+fn load(
+    crl_dir: &Path,
+    roots: &Arc<RootCertStore>,
+    provider: &Arc<CryptoProvider>,
+    configure: &Configure,
+) -> Result<Loaded, CrlError> {
+    let ders = load_crls(crl_dir)?;
+    let crls = ders
+        .iter()
+        .map(|(path, der)| {
+            OwnedCertRevocationList::from_der(der)
+                .map(CertRevocationList::from)
+                .map_err(|_| CrlError::InvalidCrl(path.clone()))
+        })
+        .collect::<Result<_, _>>()?;
+    let builder = WebPkiClientVerifier::builder_with_provider(roots.clone(), provider.clone())
+        .with_crls(ders.into_iter().map(|(_, der)| der));
+    let verifier = configure(builder).build()?;
+    Ok(Loaded { verifier, crls })
+}
+
+/// Read every file of the directory as PEM or DER CRLs, in name order.
+fn load_crls(
+    dir: &Path,
+) -> Result<Vec<(PathBuf, CertificateRevocationListDer<'static>)>, CrlError> {
+    let io_error = |path: &Path| {
+        let path = path.to_owned();
+        move |source| CrlError::Io { path, source }
+    };
+    let mut paths = std::fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|e| e.map(|e| e.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(io_error(dir))?;
+    paths.retain(|path| path.is_file());
+    paths.sort();
+
+    let mut crls = Vec::new();
+    for path in paths {
+        let data = std::fs::read(&path).map_err(io_error(&path))?;
+        let pem = CertificateRevocationListDer::pem_slice_iter(&data)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| CrlError::InvalidCrl(path.clone()))?;
+        if !pem.is_empty() {
+            crls.extend(pem.into_iter().map(|der| (path.clone(), der)));
+        } else if data.first() == Some(&0x30) {
+            crls.push((path, data.into()));
+        } else {
+            return Err(CrlError::InvalidCrl(path));
+        }
+    }
+    Ok(crls)
+}
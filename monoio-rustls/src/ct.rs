@@ -0,0 +1,382 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{hash::Hash, CryptoProvider},
+    pki_types::{CertificateDer, ServerName, SubjectPublicKeyInfoDer, UnixTime},
+    CertificateError, DigitallySignedStruct, OtherError, SignatureScheme,
+};
+
+use crate::{
+    codec::{split, vector},
+    pin::sha256,
+    x509::{precert_tbs, spki_parts, CertInfo, OcspInfo},
+    TlsError,
+};
+
+/// A Certificate Transparency log trusted by [`CtVerifier`].
+#[derive(Debug, Clone)]
+pub struct CtLog {
+    pub description: String,
+    /// The public key of the log. Log lists give it base64 encoded, which is
+    /// the body of a `PUBLIC KEY` PEM section.
+    pub key: SubjectPublicKeyInfoDer<'static>,
+}
+
+impl CtLog {
+    pub fn new(description: impl Into<String>, key: SubjectPublicKeyInfoDer<'static>) -> Self {
+        Self {
+            description: description.into(),
+            key,
+        }
+    }
+}
+
+/// Where an SCT was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SctSource {
+    /// Embedded in the certificate.
+    Certificate,
+    /// In the OCSP response stapled by the server.
+    OcspResponse,
+}
+
+/// An SCT whose signature has been verified with the key of a known log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedSct {
+    /// The SHA-256 of the log key.
+    pub log_id: [u8; 32],
+    pub log_description: String,
+    pub timestamp: SystemTime,
+    pub source: SctSource,
+}
+
+/// A `ServerCertVerifier` enforcing Certificate Transparency on top of
+/// another verifier.
+///
+/// Once the inner verifier accepts the chain, the SCTs embedded in the
+/// end-entity certificate and those in the stapled OCSP response are checked
+/// against the logs, and the server is rejected with a [`CtPolicyError`]
+/// unless SCTs from enough distinct logs are valid. SCTs from unknown logs,
+/// with a bad signature or a timestamp in the future are ignored.
+///
+/// Embedded SCTs are verified with the key of the issuer, which must be in
+/// the chain sent by the server. The `signed_certificate_timestamp` TLS
+/// extension is not a source: rustls 0.23 dropped its support, it neither
+/// offers the extension in the ClientHello nor exposes the SCTs of a server.
+///
+/// Install it with `TlsConnector::certificate_transparency` to get the
+/// verified SCTs on the stream.
+pub struct CtVerifier {
+    inner: Arc<dyn ServerCertVerifier>,
+    logs: HashMap<[u8; 32], Log>,
+    min_logs: usize,
+    sha256: &'static dyn Hash,
+    provider: Arc<CryptoProvider>,
+}
+
+struct Log {
+    description: String,
+    algorithm: Vec<u8>,
+    key: Vec<u8>,
+}
+
+impl CtVerifier {
+    /// Create a verifier requiring SCTs from at least 2 distinct logs. The
+    /// provider is used to verify SCT signatures and must have a TLS 1.3
+    /// cipher suite using SHA-256.
+    pub fn new(
+        inner: Arc<dyn ServerCertVerifier>,
+        logs: impl IntoIterator<Item = CtLog>,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Self, rustls::Error> {
+        let sha256 = sha256(&provider)?;
+        let logs = logs
+            .into_iter()
+            .map(|log| {
+                let (algorithm, key) = spki_parts(&log.key).ok_or_else(|| {
+                    rustls::Error::General(format!("invalid key of CT log {}", log.description))
+                })?;
+                let id = sha256
+                    .hash(&log.key)
+                    .as_ref()
+                    .try_into()
+                    .unwrap_or_default();
+                let log = Log {
+                    algorithm: algorithm.to_vec(),
+                    key: key.to_vec(),
+                    description: log.description,
+                };
+                Ok((id, log))
+            })
+            .collect::<Result<_, rustls::Error>>()?;
+        Ok(Self {
+            inner,
+            logs,
+            min_logs: 2,
+            sha256,
+            provider,
+        })
+    }
+
+    /// Set how many distinct logs must have a valid SCT for the certificate.
+    pub fn min_distinct_logs(mut self, n: usize) -> Self {
+        self.min_logs = n;
+        self
+    }
+
+    pub(crate) fn inner(&self) -> &Arc<dyn ServerCertVerifier> {
+        &self.inner
+    }
+
+    /// Check the SCTs of a chain accepted by a verifier against the policy,
+    /// returning the valid ones.
+    pub(crate) fn check(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<Vec<VerifiedSct>, rustls::Error> {
+        let scts = self.verify_scts(end_entity, intermediates, ocsp_response, now);
+        let logs = scts
+            .iter()
+            .map(|sct| sct.log_id)
+            .collect::<HashSet<_>>()
+            .len();
+        if logs < self.min_logs {
+            let e = CtPolicyError {
+                logs,
+                required: self.min_logs,
+            };
+            return Err(CertificateError::Other(OtherError(Arc::new(e))).into());
+        }
+        Ok(scts)
+    }
+
+    fn verify_scts(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Vec<VerifiedSct> {
+        let mut verified = Vec::new();
+        let Some(info) = CertInfo::parse(end_entity) else {
+            return verified;
+        };
+
+        if let Some(list) = info.sct_list() {
+            // precert_entry: the issuer key hash and the TBSCertificate.
+            let issuer = intermediates
+                .iter()
+                .filter_map(|cert| CertInfo::parse(cert))
+                .find(|cert| cert.subject == info.issuer);
+            if let (Some(issuer), Some(tbs)) = (issuer, precert_tbs(end_entity)) {
+                let mut entry = vec![0, 1];
+                entry.extend_from_slice(self.sha256.hash(issuer.spki).as_ref());
+                push_u24(&mut entry, &tbs);
+                verified.extend(self.verify_list(list, &entry, SctSource::Certificate, now));
+            }
+        }
+
+        if let Some(list) = OcspInfo::parse(ocsp_response)
+            .ok()
+            .filter(|ocsp| ocsp.serial == info.serial)
+            .and_then(|ocsp| ocsp.sct_list)
+        {
+            // x509_entry: the certificate.
+            let mut entry = vec![0, 0];
+            push_u24(&mut entry, end_entity);
+            verified.extend(self.verify_list(list, &entry, SctSource::OcspResponse, now));
+        }
+        verified
+    }
+
+    fn verify_list(
+        &self,
+        list: &[u8],
+        entry: &[u8],
+        source: SctSource,
+        now: UnixTime,
+    ) -> Vec<VerifiedSct> {
+        let Some((mut scts, _)) = vector(list, 2) else {
+            return Vec::new();
+        };
+        let mut verified = Vec::new();
+        while let Some((sct, rest)) = vector(scts, 2) {
+            scts = rest;
+            verified.extend(Sct::parse(sct).and_then(|sct| self.verify(&sct, entry, source, now)));
+        }
+        verified
+    }
+
+    fn verify(
+        &self,
+        sct: &Sct<'_>,
+        entry: &[u8],
+        source: SctSource,
+        now: UnixTime,
+    ) -> Option<VerifiedSct> {
+        let log = self.logs.get(&sct.log_id)?;
+        let timestamp = UNIX_EPOCH + Duration::from_millis(sct.timestamp);
+        if timestamp > UNIX_EPOCH + Duration::from_secs(now.as_secs()) {
+            return None;
+        }
+
+        // version v1, signature type certificate_timestamp.
+        let mut message = vec![0, 0];
+        message.extend_from_slice(&sct.timestamp.to_be_bytes());
+        message.extend_from_slice(entry);
+        message.extend_from_slice(&(sct.extensions.len() as u16).to_be_bytes());
+        message.extend_from_slice(sct.extensions);
+
+        let (_, algorithms) = self
+            .provider
+            .signature_verification_algorithms
+            .mapping
+            .iter()
+            .find(|(scheme, _)| *scheme == sct.scheme)?;
+        algorithms
+            .iter()
+            .filter(|alg| alg.public_key_alg_id().as_ref() == log.algorithm)
+            .any(|alg| {
+                alg.verify_signature(&log.key, &message, sct.signature)
+                    .is_ok()
+            })
+            .then(|| VerifiedSct {
+                log_id: sct.log_id,
+                log_description: log.description.clone(),
+                timestamp,
+                source,
+            })
+    }
+}
+
+impl ServerCertVerifier for CtVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        self.check(end_entity, intermediates, ocsp_response, now)?;
+        Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+impl fmt::Debug for CtVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CtVerifier")
+            .field("inner", &self.inner)
+            .field("logs", &self.logs.len())
+            .field("min_logs", &self.min_logs)
+            .finish()
+    }
+}
+
+/// The handshake error of a server whose certificate does not have valid
+/// SCTs from enough distinct logs.
+///
+/// It is reported as `CertificateError::Other`, so the server receives a
+/// `certificate_unknown` alert.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CtPolicyError {
+    /// The number of distinct logs with a valid SCT.
+    pub logs: usize,
+    pub required: usize,
+}
+
+impl CtPolicyError {
+    /// Returns the policy violation carried by the error returned from
+    /// `TlsConnector::connect`, if any.
+    pub fn from_tls_error(e: &TlsError) -> Option<&CtPolicyError> {
+        e.verifier_error()?.downcast_ref()
+    }
+}
+
+impl fmt::Display for CtPolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "valid SCTs from {} distinct CT logs, {} required",
+            self.logs, self.required
+        )
+    }
+}
+
+impl Error for CtPolicyError {}
+
+/// A SignedCertificateTimestamp, see RFC 6962 section 3.2.
+struct Sct<'a> {
+    log_id: [u8; 32],
+    /// Milliseconds since the epoch.
+    timestamp: u64,
+    extensions: &'a [u8],
+    scheme: SignatureScheme,
+    signature: &'a [u8],
+}
+
+impl<'a> Sct<'a> {
+    fn parse(input: &'a [u8]) -> Option<Self> {
+        // only v1 exists.
+        let (&0, rest) = input.split_first()? else {
+            return None;
+        };
+        let (log_id, rest) = split::<32>(rest)?;
+        let (timestamp, rest) = split::<8>(rest)?;
+        let (extensions, rest) = vector(rest, 2)?;
+        // the hash and signature algorithms, encoded as a TLS 1.2 SignatureScheme.
+        let (scheme, rest) = split::<2>(rest)?;
+        let (signature, rest) = vector(rest, 2)?;
+        rest.is_empty().then_some(Self {
+            log_id,
+            timestamp: u64::from_be_bytes(timestamp),
+            extensions,
+            scheme: u16::from_be_bytes(scheme).into(),
+            signature,
+        })
+    }
+}
+
+fn push_u24(out: &mut Vec<u8>, value: &[u8]) {
+    out.extend_from_slice(&(value.len() as u32).to_be_bytes()[1..]);
+    out.extend_from_slice(value);
+}
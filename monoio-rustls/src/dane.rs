@@ -0,0 +1,331 @@
+use std::{error::Error, fmt, sync::Arc};
+
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{hash::Hash, verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    CertificateError, DigitallySignedStruct, OtherError, SignatureScheme,
+};
+use sha2::{Digest, Sha512};
+use webpki::{anchor_from_trusted_cert, EndEntityCert, KeyUsage};
+
+use crate::{pin::sha256, x509::CertInfo, TlsError};
+
+/// A TLSA record, see RFC 6698 section 2.1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsaRecord {
+    /// 0 PKIX-TA, 1 PKIX-EE, 2 DANE-TA or 3 DANE-EE.
+    pub usage: u8,
+    /// 0 for the full certificate, 1 for the SubjectPublicKeyInfo.
+    pub selector: u8,
+    /// 0 for the exact data, 1 for its SHA2-256 and 2 for its SHA2-512.
+    pub matching_type: u8,
+    pub data: Vec<u8>,
+}
+
+impl TlsaRecord {
+    pub fn new(usage: u8, selector: u8, matching_type: u8, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            usage,
+            selector,
+            matching_type,
+            data: data.into(),
+        }
+    }
+}
+
+/// A `ServerCertVerifier` authenticating servers with TLSA records, which
+/// the caller must have obtained from a DNSSEC-validated resolver.
+///
+/// The server is accepted if any usable record matches, with the semantics
+/// of RFC 7671:
+/// - DANE-EE(3) matches the end-entity certificate, whose names and validity period are not
+///   checked.
+/// - DANE-TA(2) matches a certificate of the chain sent by the server other than the end-entity
+///   one, which only counts once the chain is verified up to it as a trust anchor, including the
+///   server name and the validity periods. Certificates outside of that path never match.
+/// - PKIX-EE(1) and PKIX-TA(0) match the same way as DANE-EE and DANE-TA, once the PKIX verifier
+///   set with [`pkix`](Self::pkix) has accepted the server. Without it these records are unusable,
+///   as RFC 7672 requires for SMTP. A PKIX-TA record can only match a certificate the server sent,
+///   not a root it omitted.
+///
+/// The server is rejected with a [`DaneError`] if no record is usable or
+/// none matches, or with the error of the chain verification of the last
+/// trust anchor record that matched.
+pub struct DaneVerifier {
+    records: Vec<TlsaRecord>,
+    sha256: &'static dyn Hash,
+    provider: Arc<CryptoProvider>,
+    pkix: Option<Arc<dyn ServerCertVerifier>>,
+}
+
+impl DaneVerifier {
+    /// The provider is used to verify the chains and handshake signatures,
+    /// it must have a TLS 1.3 cipher suite using SHA-256.
+    pub fn new(
+        records: impl IntoIterator<Item = TlsaRecord>,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Self, rustls::Error> {
+        Ok(Self {
+            records: records.into_iter().collect(),
+            sha256: sha256(&provider)?,
+            provider,
+            pkix: None,
+        })
+    }
+
+    /// Make PKIX-TA and PKIX-EE records usable, with the verifier deciding
+    /// whether the server passes PKIX validation.
+    pub fn pkix(mut self, verifier: Arc<dyn ServerCertVerifier>) -> Self {
+        self.pkix = Some(verifier);
+        self
+    }
+
+    fn is_usable(&self, record: &TlsaRecord) -> bool {
+        let usage = match record.usage {
+            0 | 1 => self.pkix.is_some(),
+            2 | 3 => true,
+            _ => false,
+        };
+        usage && record.selector <= 1 && record.matching_type <= 2
+    }
+
+    fn matches(&self, record: &TlsaRecord, cert: &CertificateDer<'_>) -> bool {
+        let selected = match record.selector {
+            0 => cert.as_ref(),
+            _ => match CertInfo::parse(cert) {
+                Some(info) => info.spki,
+                None => return false,
+            },
+        };
+        match record.matching_type {
+            0 => selected == record.data,
+            1 => self.sha256.hash(selected).as_ref() == record.data,
+            _ => Sha512::digest(selected).as_slice() == record.data,
+        }
+    }
+
+    /// Whether the record matches a certificate of the chain sent by the
+    /// server, other than the end-entity one, up to which the chain verifies
+    /// as a trust anchor. The error of the last failed verification is kept.
+    fn verify_anchor(
+        &self,
+        record: &TlsaRecord,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        now: UnixTime,
+        error: &mut rustls::Error,
+    ) -> bool {
+        for anchor in intermediates.iter().filter(|c| self.matches(record, c)) {
+            match self.verify_chain(end_entity, intermediates, anchor, server_name, now) {
+                Ok(()) => return true,
+                Err(e) => *error = e,
+            }
+        }
+        false
+    }
+
+    /// Verify the chain up to the DANE-TA certificate.
+    fn verify_chain(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        anchor: &CertificateDer<'_>,
+        server_name: &ServerName<'_>,
+        now: UnixTime,
+    ) -> Result<(), rustls::Error> {
+        let anchor = anchor_from_trusted_cert(anchor).map_err(pki_error)?;
+        let cert = EndEntityCert::try_from(end_entity).map_err(pki_error)?;
+        cert.verify_for_usage(
+            self.provider.signature_verification_algorithms.all,
+            &[anchor],
+            intermediates,
+            now,
+            KeyUsage::server_auth(),
+            None,
+            None,
+        )
+        .map_err(pki_error)?;
+        cert.verify_is_valid_for_subject_name(server_name)
+            .map_err(pki_error)
+    }
+
+    fn verify_pkix(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<(), rustls::Error> {
+        match &self.pkix {
+            Some(pkix) => pkix
+                .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+                .map(|_| ()),
+            None => Err(DaneError::NoUsableRecords.into()),
+        }
+    }
+}
+
+impl ServerCertVerifier for DaneVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let mut usable = self
+            .records
+            .iter()
+            .filter(|record| self.is_usable(record))
+            .peekable();
+        if usable.peek().is_none() {
+            return Err(DaneError::NoUsableRecords.into());
+        }
+
+        let mut pkix = None;
+        let mut error = DaneError::NoMatch.into();
+        for record in usable {
+            let matched = match record.usage {
+                3 => self.matches(record, end_entity),
+                2 => self.verify_anchor(
+                    record,
+                    end_entity,
+                    intermediates,
+                    server_name,
+                    now,
+                    &mut error,
+                ),
+                usage => {
+                    let cert_matches = match usage {
+                        1 => self.matches(record, end_entity),
+                        _ => self.verify_anchor(
+                            record,
+                            end_entity,
+                            intermediates,
+                            server_name,
+                            now,
+                            &mut error,
+                        ),
+                    };
+                    cert_matches
+                        && match pkix.get_or_insert_with(|| {
+                            self.verify_pkix(
+                                end_entity,
+                                intermediates,
+                                server_name,
+                                ocsp_response,
+                                now,
+                            )
+                        }) {
+                            Ok(()) => true,
+                            Err(e) => {
+                                error = e.clone();
+                                false
+                            }
+                        }
+                }
+            };
+            if matched {
+                return Ok(ServerCertVerified::assertion());
+            }
+        }
+        Err(error)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+impl fmt::Debug for DaneVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DaneVerifier")
+            .field("records", &self.records)
+            .field("pkix", &self.pkix)
+            .finish()
+    }
+}
+
+/// The handshake error of a server not authenticated by its TLSA records.
+///
+/// It is reported as `CertificateError::Other`, so the server receives a
+/// `certificate_unknown` alert.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaneError {
+    /// None of the records has a usage, selector and matching type the
+    /// verifier supports.
+    NoUsableRecords,
+    /// No usable record matches the chain sent by the server.
+    NoMatch,
+}
+
+impl DaneError {
+    /// Returns the DANE failure carried by the error returned from
+    /// `TlsConnector::connect_with_tlsa`, if any.
+    pub fn from_tls_error(e: &TlsError) -> Option<&DaneError> {
+        e.verifier_error()?.downcast_ref()
+    }
+}
+
+impl fmt::Display for DaneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DaneError::NoUsableRecords => f.write_str("no usable TLSA record"),
+            DaneError::NoMatch => f.write_str("no TLSA record matches the certificate chain"),
+        }
+    }
+}
+
+impl Error for DaneError {}
+
+impl From<DaneError> for rustls::Error {
+    fn from(e: DaneError) -> Self {
+        CertificateError::Other(OtherError(Arc::new(e))).into()
+    }
+}
+
+fn pki_error(e: webpki::Error) -> rustls::Error {
+    use webpki::Error::*;
+    match e {
+        CertExpired { .. } => CertificateError::Expired.into(),
+        CertNotValidYet { .. } => CertificateError::NotValidYet.into(),
+        CertNotValidForName(_) => CertificateError::NotValidForName.into(),
+        UnknownIssuer => CertificateError::UnknownIssuer.into(),
+        InvalidSignatureForPublicKey => CertificateError::BadSignature.into(),
+        e => rustls::Error::General(e.to_string()),
+    }
+}
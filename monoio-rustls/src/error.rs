@@ -1,20 +1,171 @@
-use std::io;
+#[cfg(feature = "pem")]
+use std::path::PathBuf;
+use std::{error::Error as StdError, io};
 
+use rustls::{CertificateError, OtherError};
 use thiserror::Error;
 
+use crate::Deny;
+
 #[derive(Error, Debug)]
 pub enum TlsError {
     #[error("io error")]
     Io(#[from] std::io::Error),
     #[error("rustls error")]
     Rustls(#[from] rustls::Error),
+    #[error("{0}")]
+    Denied(#[from] Deny),
+    /// `ConnectOptions::verify_name` is set, but the verifier of the
+    /// connector was not set with `TlsConnector::server_cert_verifier`.
+    #[error("verify_name needs a verifier set with TlsConnector::server_cert_verifier")]
+    UnknownVerifier,
 }
 
 impl From<TlsError> for io::Error {
     fn from(e: TlsError) -> Self {
         match e {
             TlsError::Io(e) => e,
-            TlsError::Rustls(e) => io::Error::new(io::ErrorKind::Other, e),
+            TlsError::Rustls(e) => io::Error::other(e),
+            TlsError::Denied(e) => io::Error::new(io::ErrorKind::PermissionDenied, e),
+            TlsError::UnknownVerifier => io::Error::new(io::ErrorKind::InvalidInput, e),
+        }
+    }
+}
+
+impl TlsError {
+    /// Returns the error of a certificate verifier, reported to rustls as
+    /// `CertificateError::Other`.
+    pub(crate) fn verifier_error(&self) -> Option<&(dyn StdError + Send + Sync + 'static)> {
+        let e = match self {
+            TlsError::Rustls(e) => e,
+            TlsError::Io(e) => e.get_ref()?.downcast_ref::<rustls::Error>()?,
+            TlsError::Denied(_) | TlsError::UnknownVerifier => return None,
+        };
+        match e {
+            rustls::Error::InvalidCertificate(CertificateError::Other(OtherError(e))) => Some(&**e),
+            _ => None,
         }
     }
 }
+
+/// The reason why loading certificates or keys from PEM files failed.
+#[cfg(feature = "pem")]
+#[derive(Error, Debug)]
+pub enum PemError {
+    #[error("unable to read pem file {path}: {source}")]
+    Pem {
+        path: PathBuf,
+        source: rustls::pki_types::pem::Error,
+    },
+    #[error("no certificate found in {0}")]
+    NoCertificate(PathBuf),
+    #[error("certificate {index} in {path} is invalid")]
+    InvalidCertificate { path: PathBuf, index: usize },
+    #[error("certificate {index} in {path} is not issued by the next one")]
+    ChainOrder { path: PathBuf, index: usize },
+    #[error("certificate {index} in {path} has expired")]
+    Expired { path: PathBuf, index: usize },
+    #[error("certificate {index} in {path} is not yet valid")]
+    NotYetValid { path: PathBuf, index: usize },
+    #[error("private key does not match the leaf certificate")]
+    KeyMismatch,
+    #[error("no process-level CryptoProvider installed")]
+    NoCryptoProvider,
+    #[error("rustls error: {0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+/// The former name of [`PemError`].
+#[cfg(feature = "pem")]
+#[deprecated(since = "0.5.0", note = "renamed to `PemError`")]
+pub type CertReloadError = PemError;
+
+/// The reason why loading a CA bundle or CRLs failed.
+#[cfg(feature = "pem")]
+#[derive(Error, Debug)]
+pub enum CrlError {
+    #[error("unable to load CA certificates: {0}")]
+    Ca(#[from] PemError),
+    #[error("unable to read {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("invalid CRL in {0}")]
+    InvalidCrl(PathBuf),
+    #[error("unable to build the client verifier: {0}")]
+    Verifier(#[from] rustls::server::VerifierBuilderError),
+}
+
+/// The reason why an OCSP response cannot be stapled.
+#[derive(Error, Debug)]
+pub enum OcspError {
+    #[error("unable to get the OCSP response: {0}")]
+    Io(#[from] io::Error),
+    #[error("malformed OCSP response")]
+    Malformed,
+    #[error("unsuccessful OCSP response status {0}")]
+    Unsuccessful(u8),
+    #[error("unable to parse the certificate")]
+    InvalidCertificate,
+    #[error("no issuer certificate after the leaf in the chain")]
+    MissingIssuer,
+    #[error("OCSP response is for another certificate")]
+    CertMismatch,
+    #[error("OCSP response has expired")]
+    Expired,
+}
+
+/// The reason why building a connector with well-known trust roots failed.
+#[derive(Error, Debug)]
+pub enum RootsError {
+    #[error("no process-level CryptoProvider installed")]
+    NoCryptoProvider,
+    /// No certificate of the system store could be loaded. Errors met while
+    /// reading it are kept.
+    #[cfg(feature = "native-roots")]
+    #[error("no usable certificate found in the system store")]
+    NoNativeRoots(Vec<rustls_native_certs::Error>),
+    #[error("rustls error: {0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+/// The reason why a connection was rejected for its PROXY protocol header.
+///
+/// It is reported as an `io::ErrorKind::InvalidData` error.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyHeaderError {
+    #[error("connection does not start with a PROXY header")]
+    Missing,
+    #[error("malformed PROXY header")]
+    Invalid,
+    #[error("unsupported PROXY protocol version {0}")]
+    UnsupportedVersion(u8),
+    /// The relayed connection is not a stream, e.g. a UDP datagram.
+    #[error("unsupported PROXY transport protocol {0}")]
+    UnsupportedTransport(u8),
+    #[error("PROXY header checksum mismatch")]
+    ChecksumMismatch,
+}
+
+impl From<ProxyHeaderError> for io::Error {
+    fn from(e: ProxyHeaderError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// The reason why a connection was rejected while reading its ClientHello.
+///
+/// It is reported as an `io::ErrorKind::InvalidData` error.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientHelloError {
+    #[error("connection does not start with a TLS ClientHello")]
+    NotTls,
+    #[error("malformed ClientHello")]
+    Malformed,
+    #[error("ClientHello is too large")]
+    TooLarge,
+}
+
+impl From<ClientHelloError> for io::Error {
+    fn from(e: ClientHelloError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
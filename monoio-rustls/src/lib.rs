@@ -1,19 +1,66 @@
 #![allow(stable_features)]
 
+mod authorize;
 mod client;
+mod client_hello;
+mod codec;
+#[cfg(feature = "pem")]
+mod crl;
+mod ct;
+mod dane;
+#[cfg(feature = "dangerous")]
+mod danger;
 mod error;
+mod ocsp;
+mod options;
+#[cfg(feature = "pem")]
+mod pem;
+mod pin;
+mod proxy_protocol;
+#[cfg(feature = "pem")]
+mod reload;
+#[cfg(any(
+    feature = "webpki-roots",
+    feature = "native-roots",
+    feature = "platform-verifier"
+))]
+mod roots;
 mod server;
+mod sni;
 mod stream;
+#[cfg(feature = "poll-io")]
+mod stream_poll;
+mod x509;
 
+pub use authorize::{Deny, PeerIdentity};
 pub use client::{
-    TlsConnector, TlsStream as ClientTlsStream, TlsStreamReadHalf as ClientTlsStreamReadHalf,
-    TlsStreamWriteHalf as ClientTlsStreamWriteHalf,
+    AlpnPreset, TlsConnector, TlsStream as ClientTlsStream,
+    TlsStreamReadHalf as ClientTlsStreamReadHalf, TlsStreamWriteHalf as ClientTlsStreamWriteHalf,
 };
-pub use error::TlsError;
+pub use client_hello::{PeekedHello, PeekedIo};
+#[cfg(feature = "pem")]
+pub use crl::{CertRevoked, CrlClientVerifier, RevocationReason};
+pub use ct::{CtLog, CtPolicyError, CtVerifier, SctSource, VerifiedSct};
+pub use dane::{DaneError, DaneVerifier, TlsaRecord};
+#[cfg(feature = "dangerous")]
+pub use danger::AcceptAnyServerCert;
+#[cfg(feature = "pem")]
+#[allow(deprecated)]
+pub use error::CertReloadError;
+pub use error::{ClientHelloError, OcspError, ProxyHeaderError, RootsError, TlsError};
+#[cfg(feature = "pem")]
+pub use error::{CrlError, PemError};
+pub use ocsp::{OcspCertStatus, OcspResponse, OcspStapler};
+pub use options::ConnectOptions;
+pub use pin::SpkiPinVerifier;
+pub use proxy_protocol::{ProxiedIo, ProxyHeader, ProxyProtocolAcceptor, SslInfo, Tlv};
+#[cfg(feature = "pem")]
+pub use reload::ReloadableCertResolver;
 pub use server::{
     TlsAcceptor, TlsStream as ServerTlsStream, TlsStreamReadHalf as ServerTlsStreamReadHalf,
     TlsStreamWriteHalf as ServerTlsStreamWriteHalf,
 };
+pub use sni::SniCertStore;
 
 /// A wrapper around an underlying raw stream which implements the TLS protocol.
 pub type TlsStream<IO> = stream::Stream<IO, rustls::Connection>;
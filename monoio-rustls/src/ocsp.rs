@@ -0,0 +1,261 @@
+use std::{
+    fmt,
+    future::Future,
+    io,
+    path::PathBuf,
+    pin::Pin,
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rustls::{
+    pki_types::CertificateDer,
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+use sha2::Digest;
+
+use crate::{
+    x509::{spki_parts, CertInfo, OcspInfo},
+    OcspError,
+};
+
+type Fetch = dyn Fn(Vec<CertificateDer<'static>>) -> Pin<Box<dyn Future<Output = io::Result<Vec<u8>>>>>
+    + Send
+    + Sync;
+type ErrorCallback = dyn Fn(&OcspError) + Send + Sync;
+type HashFn = fn(&[u8]) -> Vec<u8>;
+
+/// How long a response without nextUpdate is used before refreshing.
+const DEFAULT_REFRESH: Duration = Duration::from_secs(3600);
+
+/// Status of the certificate in an OCSP response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OcspCertStatus {
+    Good,
+    Revoked,
+    Unknown,
+}
+
+/// A parsed OCSP response. The signature is not checked, that is up to the
+/// clients it is stapled for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OcspResponse {
+    /// The DER encoded OCSPResponse.
+    pub der: Vec<u8>,
+    pub status: OcspCertStatus,
+    pub this_update: SystemTime,
+    pub next_update: Option<SystemTime>,
+}
+
+impl OcspResponse {
+    /// Parse the response and check it is about the leaf of the chain and
+    /// not expired. The CertID must match the serial number of the leaf, and
+    /// the hashes of its issuer name and of the key of the next certificate.
+    fn parse(der: Vec<u8>, chain: &[CertificateDer<'_>]) -> Result<Self, OcspError> {
+        let [leaf, issuer, ..] = chain else {
+            return Err(OcspError::MissingIssuer);
+        };
+        let cert = CertInfo::parse(leaf).ok_or(OcspError::InvalidCertificate)?;
+        let (_, issuer_key) = CertInfo::parse(issuer)
+            .and_then(|issuer| spki_parts(issuer.spki))
+            .ok_or(OcspError::InvalidCertificate)?;
+        let info = OcspInfo::parse(&der).map_err(|status| match status {
+            Some(status) => OcspError::Unsuccessful(status),
+            None => OcspError::Malformed,
+        })?;
+        let hash = cert_id_hash(info.hash_algorithm).ok_or(OcspError::CertMismatch)?;
+        if info.serial != cert.serial
+            || info.issuer_name_hash != hash(cert.issuer)
+            || info.issuer_key_hash != hash(issuer_key)
+        {
+            return Err(OcspError::CertMismatch);
+        }
+        let status = match info.status {
+            0 => OcspCertStatus::Good,
+            1 => OcspCertStatus::Revoked,
+            _ => OcspCertStatus::Unknown,
+        };
+        let response = Self {
+            status,
+            this_update: system_time(info.this_update),
+            next_update: info.next_update.map(system_time),
+            der,
+        };
+        if response.is_expired(SystemTime::now()) {
+            return Err(OcspError::Expired);
+        }
+        Ok(response)
+    }
+
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.next_update.is_some_and(|next| now > next)
+    }
+
+    /// Halfway through the validity period, or an hour after thisUpdate
+    /// if there is no nextUpdate.
+    fn refresh_at(&self) -> SystemTime {
+        match self.next_update {
+            Some(next) => {
+                let validity = next.duration_since(self.this_update).unwrap_or_default();
+                self.this_update + validity / 2
+            }
+            None => self.this_update + DEFAULT_REFRESH,
+        }
+    }
+}
+
+/// A `ResolvesServerCert` stapling an OCSP response to a certificate and
+/// refreshing it before it expires.
+///
+/// Responses come from a file kept up to date by another tool, or from an
+/// async fetcher given the certificate chain(leaf first), e.g. one posting
+/// an OCSP request to the responder of the CA. Each response must be
+/// successful, about the leaf certificate and not expired; the stapled one is
+/// dropped once it expires without a successful refresh. The chain must hold
+/// the issuer of the leaf right after it, the CertID of the response is
+/// matched against both.
+///
+/// Nothing is stapled until [`refresh`](Self::refresh) or
+/// [`run`](Self::run) has fetched a response.
+pub struct OcspStapler {
+    key: Arc<CertifiedKey>,
+    fetch: Box<Fetch>,
+    retry: Duration,
+    current: RwLock<Option<(Arc<CertifiedKey>, OcspResponse)>>,
+    on_error: Option<Box<ErrorCallback>>,
+}
+
+impl OcspStapler {
+    /// Staple the DER encoded response read from the file on every refresh.
+    ///
+    /// The file is read with the file io of the runtime, which only avoids
+    /// blocking the thread on the io_uring driver.
+    pub fn from_file(key: Arc<CertifiedKey>, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self::with_fetcher(key, move |_| monoio::fs::read(path.clone()))
+    }
+
+    /// Staple the DER encoded response returned by the fetcher.
+    pub fn with_fetcher<F, Fut>(key: Arc<CertifiedKey>, fetch: F) -> Self
+    where
+        F: Fn(Vec<CertificateDer<'static>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<Vec<u8>>> + 'static,
+    {
+        let mut key = Arc::unwrap_or_clone(key);
+        key.ocsp = None;
+        Self {
+            key: Arc::new(key),
+            fetch: Box::new(move |chain| Box::pin(fetch(chain))),
+            retry: Duration::from_secs(300),
+            current: RwLock::new(None),
+            on_error: None,
+        }
+    }
+
+    /// Set how long [`run`](Self::run) waits at least between two refreshes,
+    /// which is how long it waits after a failed one. The default is 5
+    /// minutes.
+    pub fn retry_interval(mut self, interval: Duration) -> Self {
+        self.retry = interval;
+        self
+    }
+
+    /// Set the callback to report errors of refreshes done by
+    /// [`run`](Self::run).
+    pub fn on_error<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&OcspError) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Box::new(f));
+        self
+    }
+
+    /// Fetch a response and staple it if it is valid.
+    /// On error the current one is kept until it expires.
+    pub async fn refresh(&self) -> Result<OcspResponse, OcspError> {
+        let der = (self.fetch)(self.key.cert.clone()).await?;
+        let response = OcspResponse::parse(der, &self.key.cert)?;
+
+        let mut key = (*self.key).clone();
+        key.ocsp = Some(response.der.clone());
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) =
+            Some((Arc::new(key), response.clone()));
+        Ok(response)
+    }
+
+    /// Returns the stapled response, if it is not expired.
+    pub fn response(&self) -> Option<OcspResponse> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .map(|(_, response)| response.clone())
+            .filter(|response| !response.is_expired(SystemTime::now()))
+    }
+
+    /// Returns the key served to clients, with the response stapled if there
+    /// is a valid one.
+    pub fn current(&self) -> Arc<CertifiedKey> {
+        let current = self.current.read().unwrap_or_else(PoisonError::into_inner);
+        match &*current {
+            Some((key, response)) if !response.is_expired(SystemTime::now()) => key.clone(),
+            _ => self.key.clone(),
+        }
+    }
+
+    /// Refresh halfway through the validity period of each response(an hour
+    /// after thisUpdate if there is no nextUpdate). The first refresh is done
+    /// at once unless a response is already stapled. Failed refreshes are
+    /// reported to the error callback. Refreshes are at least the retry
+    /// interval apart, even when a fetched response is already due, e.g. a
+    /// stale file. The returned future never completes, spawn it on a runtime
+    /// with timer enabled.
+    pub async fn run(self: Arc<Self>) {
+        let mut min_wait = Duration::ZERO;
+        loop {
+            let wait = self.response().map_or(Duration::ZERO, |response| {
+                response
+                    .refresh_at()
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default()
+            });
+            monoio::time::sleep(wait.max(min_wait)).await;
+            min_wait = self.retry;
+            if let Err(e) = self.refresh().await {
+                if let Some(on_error) = &self.on_error {
+                    on_error(&e);
+                }
+            }
+        }
+    }
+}
+
+impl ResolvesServerCert for OcspStapler {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+}
+
+impl fmt::Debug for OcspStapler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OcspStapler")
+            .field("response", &self.response())
+            .finish()
+    }
+}
+
+/// Returns the hash function of the CertID hashAlgorithm, SHA-1 or SHA-256.
+fn cert_id_hash(oid: &[u8]) -> Option<HashFn> {
+    const SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+    const SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+    match oid {
+        SHA1 => Some(|data| sha1::Sha1::digest(data).to_vec()),
+        SHA256 => Some(|data| sha2::Sha256::digest(data).to_vec()),
+        _ => None,
+    }
+}
+
+fn system_time(secs: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+}
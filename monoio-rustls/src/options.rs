@@ -0,0 +1,218 @@
+use std::{
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use rustls::{
+    client::{
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        ResolvesClientCert, Resumption, Tls12Resumption,
+    },
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    sign::CertifiedKey,
+    ClientConfig, DigitallySignedStruct, SignatureScheme,
+};
+
+use crate::TlsError;
+
+/// The session cache size of derived configs, the default of rustls.
+const SESSIONS: usize = 256;
+
+/// Per-connection overrides of the `ClientConfig` of a `TlsConnector`, see
+/// [`TlsConnector::connect_with`](crate::TlsConnector::connect_with).
+///
+/// Unset options keep the value of the config. Options are compared by
+/// value, except the client certificate which is compared by pointer.
+///
+/// Each option set has its own in-memory session cache, so a session is
+/// only resumed with the options it was established with. The cache follows
+/// the resumption settings of the config: none if resumption is disabled,
+/// and the same TLS 1.2 mechanisms.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    alpn_protocols: Option<Vec<Vec<u8>>>,
+    enable_sni: Option<bool>,
+    verify_name: Option<ServerName<'static>>,
+    client_cert: Option<Arc<CertifiedKey>>,
+}
+
+impl ConnectOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the ALPN protocols offered to the server.
+    pub fn alpn_protocols(mut self, protocols: impl Into<Vec<Vec<u8>>>) -> Self {
+        self.alpn_protocols = Some(protocols.into());
+        self
+    }
+
+    /// Send the server name in the SNI extension or not. It is never sent
+    /// for IP addresses.
+    pub fn sni(mut self, enabled: bool) -> Self {
+        self.enable_sni = Some(enabled);
+        self
+    }
+
+    /// Verify the server certificate against this name(or IP address)
+    /// instead of the one passed to `connect_with`, which is still sent as
+    /// SNI.
+    ///
+    /// The verifier of the connector is wrapped, so it must have been set
+    /// by the connector: with `server_cert_verifier` or the methods and
+    /// constructors using it. `connect_with` fails with
+    /// [`TlsError::UnknownVerifier`] otherwise.
+    pub fn verify_name(mut self, name: ServerName<'static>) -> Self {
+        self.verify_name = Some(name);
+        self
+    }
+
+    /// Authenticate with this client certificate.
+    pub fn client_cert(mut self, key: Arc<CertifiedKey>) -> Self {
+        self.client_cert = Some(key);
+        self
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.alpn_protocols.is_none()
+            && self.enable_sni.is_none()
+            && self.verify_name.is_none()
+            && self.client_cert.is_none()
+    }
+
+    /// Apply the options to a clone of the config with its own session
+    /// cache, returning it with its verifier if known.
+    pub(crate) fn apply(
+        &self,
+        config: &ClientConfig,
+        verifier: Option<&Arc<dyn ServerCertVerifier>>,
+    ) -> Result<(ClientConfig, Option<Arc<dyn ServerCertVerifier>>), TlsError> {
+        let mut verifier = verifier.cloned();
+        let mut config = config.clone();
+        config.resumption = fresh_resumption(&config.resumption);
+        if let Some(protocols) = &self.alpn_protocols {
+            config.alpn_protocols = protocols.clone();
+        }
+        if let Some(enabled) = self.enable_sni {
+            config.enable_sni = enabled;
+        }
+        if let Some(key) = &self.client_cert {
+            config.client_auth_cert_resolver = Arc::new(ClientCert(key.clone()));
+        }
+        if let Some(name) = &self.verify_name {
+            let inner = verifier.ok_or(TlsError::UnknownVerifier)?;
+            let name_verifier: Arc<dyn ServerCertVerifier> = Arc::new(VerifyName {
+                inner,
+                name: name.clone(),
+            });
+            config
+                .dangerous()
+                .set_certificate_verifier(name_verifier.clone());
+            verifier = Some(name_verifier);
+        }
+        Ok((config, verifier))
+    }
+}
+
+/// A new session cache with the settings of `resumption`. rustls has no
+/// getters for them, only its debug output shows them.
+fn fresh_resumption(resumption: &Resumption) -> Resumption {
+    let debug = format!("{resumption:?}");
+    if debug == format!("{:?}", Resumption::disabled()) {
+        return Resumption::disabled();
+    }
+    let tls12 = [
+        Tls12Resumption::Disabled,
+        Tls12Resumption::SessionIdOnly,
+        Tls12Resumption::SessionIdOrTickets,
+    ]
+    .into_iter()
+    .find(|mode| debug.ends_with(&format!("tls12_resumption: {mode:?} }}")))
+    .unwrap_or(Tls12Resumption::SessionIdOrTickets);
+    Resumption::in_memory_sessions(SESSIONS).tls12_resumption(tls12)
+}
+
+impl PartialEq for ConnectOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.alpn_protocols == other.alpn_protocols
+            && self.enable_sni == other.enable_sni
+            && self.verify_name == other.verify_name
+            && match (&self.client_cert, &other.client_cert) {
+                (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+    }
+}
+
+impl Eq for ConnectOptions {}
+
+impl Hash for ConnectOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.alpn_protocols.hash(state);
+        self.enable_sni.hash(state);
+        self.verify_name.hash(state);
+        self.client_cert.as_ref().map(Arc::as_ptr).hash(state);
+    }
+}
+
+/// Verify the server against a fixed name instead of the one it is
+/// connected with.
+#[derive(Debug)]
+struct VerifyName {
+    inner: Arc<dyn ServerCertVerifier>,
+    name: ServerName<'static>,
+}
+
+impl ServerCertVerifier for VerifyName {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.inner
+            .verify_server_cert(end_entity, intermediates, &self.name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Always offer the same client certificate.
+#[derive(Debug)]
+struct ClientCert(Arc<CertifiedKey>);
+
+impl ResolvesClientCert for ClientCert {
+    fn resolve(
+        &self,
+        _root_hint_subjects: &[&[u8]],
+        _sigschemes: &[SignatureScheme],
+    ) -> Option<Arc<CertifiedKey>> {
+        Some(self.0.clone())
+    }
+
+    fn has_certs(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use rustls::{
+    crypto::CryptoProvider,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, UnixTime},
+    sign::CertifiedKey,
+    InconsistentKeys,
+};
+
+use crate::{x509::CertInfo, PemError};
+
+/// Load a PEM certificate chain, leaf first.
+/// Each certificate must be valid now and issued by the next one.
+pub(crate) fn load_cert_chain(path: &Path) -> Result<Vec<CertificateDer<'static>>, PemError> {
+    let certs = load_certs(path)?;
+    let infos = parse_all(path, &certs)?;
+    for (index, pair) in infos.windows(2).enumerate() {
+        if pair[0].issuer != pair[1].subject {
+            return Err(PemError::ChainOrder {
+                path: path.to_owned(),
+                index,
+            });
+        }
+    }
+    Ok(certs)
+}
+
+/// Load a PEM private key in PKCS#1, PKCS#8 or SEC1 format.
+pub(crate) fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>, PemError> {
+    PrivateKeyDer::from_pem_file(path).map_err(|source| PemError::Pem {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+/// Load a certificate chain and the private key of its leaf.
+pub(crate) fn load_certified_key(
+    cert_path: &Path,
+    key_path: &Path,
+    provider: &CryptoProvider,
+) -> Result<CertifiedKey, PemError> {
+    certified_key(
+        load_cert_chain(cert_path)?,
+        load_private_key(key_path)?,
+        provider,
+    )
+}
+
+/// Load the private key with the provider and check it matches the leaf.
+pub(crate) fn certified_key(
+    certs: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    provider: &CryptoProvider,
+) -> Result<CertifiedKey, PemError> {
+    CertifiedKey::from_der(certs, key, provider).map_err(|e| match e {
+        rustls::Error::InconsistentKeys(InconsistentKeys::KeyMismatch) => PemError::KeyMismatch,
+        e => e.into(),
+    })
+}
+
+/// Load PEM CA certificates. Those not valid now are skipped, as they cannot
+/// anchor any chain; the first one is reported only if none is left.
+pub(crate) fn load_root_store(path: &Path) -> Result<rustls::RootCertStore, PemError> {
+    let certs = load_certs(path)?;
+    let now = UnixTime::now().as_secs() as i64;
+    let mut roots = rustls::RootCertStore::empty();
+    let mut unusable = None;
+    for (index, cert) in certs.into_iter().enumerate() {
+        let info = parse(path, index, &cert)?;
+        if let Err(e) = check_validity(path, index, &info, now) {
+            unusable.get_or_insert(e);
+            continue;
+        }
+        if roots.add(cert).is_err() {
+            return Err(PemError::InvalidCertificate {
+                path: path.to_owned(),
+                index,
+            });
+        }
+    }
+    match unusable {
+        Some(e) if roots.is_empty() => Err(e),
+        _ => Ok(roots),
+    }
+}
+
+/// Returns the process-level default provider.
+pub(crate) fn default_provider() -> Result<&'static std::sync::Arc<CryptoProvider>, PemError> {
+    CryptoProvider::get_default().ok_or(PemError::NoCryptoProvider)
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, PemError> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .map_err(|source| PemError::Pem {
+            path: path.to_owned(),
+            source,
+        })?;
+    if certs.is_empty() {
+        return Err(PemError::NoCertificate(path.to_owned()));
+    }
+    Ok(certs)
+}
+
+fn parse_all<'a>(
+    path: &Path,
+    certs: &'a [CertificateDer<'static>],
+) -> Result<Vec<CertInfo<'a>>, PemError> {
+    let now = UnixTime::now().as_secs() as i64;
+    certs
+        .iter()
+        .enumerate()
+        .map(|(index, cert)| {
+            let info = parse(path, index, cert)?;
+            check_validity(path, index, &info, now)?;
+            Ok(info)
+        })
+        .collect()
+}
+
+fn parse<'a>(
+    path: &Path,
+    index: usize,
+    cert: &'a CertificateDer<'static>,
+) -> Result<CertInfo<'a>, PemError> {
+    CertInfo::parse(cert).ok_or_else(|| PemError::InvalidCertificate {
+        path: path.to_owned(),
+        index,
+    })
+}
+
+fn check_validity(path: &Path, index: usize, info: &CertInfo, now: i64) -> Result<(), PemError> {
+    if now < info.not_before {
+        return Err(PemError::NotYetValid {
+            path: path.to_owned(),
+            index,
+        });
+    }
+    if now > info.not_after {
+        return Err(PemError::Expired {
+            path: path.to_owned(),
+            index,
+        });
+    }
+    Ok(())
+}
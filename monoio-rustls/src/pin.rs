@@ -0,0 +1,231 @@
+use std::{fmt, sync::Arc};
+
+use rustls::{
+    client::{
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        VerifierBuilderError, WebPkiServerVerifier,
+    },
+    crypto::{
+        hash::{Hash, HashAlgorithm},
+        verify_tls12_signature, verify_tls13_signature, CryptoProvider,
+    },
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    CertificateError, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use webpki::{EndEntityCert, KeyUsage, VerifiedPath};
+
+use crate::x509::{self, CertInfo};
+
+/// A `ServerCertVerifier` accepting servers whose public key is pinned.
+///
+/// A pin is the SHA-256 of a DER encoded SubjectPublicKeyInfo, the same value
+/// as `openssl x509 -pubkey -noout | openssl pkey -pubin -outform der |
+/// openssl dgst -sha256`.
+///
+/// By default only the leaf key is matched and nothing else of the
+/// certificate is checked, neither the name nor the validity period: the pin
+/// alone identifies the server. With [`webpki`](Self::webpki) the chain is
+/// verified first, and then any certificate of a path WebPKI validates from
+/// the leaf to one of the roots may match, so pinning an intermediate or a
+/// root CA works. Other certificates the server sent do not count. With
+/// [`verifier`](Self::verifier) the path it validated is unknown, so only
+/// the leaf key is matched.
+pub struct SpkiPinVerifier {
+    pins: Vec<[u8; 32]>,
+    sha256: &'static dyn Hash,
+    provider: Arc<CryptoProvider>,
+    inner: Option<Arc<dyn ServerCertVerifier>>,
+    roots: Option<Arc<RootCertStore>>,
+}
+
+impl SpkiPinVerifier {
+    /// Create a verifier matching only the pins. The provider is used to hash
+    /// keys and to verify handshake signatures, it must have a TLS 1.3 cipher
+    /// suite using SHA-256.
+    pub fn new(
+        pins: impl IntoIterator<Item = [u8; 32]>,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Self, rustls::Error> {
+        Ok(Self {
+            pins: pins.into_iter().collect(),
+            sha256: sha256(&provider)?,
+            provider,
+            inner: None,
+            roots: None,
+        })
+    }
+
+    /// Also verify the chain against the roots with WebPKI.
+    pub fn webpki(
+        self,
+        roots: impl Into<Arc<RootCertStore>>,
+    ) -> Result<Self, VerifierBuilderError> {
+        let roots = roots.into();
+        let verifier =
+            WebPkiServerVerifier::builder_with_provider(roots.clone(), self.provider.clone())
+                .build()?;
+        let mut verifier = self.verifier(verifier);
+        verifier.roots = Some(roots);
+        Ok(verifier)
+    }
+
+    /// Also verify the server with another verifier, e.g. a platform verifier.
+    /// Only the leaf key is matched then.
+    pub fn verifier(mut self, verifier: Arc<dyn ServerCertVerifier>) -> Self {
+        self.inner = Some(verifier);
+        self.roots = None;
+        self
+    }
+
+    /// Returns the pin of the certificate, or `None` if it cannot be parsed.
+    pub fn spki_pin(&self, cert: &CertificateDer<'_>) -> Option<[u8; 32]> {
+        let info = CertInfo::parse(cert)?;
+        self.sha256.hash(info.spki).as_ref().try_into().ok()
+    }
+
+    fn is_pinned(&self, cert: &CertificateDer<'_>) -> bool {
+        self.spki_pin(cert)
+            .is_some_and(|pin| self.pins.contains(&pin))
+    }
+
+    fn is_pinned_spki(&self, spki: &[u8]) -> bool {
+        let pin = self.sha256.hash(spki);
+        self.pins.iter().any(|p| p == pin.as_ref())
+    }
+
+    /// Whether a path from the leaf to one of the roots has a pinned key.
+    /// Certificates the server sent outside of the path do not count.
+    fn is_path_pinned(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        roots: &RootCertStore,
+        now: UnixTime,
+    ) -> bool {
+        let Ok(cert) = EndEntityCert::try_from(end_entity) else {
+            return false;
+        };
+        let pinned = |path: &VerifiedPath<'_>| {
+            // the trust anchor has the content of the SubjectPublicKeyInfo
+            // SEQUENCE only.
+            let anchor = x509::encode(0x30, &path.anchor().subject_public_key_info);
+            let pinned = self.is_pinned_spki(&path.end_entity().subject_public_key_info())
+                || path
+                    .intermediate_certificates()
+                    .any(|cert| self.is_pinned_spki(&cert.subject_public_key_info()))
+                || self.is_pinned_spki(&anchor);
+            match pinned {
+                true => Ok(()),
+                // keep looking for another path.
+                false => Err(webpki::Error::UnknownIssuer),
+            }
+        };
+        cert.verify_for_usage(
+            self.provider.signature_verification_algorithms.all,
+            &roots.roots,
+            intermediates,
+            now,
+            KeyUsage::server_auth(),
+            None,
+            Some(&pinned),
+        )
+        .is_ok()
+    }
+}
+
+impl ServerCertVerifier for SpkiPinVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let pinned = match &self.inner {
+            Some(inner) => {
+                inner.verify_server_cert(
+                    end_entity,
+                    intermediates,
+                    server_name,
+                    ocsp_response,
+                    now,
+                )?;
+                match &self.roots {
+                    Some(roots) => self.is_path_pinned(end_entity, intermediates, roots, now),
+                    None => self.is_pinned(end_entity),
+                }
+            }
+            None => self.is_pinned(end_entity),
+        };
+        if !pinned {
+            return Err(CertificateError::ApplicationVerificationFailure.into());
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        match &self.inner {
+            Some(inner) => inner.verify_tls12_signature(message, cert, dss),
+            None => verify_tls12_signature(
+                message,
+                cert,
+                dss,
+                &self.provider.signature_verification_algorithms,
+            ),
+        }
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        match &self.inner {
+            Some(inner) => inner.verify_tls13_signature(message, cert, dss),
+            None => verify_tls13_signature(
+                message,
+                cert,
+                dss,
+                &self.provider.signature_verification_algorithms,
+            ),
+        }
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        match &self.inner {
+            Some(inner) => inner.supported_verify_schemes(),
+            None => self
+                .provider
+                .signature_verification_algorithms
+                .supported_schemes(),
+        }
+    }
+}
+
+/// Returns the SHA-256 of the TLS 1.3 cipher suites of the provider.
+pub(crate) fn sha256(provider: &CryptoProvider) -> Result<&'static dyn Hash, rustls::Error> {
+    provider
+        .cipher_suites
+        .iter()
+        .filter_map(|suite| suite.tls13())
+        .map(|suite| suite.common.hash_provider)
+        .find(|hash| hash.algorithm() == HashAlgorithm::SHA256)
+        .ok_or_else(|| rustls::Error::General("no SHA-256 in the crypto provider".into()))
+}
+
+impl fmt::Debug for SpkiPinVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpkiPinVerifier")
+            .field("pins", &self.pins.len())
+            .field("inner", &self.inner)
+            .field("roots", &self.roots.as_ref().map(|roots| roots.len()))
+            .finish()
+    }
+}
@@ -0,0 +1,757 @@
+use std::{
+    fmt,
+    future::Future,
+    io::{self, Cursor},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    ops::Range,
+};
+
+use monoio::{
+    buf::{IoBuf, IoBufMut, IoVecBuf, IoVecBufMut},
+    io::{AsyncReadRent, AsyncWriteRent, PrefixedReadIo, Split},
+    BufResult,
+};
+use rustls::ServerConnection;
+
+use crate::{server::TlsStream, stream::Stream, ProxyHeaderError, TlsAcceptor, TlsError};
+
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+/// Maximum length of a v1 header, including the CRLF.
+const V1_MAX: usize = 107;
+
+const PP2_TYPE_ALPN: u8 = 0x01;
+const PP2_TYPE_AUTHORITY: u8 = 0x02;
+const PP2_TYPE_CRC32C: u8 = 0x03;
+const PP2_TYPE_UNIQUE_ID: u8 = 0x05;
+const PP2_TYPE_SSL: u8 = 0x20;
+const PP2_SUBTYPE_SSL_VERSION: u8 = 0x21;
+const PP2_SUBTYPE_SSL_CN: u8 = 0x22;
+const PP2_SUBTYPE_SSL_CIPHER: u8 = 0x23;
+const PP2_SUBTYPE_SSL_SIG_ALG: u8 = 0x24;
+const PP2_SUBTYPE_SSL_KEY_ALG: u8 = 0x25;
+
+/// A TLV of a v2 PROXY header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tlv {
+    pub kind: u8,
+    pub value: Vec<u8>,
+}
+
+/// The TLS information the proxy sends in the `PP2_TYPE_SSL` TLV, when it
+/// terminated TLS itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SslInfo {
+    client: u8,
+    verify: u32,
+    tlvs: Vec<Tlv>,
+}
+
+impl SslInfo {
+    /// The client connected over TLS.
+    pub fn is_tls(&self) -> bool {
+        self.client & 0x01 != 0
+    }
+
+    /// The client presented a certificate, on this connection or on the
+    /// resumed session.
+    pub fn has_client_cert(&self) -> bool {
+        self.client & 0x06 != 0
+    }
+
+    /// The client certificate was verified, if one was presented.
+    pub fn verified(&self) -> bool {
+        self.verify == 0
+    }
+
+    /// The protocol version, e.g. `TLSv1.3`.
+    pub fn version(&self) -> Option<&str> {
+        self.str(PP2_SUBTYPE_SSL_VERSION)
+    }
+
+    /// The common name of the client certificate subject.
+    pub fn common_name(&self) -> Option<&str> {
+        self.str(PP2_SUBTYPE_SSL_CN)
+    }
+
+    pub fn cipher(&self) -> Option<&str> {
+        self.str(PP2_SUBTYPE_SSL_CIPHER)
+    }
+
+    /// The signature algorithm of the server certificate.
+    pub fn sig_alg(&self) -> Option<&str> {
+        self.str(PP2_SUBTYPE_SSL_SIG_ALG)
+    }
+
+    /// The key algorithm of the server certificate.
+    pub fn key_alg(&self) -> Option<&str> {
+        self.str(PP2_SUBTYPE_SSL_KEY_ALG)
+    }
+
+    fn str(&self, kind: u8) -> Option<&str> {
+        let tlv = self.tlvs.iter().find(|tlv| tlv.kind == kind)?;
+        std::str::from_utf8(&tlv.value).ok()
+    }
+}
+
+/// A PROXY protocol header, see the
+/// [specification](https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyHeader {
+    version: u8,
+    local: bool,
+    source: Option<SocketAddr>,
+    destination: Option<SocketAddr>,
+    tlvs: Vec<Tlv>,
+    ssl: Option<SslInfo>,
+}
+
+impl ProxyHeader {
+    /// 1 for the text format, 2 for the binary one.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// The connection was made by the proxy itself(the v2 LOCAL command),
+    /// e.g. for health checks, so there is no relayed address.
+    pub fn is_local(&self) -> bool {
+        self.local
+    }
+
+    /// The address of the client. `None` for local connections and for
+    /// unknown or UNIX socket address families.
+    pub fn source(&self) -> Option<SocketAddr> {
+        self.source
+    }
+
+    /// The address the client connected to.
+    pub fn destination(&self) -> Option<SocketAddr> {
+        self.destination
+    }
+
+    /// All the TLVs of a v2 header, in order.
+    pub fn tlvs(&self) -> &[Tlv] {
+        &self.tlvs
+    }
+
+    /// The value of the first TLV of this type.
+    pub fn tlv(&self, kind: u8) -> Option<&[u8]> {
+        let tlv = self.tlvs.iter().find(|tlv| tlv.kind == kind)?;
+        Some(&tlv.value)
+    }
+
+    /// The ALPN protocol negotiated with the client by the proxy.
+    pub fn alpn(&self) -> Option<&[u8]> {
+        self.tlv(PP2_TYPE_ALPN)
+    }
+
+    /// The host name the client asked for, e.g. by SNI.
+    pub fn authority(&self) -> Option<&str> {
+        std::str::from_utf8(self.tlv(PP2_TYPE_AUTHORITY)?).ok()
+    }
+
+    /// The opaque ID the proxy assigned to the connection.
+    pub fn unique_id(&self) -> Option<&[u8]> {
+        self.tlv(PP2_TYPE_UNIQUE_ID)
+    }
+
+    pub fn ssl(&self) -> Option<&SslInfo> {
+        self.ssl.as_ref()
+    }
+}
+
+/// An io which started with a PROXY header. Reads return the bytes after
+/// it.
+pub struct ProxiedIo<IO> {
+    io: PrefixedReadIo<IO, Cursor<Vec<u8>>>,
+    header: ProxyHeader,
+}
+
+impl<IO: AsyncReadRent> ProxiedIo<IO> {
+    /// Read the v1 or v2 PROXY header at the start of `io`.
+    ///
+    /// A missing or malformed header is reported as an
+    /// `io::ErrorKind::InvalidData` error wrapping a [`ProxyHeaderError`].
+    pub async fn read_header(mut io: IO) -> io::Result<Self> {
+        let mut buf = Vec::new();
+        loop {
+            if let Some((header, len)) = parse(&buf)? {
+                let rest = buf.split_off(len);
+                return Ok(Self {
+                    io: PrefixedReadIo::new(io, Cursor::new(rest)),
+                    header,
+                });
+            }
+            let (res, chunk) = io.read(Vec::with_capacity(512)).await;
+            if res? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            buf.extend_from_slice(&chunk);
+        }
+    }
+}
+
+impl<IO> ProxiedIo<IO> {
+    pub fn header(&self) -> &ProxyHeader {
+        &self.header
+    }
+}
+
+impl<IO: AsyncReadRent> AsyncReadRent for ProxiedIo<IO> {
+    #[inline]
+    fn read<T: IoBufMut>(&mut self, buf: T) -> impl Future<Output = BufResult<usize, T>> {
+        self.io.read(buf)
+    }
+
+    #[inline]
+    fn readv<T: IoVecBufMut>(&mut self, buf: T) -> impl Future<Output = BufResult<usize, T>> {
+        self.io.readv(buf)
+    }
+}
+
+impl<IO: AsyncWriteRent> AsyncWriteRent for ProxiedIo<IO> {
+    #[inline]
+    fn write<T: IoBuf>(&mut self, buf: T) -> impl Future<Output = BufResult<usize, T>> {
+        self.io.write(buf)
+    }
+
+    #[inline]
+    fn writev<T: IoVecBuf>(&mut self, buf_vec: T) -> impl Future<Output = BufResult<usize, T>> {
+        self.io.writev(buf_vec)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> impl Future<Output = io::Result<()>> {
+        self.io.flush()
+    }
+
+    #[inline]
+    fn shutdown(&mut self) -> impl Future<Output = io::Result<()>> {
+        self.io.shutdown()
+    }
+}
+
+unsafe impl<IO: Split> Split for ProxiedIo<IO> {}
+
+impl<IO> fmt::Debug for ProxiedIo<IO> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxiedIo")
+            .field("header", &self.header)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<IO> Stream<ProxiedIo<IO>, ServerConnection> {
+    /// The PROXY header the connection started with.
+    pub fn proxy_header(&self) -> &ProxyHeader {
+        &self.io.header
+    }
+
+    /// The address of the client as relayed by the proxy, see
+    /// [`ProxyHeader::source`].
+    pub fn source_addr(&self) -> Option<SocketAddr> {
+        self.io.header.source
+    }
+}
+
+/// A `TlsAcceptor` for connections relayed by a load balancer, which start
+/// with a PROXY protocol v1 or v2 header before the TLS handshake.
+///
+/// Connections without a valid header are rejected, so this must only
+/// accept connections from the load balancer.
+#[derive(Clone)]
+pub struct ProxyProtocolAcceptor {
+    inner: TlsAcceptor,
+}
+
+impl ProxyProtocolAcceptor {
+    pub fn new(acceptor: TlsAcceptor) -> Self {
+        Self { inner: acceptor }
+    }
+
+    /// Read the PROXY header, then do the handshake over the rest of the
+    /// stream. The header is available with
+    /// [`proxy_header`](crate::ServerTlsStream::proxy_header).
+    pub async fn accept<IO>(&self, stream: IO) -> Result<TlsStream<ProxiedIo<IO>>, TlsError>
+    where
+        IO: AsyncReadRent + AsyncWriteRent,
+    {
+        let io = ProxiedIo::read_header(stream).await?;
+        self.inner.accept(io).await
+    }
+}
+
+impl From<TlsAcceptor> for ProxyProtocolAcceptor {
+    fn from(acceptor: TlsAcceptor) -> Self {
+        Self::new(acceptor)
+    }
+}
+
+/// Parse the header at the start of `buf`, returning it with its length, or
+/// `None` if more bytes are needed.
+fn parse(buf: &[u8]) -> Result<Option<(ProxyHeader, usize)>, ProxyHeaderError> {
+    if V2_SIGNATURE.starts_with(&buf[..buf.len().min(V2_SIGNATURE.len())]) {
+        return parse_v2(buf);
+    }
+    if !b"PROXY ".starts_with(&buf[..buf.len().min(6)]) {
+        return Err(ProxyHeaderError::Missing);
+    }
+    match buf.windows(2).position(|w| w == b"\r\n") {
+        Some(end) if end + 2 <= V1_MAX => {
+            let line = std::str::from_utf8(&buf[..end]).map_err(|_| ProxyHeaderError::Invalid)?;
+            Ok(Some((parse_v1(line)?, end + 2)))
+        }
+        None if buf.len() < V1_MAX => Ok(None),
+        _ => Err(ProxyHeaderError::Invalid),
+    }
+}
+
+/// PROXY TCP4 192.0.2.1 198.51.100.1 56324 443
+fn parse_v1(line: &str) -> Result<ProxyHeader, ProxyHeaderError> {
+    let mut header = ProxyHeader {
+        version: 1,
+        local: false,
+        source: None,
+        destination: None,
+        tlvs: Vec::new(),
+        ssl: None,
+    };
+    let parts: Vec<&str> = line.split(' ').collect();
+    match parts[..] {
+        ["PROXY", "UNKNOWN", ..] => Ok(header),
+        ["PROXY", protocol @ ("TCP4" | "TCP6"), src, dst, sport, dport] => {
+            let ip = |s: &str| {
+                let ip = s.parse::<IpAddr>().ok()?;
+                (ip.is_ipv4() == (protocol == "TCP4")).then_some(ip)
+            };
+            let port = |s: &str| match s.as_bytes().first() {
+                Some(b'1'..=b'9') => s.parse::<u16>().ok(),
+                _ => (s == "0").then_some(0),
+            };
+            let addr = |ip_str, port_str| Some(SocketAddr::new(ip(ip_str)?, port(port_str)?));
+            header.source = Some(addr(src, sport).ok_or(ProxyHeaderError::Invalid)?);
+            header.destination = Some(addr(dst, dport).ok_or(ProxyHeaderError::Invalid)?);
+            Ok(header)
+        }
+        _ => Err(ProxyHeaderError::Invalid),
+    }
+}
+
+fn parse_v2(buf: &[u8]) -> Result<Option<(ProxyHeader, usize)>, ProxyHeaderError> {
+    if buf.len() < 16 {
+        return Ok(None);
+    }
+    let version = buf[12] >> 4;
+    if version != 2 {
+        return Err(ProxyHeaderError::UnsupportedVersion(version));
+    }
+    let local = match buf[12] & 0x0f {
+        0 => true,
+        1 => false,
+        _ => return Err(ProxyHeaderError::Invalid),
+    };
+    let len = 16 + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+    if buf.len() < len {
+        return Ok(None);
+    }
+    let payload = &buf[16..len];
+    // The block of a local connection is discarded.
+    if local {
+        let header = ProxyHeader {
+            version,
+            local,
+            source: None,
+            destination: None,
+            tlvs: Vec::new(),
+            ssl: None,
+        };
+        return Ok(Some((header, len)));
+    }
+
+    // Only streams are relayed: UNSPEC is only valid with the UNSPEC family,
+    // where the addresses are unknown.
+    match buf[13] & 0x0f {
+        1 => {}
+        0 if buf[13] == 0 => {}
+        transport => return Err(ProxyHeaderError::UnsupportedTransport(transport)),
+    }
+    // Addresses of the UNIX family are not kept.
+    let (source, destination, addr_len) = match buf[13] >> 4 {
+        0 => (None, None, 0),
+        1 if payload.len() >= 12 => {
+            let ip = |b: &[u8]| IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(b).unwrap()));
+            let (src, dst) = socket_addrs(ip(&payload[0..4]), ip(&payload[4..8]), &payload[8..12]);
+            (Some(src), Some(dst), 12)
+        }
+        2 if payload.len() >= 36 => {
+            let ip = |b: &[u8]| IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(b).unwrap()));
+            let (src, dst) =
+                socket_addrs(ip(&payload[0..16]), ip(&payload[16..32]), &payload[32..36]);
+            (Some(src), Some(dst), 36)
+        }
+        3 if payload.len() >= 216 => (None, None, 216),
+        _ => return Err(ProxyHeaderError::Invalid),
+    };
+
+    let tlvs = tlv_ranges(&payload[addr_len..])?;
+    if let Some((_, range)) = tlvs.iter().find(|(kind, _)| *kind == PP2_TYPE_CRC32C) {
+        if range.len() != 4 {
+            return Err(ProxyHeaderError::Invalid);
+        }
+        // The checksum covers the whole header, with its own value zeroed.
+        let offset = 16 + addr_len + range.start;
+        let mut header = buf[..len].to_vec();
+        let expected = u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap());
+        header[offset..offset + 4].fill(0);
+        if crc32c(&header) != expected {
+            return Err(ProxyHeaderError::ChecksumMismatch);
+        }
+    }
+    let tlvs = to_tlvs(&payload[addr_len..], tlvs);
+    let ssl = match tlvs.iter().find(|tlv| tlv.kind == PP2_TYPE_SSL) {
+        Some(tlv) if tlv.value.len() >= 5 => Some(SslInfo {
+            client: tlv.value[0],
+            verify: u32::from_be_bytes(tlv.value[1..5].try_into().unwrap()),
+            tlvs: to_tlvs(&tlv.value[5..], tlv_ranges(&tlv.value[5..])?),
+        }),
+        Some(_) => return Err(ProxyHeaderError::Invalid),
+        None => None,
+    };
+    let header = ProxyHeader {
+        version,
+        local: false,
+        source,
+        destination,
+        tlvs,
+        ssl,
+    };
+    Ok(Some((header, len)))
+}
+
+fn socket_addrs(src: IpAddr, dst: IpAddr, ports: &[u8]) -> (SocketAddr, SocketAddr) {
+    (
+        SocketAddr::new(src, u16::from_be_bytes([ports[0], ports[1]])),
+        SocketAddr::new(dst, u16::from_be_bytes([ports[2], ports[3]])),
+    )
+}
+
+/// The types and value ranges of the TLVs in `buf`.
+fn tlv_ranges(buf: &[u8]) -> Result<Vec<(u8, Range<usize>)>, ProxyHeaderError> {
+    let mut tlvs = Vec::new();
+    let mut pos = 0;
+    while pos < buf.len() {
+        let tl = buf.get(pos..pos + 3).ok_or(ProxyHeaderError::Invalid)?;
+        let value = pos + 3..pos + 3 + u16::from_be_bytes([tl[1], tl[2]]) as usize;
+        if value.end > buf.len() {
+            return Err(ProxyHeaderError::Invalid);
+        }
+        pos = value.end;
+        tlvs.push((tl[0], value));
+    }
+    Ok(tlvs)
+}
+
+fn to_tlvs(buf: &[u8], ranges: Vec<(u8, Range<usize>)>) -> Vec<Tlv> {
+    ranges
+        .into_iter()
+        .map(|(kind, range)| Tlv {
+            kind,
+            value: buf[range].to_vec(),
+        })
+        .collect()
+}
+
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0x82f6_3b78 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v1(line: &str) -> Result<Option<(ProxyHeader, usize)>, ProxyHeaderError> {
+        parse(line.as_bytes())
+    }
+
+    /// A v2 header with the command, the family and transport byte and the
+    /// payload.
+    fn v2(command: u8, family: u8, payload: &[u8]) -> Vec<u8> {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.extend_from_slice(&[command, family]);
+        header.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        header.extend_from_slice(payload);
+        header
+    }
+
+    fn tlv(kind: u8, value: &[u8]) -> Vec<u8> {
+        let mut tlv = vec![kind];
+        tlv.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        tlv.extend_from_slice(value);
+        tlv
+    }
+
+    /// 192.0.2.1:56324 to 198.51.100.1:443.
+    const TCP4: &[u8] = &[192, 0, 2, 1, 198, 51, 100, 1, 0xdc, 0x04, 0x01, 0xbb];
+
+    fn header(buf: &[u8]) -> ProxyHeader {
+        let (header, len) = parse(buf).unwrap().unwrap();
+        assert_eq!(len, buf.len());
+        header
+    }
+
+    #[test]
+    fn v1_examples() {
+        let h = header(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n");
+        assert_eq!(h.version(), 1);
+        assert!(!h.is_local());
+        assert_eq!(h.source(), Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(h.destination(), Some("198.51.100.1:443".parse().unwrap()));
+        assert!(h.tlvs().is_empty());
+
+        let h = header(b"PROXY TCP6 2001:db8::1 ::1 0 65535\r\n");
+        assert_eq!(h.source(), Some("[2001:db8::1]:0".parse().unwrap()));
+        assert_eq!(h.destination(), Some("[::1]:65535".parse().unwrap()));
+
+        // the longest header, of 107 bytes.
+        let ffff = "ffff:".repeat(7) + "ffff";
+        let longest = format!("PROXY UNKNOWN {ffff} {ffff} 65535 65535\r\n");
+        assert_eq!(longest.len(), V1_MAX);
+        header(longest.as_bytes());
+        let tcp6 = format!("PROXY TCP6 {ffff} {ffff} 65535 65535\r\n");
+        assert!(header(tcp6.as_bytes()).source().is_some());
+
+        for unknown in ["PROXY UNKNOWN\r\n", "PROXY UNKNOWN ::1 ::1 1 2\r\n"] {
+            let h = header(unknown.as_bytes());
+            assert_eq!((h.source(), h.destination()), (None, None));
+        }
+
+        for invalid in [
+            "PROXY TCP4 ::1 ::1 1 2\r\n",
+            "PROXY TCP6 192.0.2.1 192.0.2.2 1 2\r\n",
+            "PROXY TCP4 192.0.2.1 192.0.2.2 01 2\r\n",
+            "PROXY TCP4 192.0.2.1 192.0.2.2 1 65536\r\n",
+            "PROXY TCP4 192.0.2.1 192.0.2.2 1\r\n",
+            "PROXY TCP4 192.0.2.1 192.0.2.2 1 2 3\r\n",
+            "PROXY TCP4  192.0.2.1 192.0.2.2 1 2\r\n",
+            "PROXY UDP4 192.0.2.1 192.0.2.2 1 2\r\n",
+            "PROXY \r\n",
+        ] {
+            assert_eq!(
+                v1(invalid).unwrap_err(),
+                ProxyHeaderError::Invalid,
+                "{invalid}"
+            );
+        }
+        let too_long = format!("PROXY UNKNOWN {ffff} {ffff} 65535 655350\r\n");
+        assert_eq!(v1(&too_long).unwrap_err(), ProxyHeaderError::Invalid);
+        assert_eq!(
+            v1(&too_long[..V1_MAX]).unwrap_err(),
+            ProxyHeaderError::Invalid
+        );
+    }
+
+    #[test]
+    fn not_proxy() {
+        for buf in [
+            &b"GET / HTTP/1.1\r\n"[..],
+            b"proxy TCP4",
+            b"\x16\x03\x01",
+            b"\r\n\r\n\0\r\nquit",
+        ] {
+            assert_eq!(
+                parse(buf).unwrap_err(),
+                ProxyHeaderError::Missing,
+                "{buf:?}"
+            );
+        }
+        let mut v3 = v2(0x21, 0x11, TCP4);
+        v3[12] = 0x31;
+        assert_eq!(
+            parse(&v3).unwrap_err(),
+            ProxyHeaderError::UnsupportedVersion(3)
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        let full = [
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n".to_vec(),
+            v2(
+                0x21,
+                0x11,
+                &[TCP4, &tlv(PP2_TYPE_AUTHORITY, b"example.com")].concat(),
+            ),
+        ];
+        for full in full {
+            for len in 0..full.len() {
+                assert_eq!(parse(&full[..len]), Ok(None), "{len}");
+            }
+            assert!(parse(&full).unwrap().is_some());
+        }
+    }
+
+    #[test]
+    fn bytes_after_header() {
+        let mut buf = b"PROXY UNKNOWN\r\n".to_vec();
+        buf.extend_from_slice(b"\x16\x03\x01");
+        assert_eq!(parse(&buf).unwrap().unwrap().1, 15);
+
+        let header = v2(0x21, 0x11, TCP4);
+        let len = header.len();
+        let buf = [header, b"\x16\x03\x01".to_vec()].concat();
+        assert_eq!(parse(&buf).unwrap().unwrap().1, len);
+    }
+
+    #[test]
+    fn v2_addresses() {
+        let h = header(&v2(0x21, 0x11, TCP4));
+        assert_eq!(h.version(), 2);
+        assert!(!h.is_local());
+        assert_eq!(h.source(), Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(h.destination(), Some("198.51.100.1:443".parse().unwrap()));
+
+        let mut tcp6 = [0; 36];
+        tcp6[15] = 1;
+        tcp6[16..18].copy_from_slice(&[0x20, 0x01]);
+        tcp6[32..36].copy_from_slice(&[0, 80, 1, 187]);
+        let h = header(&v2(0x21, 0x21, &tcp6));
+        assert_eq!(h.source(), Some("[::1]:80".parse().unwrap()));
+        assert_eq!(h.destination(), Some("[2001::]:443".parse().unwrap()));
+
+        // UNIX and UNSPEC addresses are not kept.
+        for (family, payload) in [(0x31, &[0; 216][..]), (0x00, &[])] {
+            let h = header(&v2(0x21, family, payload));
+            assert_eq!((h.source(), h.destination()), (None, None));
+        }
+
+        // LOCAL ignores the rest of the block.
+        let h = header(&v2(0x20, 0x12, &[1, 2, 3]));
+        assert!(h.is_local());
+        assert_eq!(h.source(), None);
+
+        for (command, family, payload) in [
+            (0x22, 0x11, TCP4),
+            (0x21, 0x11, &TCP4[..11]),
+            (0x21, 0x21, TCP4),
+            (0x21, 0x41, TCP4),
+        ] {
+            assert_eq!(
+                parse(&v2(command, family, payload)).unwrap_err(),
+                ProxyHeaderError::Invalid
+            );
+        }
+    }
+
+    #[test]
+    fn v2_transports() {
+        // DGRAM, also with the UNSPEC family, and unknown transports.
+        for (family, transport) in [(0x12, 2), (0x22, 2), (0x02, 2), (0x10, 0), (0x13, 3)] {
+            assert_eq!(
+                parse(&v2(0x21, family, &[0; 36])).unwrap_err(),
+                ProxyHeaderError::UnsupportedTransport(transport),
+                "{family:#x}"
+            );
+        }
+    }
+
+    #[test]
+    fn v2_tlvs() {
+        let payload = [
+            TCP4,
+            &tlv(PP2_TYPE_ALPN, b"h2"),
+            &tlv(PP2_TYPE_AUTHORITY, b"example.com"),
+            &tlv(PP2_TYPE_UNIQUE_ID, &[1, 2, 3]),
+            &tlv(0xe0, b""),
+            &tlv(PP2_TYPE_ALPN, b"http/1.1"),
+        ]
+        .concat();
+        let h = header(&v2(0x21, 0x11, &payload));
+        assert_eq!(h.tlvs().len(), 5);
+        assert_eq!(
+            h.tlvs()[3],
+            Tlv {
+                kind: 0xe0,
+                value: vec![]
+            }
+        );
+        // the first TLV of a type wins.
+        assert_eq!(h.alpn(), Some(&b"h2"[..]));
+        assert_eq!(h.authority(), Some("example.com"));
+        assert_eq!(h.unique_id(), Some(&[1, 2, 3][..]));
+        assert_eq!(h.ssl(), None);
+
+        // a TLV overflowing the block, or a truncated type and length.
+        for tlvs in [&[PP2_TYPE_ALPN, 0, 3, b'h', b'2'][..], &[PP2_TYPE_ALPN, 0]] {
+            let buf = v2(0x21, 0x11, &[TCP4, tlvs].concat());
+            assert_eq!(parse(&buf).unwrap_err(), ProxyHeaderError::Invalid);
+        }
+    }
+
+    #[test]
+    fn v2_ssl() {
+        let sub_tlvs = [
+            tlv(PP2_SUBTYPE_SSL_VERSION, b"TLSv1.3"),
+            tlv(PP2_SUBTYPE_SSL_CN, b"client"),
+            tlv(PP2_SUBTYPE_SSL_CIPHER, b"TLS_AES_128_GCM_SHA256"),
+            tlv(PP2_SUBTYPE_SSL_SIG_ALG, b"SHA256"),
+            tlv(PP2_SUBTYPE_SSL_KEY_ALG, b"EC256"),
+        ]
+        .concat();
+        let ssl = [&[0x05][..], &[0, 0, 0, 0], &sub_tlvs].concat();
+        let h = header(&v2(0x21, 0x11, &[TCP4, &tlv(PP2_TYPE_SSL, &ssl)].concat()));
+        let ssl = h.ssl().unwrap();
+        assert!(ssl.is_tls());
+        assert!(ssl.has_client_cert());
+        assert!(ssl.verified());
+        assert_eq!(ssl.version(), Some("TLSv1.3"));
+        assert_eq!(ssl.common_name(), Some("client"));
+        assert_eq!(ssl.cipher(), Some("TLS_AES_128_GCM_SHA256"));
+        assert_eq!(ssl.sig_alg(), Some("SHA256"));
+        assert_eq!(ssl.key_alg(), Some("EC256"));
+
+        // plain TCP, and a certificate that failed verification.
+        let ssl = tlv(PP2_TYPE_SSL, &[0x00, 0, 0, 0, 0]);
+        let h = header(&v2(0x21, 0x11, &[TCP4, &ssl].concat()));
+        assert!(!h.ssl().unwrap().is_tls());
+        assert_eq!(h.ssl().unwrap().version(), None);
+        let ssl = tlv(PP2_TYPE_SSL, &[0x03, 0, 0, 0, 1]);
+        let ssl = header(&v2(0x21, 0x11, &[TCP4, &ssl].concat()))
+            .ssl()
+            .cloned();
+        assert!(!ssl.unwrap().verified());
+
+        for value in [
+            &[0x01, 0, 0, 0][..],
+            &[0x01, 0, 0, 0, 0, PP2_SUBTYPE_SSL_CN, 0, 9],
+        ] {
+            let buf = v2(0x21, 0x11, &[TCP4, &tlv(PP2_TYPE_SSL, value)].concat());
+            assert_eq!(parse(&buf).unwrap_err(), ProxyHeaderError::Invalid);
+        }
+    }
+
+    #[test]
+    fn crc32c_checksum() {
+        // the check value of CRC-32C.
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+        assert_eq!(crc32c(b""), 0);
+        assert_eq!(crc32c(&[0; 32]), 0x8a91_36aa);
+
+        let mut buf = v2(0x21, 0x11, &[TCP4, &tlv(PP2_TYPE_CRC32C, &[0; 4])].concat());
+        let crc = crc32c(&buf);
+        let len = buf.len();
+        buf[len - 4..].copy_from_slice(&crc.to_be_bytes());
+        let h = header(&buf);
+        assert_eq!(h.tlv(PP2_TYPE_CRC32C), Some(&crc.to_be_bytes()[..]));
+
+        buf[20] ^= 1;
+        assert_eq!(parse(&buf).unwrap_err(), ProxyHeaderError::ChecksumMismatch);
+        let buf = v2(0x21, 0x11, &[TCP4, &tlv(PP2_TYPE_CRC32C, &[0; 3])].concat());
+        assert_eq!(parse(&buf).unwrap_err(), ProxyHeaderError::Invalid);
+    }
+}
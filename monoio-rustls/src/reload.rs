@@ -0,0 +1,129 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use rustls::{
+    crypto::CryptoProvider,
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+};
+
+use crate::{pem::load_certified_key, PemError};
+
+type ErrorCallback = dyn Fn(&PemError) + Send + Sync;
+
+/// A `ResolvesServerCert` serving a PEM certificate chain and private key
+/// loaded from disk, which can be reloaded without rebuilding the
+/// `ServerConfig`.
+///
+/// A new pair is validated(the chain must be in order and valid now, and the
+/// key must match the leaf) before it replaces the current one. Handshakes
+/// started after the swap use the new pair.
+pub struct ReloadableCertResolver {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    provider: Arc<CryptoProvider>,
+    current: RwLock<Arc<CertifiedKey>>,
+    on_error: Option<Box<ErrorCallback>>,
+}
+
+impl ReloadableCertResolver {
+    /// Load the certificate chain and key with the given crypto provider.
+    pub fn new(
+        cert_path: impl Into<PathBuf>,
+        key_path: impl Into<PathBuf>,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Self, PemError> {
+        let cert_path = cert_path.into();
+        let key_path = key_path.into();
+        let current = load_certified_key(&cert_path, &key_path, &provider)?;
+        Ok(Self {
+            cert_path,
+            key_path,
+            provider,
+            current: RwLock::new(Arc::new(current)),
+            on_error: None,
+        })
+    }
+
+    /// Set the callback to report errors of reloads done by
+    /// [`watch`](Self::watch). The current pair is kept on error.
+    pub fn on_error<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&PemError) + Send + Sync + 'static,
+    {
+        self.on_error = Some(Box::new(f));
+        self
+    }
+
+    /// Load the files again and swap in the new pair if it is valid.
+    /// On error the current pair is kept.
+    pub fn reload(&self) -> Result<(), PemError> {
+        let new = load_certified_key(&self.cert_path, &self.key_path, &self.provider)?;
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(new);
+        Ok(())
+    }
+
+    /// Returns the pair used by new handshakes.
+    pub fn current(&self) -> Arc<CertifiedKey> {
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Reload whenever the files change.
+    ///
+    /// Files are checked every `interval` by their modification time. A failed
+    /// reload(e.g. only the certificate has been written so far) is retried
+    /// on every check until it succeeds, and reported to the error callback
+    /// once per change of the files. The returned future never completes,
+    /// spawn it on a runtime with timer enabled.
+    pub async fn watch(self: Arc<Self>, interval: Duration) {
+        let mut loaded = self.modified();
+        let mut reported = None;
+        loop {
+            monoio::time::sleep(interval).await;
+            let modified = self.modified();
+            if modified == loaded {
+                continue;
+            }
+            match self.reload() {
+                Ok(()) => {
+                    loaded = modified;
+                    reported = None;
+                }
+                Err(e) if reported != Some(modified) => {
+                    if let Some(on_error) = &self.on_error {
+                        on_error(&e);
+                    }
+                    reported = Some(modified);
+                }
+                Err(_) => (),
+            }
+        }
+    }
+
+    fn modified(&self) -> [Option<SystemTime>; 2] {
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        [modified(&self.cert_path), modified(&self.key_path)]
+    }
+}
+
+impl ResolvesServerCert for ReloadableCertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+}
+
+impl fmt::Debug for ReloadableCertResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReloadableCertResolver")
+            .field("cert_path", &self.cert_path)
+            .field("key_path", &self.key_path)
+            .finish()
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+#[cfg(any(feature = "webpki-roots", feature = "native-roots"))]
+use rustls::RootCertStore;
+use rustls::crypto::CryptoProvider;
+#[cfg(feature = "platform-verifier")]
+use rustls::ClientConfig;
+
+#[cfg(any(feature = "webpki-roots", feature = "native-roots"))]
+use crate::client::webpki_connector;
+use crate::{RootsError, TlsConnector};
+
+impl TlsConnector {
+    /// Create a connector without client auth trusting the Mozilla root
+    /// certificates bundled by `webpki-roots`.
+    ///
+    /// The process-level default `CryptoProvider` is used, so it must be
+    /// installed before.
+    #[cfg(feature = "webpki-roots")]
+    pub fn with_webpki_roots() -> Result<Self, RootsError> {
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        with_roots(roots)
+    }
+
+    /// Create a connector without client auth trusting the certificates of
+    /// the system store, e.g. `/etc/ssl/certs` on Linux. `SSL_CERT_FILE` and
+    /// `SSL_CERT_DIR` override the default locations.
+    ///
+    /// Certificates that fail to parse are skipped; it is an error only if
+    /// none can be used. The store is read on every call, so reuse the
+    /// connector.
+    ///
+    /// The process-level default `CryptoProvider` is used, so it must be
+    /// installed before.
+    #[cfg(feature = "native-roots")]
+    pub fn with_native_roots() -> Result<Self, RootsError> {
+        let result = rustls_native_certs::load_native_certs();
+        let mut roots = RootCertStore::empty();
+        roots.add_parsable_certificates(result.certs);
+        if roots.is_empty() {
+            return Err(RootsError::NoNativeRoots(result.errors));
+        }
+        with_roots(roots)
+    }
+
+    /// Create a connector without client auth verifying servers with the
+    /// platform verifier: the OS verifier on Windows, macOS, iOS and Android,
+    /// and webpki with the system store elsewhere.
+    ///
+    /// The process-level default `CryptoProvider` is used, so it must be
+    /// installed before.
+    #[cfg(feature = "platform-verifier")]
+    pub fn with_platform_verifier() -> Result<Self, RootsError> {
+        let provider = default_provider()?;
+        let verifier = Arc::new(rustls_platform_verifier::Verifier::new(provider.clone())?);
+        let config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .dangerous()
+            .with_custom_certificate_verifier(verifier.clone())
+            .with_no_client_auth();
+        Ok(TlsConnector::from(config).server_cert_verifier(verifier))
+    }
+}
+
+#[cfg(any(feature = "webpki-roots", feature = "native-roots"))]
+fn with_roots(roots: RootCertStore) -> Result<TlsConnector, RootsError> {
+    Ok(webpki_connector(default_provider()?, roots)?)
+}
+
+fn default_provider() -> Result<Arc<CryptoProvider>, RootsError> {
+    CryptoProvider::get_default()
+        .cloned()
+        .ok_or(RootsError::NoCryptoProvider)
+}
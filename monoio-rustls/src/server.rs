@@ -1,9 +1,20 @@
-use std::sync::Arc;
+#[cfg(feature = "pem")]
+use std::path::Path;
+use std::{future::Future, sync::Arc};
 
-use monoio::io::{AsyncReadRent, AsyncWriteRent, OwnedReadHalf, OwnedWriteHalf};
+use monoio::io::{AsyncReadRent, AsyncWriteRent, AsyncWriteRentExt, OwnedReadHalf, OwnedWriteHalf};
 use rustls::{ServerConfig, ServerConnection};
 
-use crate::{stream::Stream, TlsError};
+use crate::{
+    authorize::{access_denied_record, AuthorizeFn, Extension},
+    stream::Stream,
+    Deny, PeerIdentity, TlsError,
+};
+#[cfg(feature = "pem")]
+use crate::{
+    pem::{certified_key, default_provider, load_cert_chain, load_private_key},
+    PemError,
+};
 
 /// A wrapper around an underlying raw stream which implements the TLS protocol.
 pub type TlsStream<IO> = Stream<IO, ServerConnection>;
@@ -16,6 +27,7 @@ pub type TlsStreamWriteHalf<IO> = OwnedWriteHalf<TlsStream<IO>>;
 #[derive(Clone)]
 pub struct TlsAcceptor {
     inner: Arc<ServerConfig>,
+    authorize: Option<Arc<AuthorizeFn>>,
     #[cfg(feature = "unsafe_io")]
     unsafe_io: bool,
 }
@@ -24,6 +36,7 @@ impl From<Arc<ServerConfig>> for TlsAcceptor {
     fn from(inner: Arc<ServerConfig>) -> TlsAcceptor {
         TlsAcceptor {
             inner,
+            authorize: None,
             #[cfg(feature = "unsafe_io")]
             unsafe_io: false,
         }
@@ -34,6 +47,7 @@ impl From<ServerConfig> for TlsAcceptor {
     fn from(inner: ServerConfig) -> TlsAcceptor {
         TlsAcceptor {
             inner: Arc::new(inner),
+            authorize: None,
             #[cfg(feature = "unsafe_io")]
             unsafe_io: false,
         }
@@ -41,6 +55,48 @@ impl From<ServerConfig> for TlsAcceptor {
 }
 
 impl TlsAcceptor {
+    /// Create an acceptor without client auth from a PEM certificate chain
+    /// (leaf first) and a PEM private key in PKCS#1, PKCS#8 or SEC1 format.
+    ///
+    /// The chain order, validity period of each certificate and whether the
+    /// key matches the leaf are checked here. The process-level default
+    /// `CryptoProvider` is used, so it must be installed before.
+    #[cfg(feature = "pem")]
+    pub fn from_pem_files(cert: impl AsRef<Path>, key: impl AsRef<Path>) -> Result<Self, PemError> {
+        let provider = default_provider()?.clone();
+        let chain = load_cert_chain(cert.as_ref())?;
+        let key = load_private_key(key.as_ref())?;
+        certified_key(chain.clone(), key.clone_key(), &provider)?;
+        let config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(chain, key)?;
+        Ok(config.into())
+    }
+
+    /// Set a hook deciding whether a client is allowed in, e.g. by the SAN or
+    /// SPIFFE ID of its certificate. It runs after the handshake, before
+    /// `accept` returns.
+    ///
+    /// On `Ok` the value is attached to the stream, see
+    /// [`extension`](crate::TlsStream::extension). On `Err` the client gets
+    /// an `access_denied` alert and `accept` returns `TlsError::Denied`. This
+    /// enables secret extraction of the wrapped `ServerConfig`(which is cloned
+    /// if shared), since rustls cannot send alerts after the handshake.
+    pub fn authorize<F, Fut, E>(mut self, f: F) -> Self
+    where
+        F: Fn(&PeerIdentity) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<E, Deny>> + 'static,
+        E: Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.inner).enable_secret_extraction = true;
+        self.authorize = Some(Arc::new(move |peer: &PeerIdentity| {
+            let fut = f(peer);
+            Box::pin(async move { fut.await.map(|ext| Box::new(ext) as Extension) })
+        }));
+        self
+    }
+
     /// Enable unsafe-io.
     /// # Safety
     /// Users must make sure the buffer ptr and len is valid until io finished.
@@ -69,6 +125,20 @@ impl TlsAcceptor {
         #[cfg(not(feature = "unsafe_io"))]
         let mut stream = Stream::new(stream, session);
         stream.handshake().await?;
+
+        if let Some(authorize) = &self.authorize {
+            match authorize(&PeerIdentity::new(&stream.session)).await {
+                Ok(extension) => stream.extension = Some(extension),
+                Err(deny) => {
+                    let (mut io, session) = stream.into_parts();
+                    if let Some(record) = access_denied_record(session) {
+                        let _ = io.write_all(record).await;
+                    }
+                    let _ = io.shutdown().await;
+                    return Err(TlsError::Denied(deny));
+                }
+            }
+        }
         Ok(stream)
     }
 }
@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, PoisonError, RwLock},
+};
+
+use rustls::{
+    pki_types::{DnsName, InvalidDnsNameError},
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+    SignatureAlgorithm,
+};
+
+/// A `ResolvesServerCert` choosing the certificate by SNI, for servers hosting
+/// many domains behind one `TlsAcceptor`.
+///
+/// Names are either exact(`example.com`) or wildcard(`*.example.com`, which
+/// matches exactly one more label). Exact names take precedence. Each name can
+/// have one key per signature algorithm, e.g. an ECDSA and an RSA chain; the
+/// first one usable with the client's `signature_schemes` is chosen, non-RSA
+/// keys first.
+///
+/// Entries can be inserted and removed at runtime through a shared reference.
+#[derive(Default)]
+pub struct SniCertStore {
+    inner: RwLock<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    exact: HashMap<String, Vec<Arc<CertifiedKey>>>,
+    // keyed by the name without the leading `*.`
+    wildcard: HashMap<String, Vec<Arc<CertifiedKey>>>,
+    default: Option<String>,
+}
+
+impl SniCertStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a key for the name. A key of the same signature algorithm for this
+    /// name is replaced.
+    pub fn insert(&self, name: &str, key: Arc<CertifiedKey>) -> Result<(), InvalidDnsNameError> {
+        let name = normalize(name)?;
+        let mut inner = self.write();
+        let keys = match name.strip_prefix("*.") {
+            Some(suffix) => inner.wildcard.entry(suffix.to_owned()).or_default(),
+            None => inner.exact.entry(name).or_default(),
+        };
+        let algorithm = key.key.algorithm();
+        keys.retain(|k| k.key.algorithm() != algorithm);
+        keys.push(key);
+        // try non-RSA keys first since they are cheaper.
+        keys.sort_by_key(|k| k.key.algorithm() == SignatureAlgorithm::RSA);
+        Ok(())
+    }
+
+    /// Remove all keys of the name. Returns the removed keys.
+    pub fn remove(&self, name: &str) -> Option<Vec<Arc<CertifiedKey>>> {
+        let name = normalize(name).ok()?;
+        let mut inner = self.write();
+        match name.strip_prefix("*.") {
+            Some(suffix) => inner.wildcard.remove(suffix),
+            None => inner.exact.remove(&name),
+        }
+    }
+
+    /// Set the name whose keys are used for clients that send no SNI.
+    /// Clients sending an unknown name are rejected either way.
+    pub fn set_default(&self, name: Option<&str>) -> Result<(), InvalidDnsNameError> {
+        let name = name.map(normalize).transpose()?;
+        self.write().default = name;
+        Ok(())
+    }
+
+    /// Returns the number of names.
+    pub fn len(&self) -> usize {
+        let inner = self.read();
+        inner.exact.len() + inner.wildcard.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the keys the name would be served with.
+    pub fn lookup(&self, name: &str) -> Option<Vec<Arc<CertifiedKey>>> {
+        let name = name.trim_end_matches('.').to_ascii_lowercase();
+        self.read().lookup(&name).cloned()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Inner> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Inner> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Inner {
+    fn lookup(&self, name: &str) -> Option<&Vec<Arc<CertifiedKey>>> {
+        if let Some(keys) = self.exact.get(name) {
+            return Some(keys);
+        }
+        let (_, parent) = name.split_once('.')?;
+        self.wildcard.get(parent)
+    }
+}
+
+impl ResolvesServerCert for SniCertStore {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let inner = self.read();
+        let keys = match client_hello.server_name() {
+            Some(name) => inner.lookup(name)?,
+            None => inner.lookup(inner.default.as_deref()?)?,
+        };
+        let schemes = client_hello.signature_schemes();
+        keys.iter()
+            .find(|k| k.key.choose_scheme(schemes).is_some())
+            .cloned()
+    }
+}
+
+impl fmt::Debug for SniCertStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SniCertStore")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// Validate and lowercase the name, keeping the wildcard prefix.
+fn normalize(name: &str) -> Result<String, InvalidDnsNameError> {
+    let name = name.trim_end_matches('.');
+    let (prefix, dns) = match name.strip_prefix("*.") {
+        Some(suffix) => ("*.", suffix),
+        None => ("", name),
+    };
+    let dns = DnsName::try_from(dns)?.to_lowercase_owned();
+    Ok(format!("{prefix}{}", dns.as_ref()))
+}
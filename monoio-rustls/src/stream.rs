@@ -1,4 +1,5 @@
 use std::{
+    any::Any,
     io::{self, Read, Write},
     ops::{Deref, DerefMut},
 };
@@ -11,12 +12,15 @@ use monoio::{
 use monoio_io_wrapper::{ReadBuffer, WriteBuffer};
 use rustls::{ClientConnection, ConnectionCommon, ServerConnection, SideData};
 
+use crate::{client::Recorded, VerifiedSct};
+
 #[derive(Debug)]
 pub struct Stream<IO, C> {
     pub(crate) io: IO,
     pub(crate) session: C,
-    r_buffer: ReadBuffer,
-    w_buffer: WriteBuffer,
+    pub(crate) r_buffer: ReadBuffer,
+    pub(crate) w_buffer: WriteBuffer,
+    pub(crate) extension: Option<Box<dyn Any + Send + Sync>>,
 }
 
 impl<IO> Stream<IO, ServerConnection> {
@@ -31,6 +35,21 @@ impl<IO> Stream<IO, ClientConnection> {
     pub fn alpn_protocol(&self) -> Option<Vec<u8>> {
         self.session.alpn_protocol().map(|s| s.to_vec())
     }
+
+    /// Returns the DER encoded OCSP response stapled by the server, if the
+    /// connector records them(see `TlsConnector::capture_ocsp`). Resumed
+    /// sessions have none, the server does not staple again.
+    pub fn ocsp_response(&self) -> Option<&[u8]> {
+        self.extension::<Recorded>()?.ocsp_response.as_deref()
+    }
+
+    /// Returns the SCTs verified for the server certificate, if the
+    /// connector enforces Certificate Transparency(see
+    /// `TlsConnector::certificate_transparency`). Resumed sessions have none.
+    pub fn scts(&self) -> &[VerifiedSct] {
+        self.extension::<Recorded>()
+            .map_or(&[], |r| r.scts.as_slice())
+    }
 }
 
 unsafe impl<IO: Split, C> Split for Stream<IO, C> {}
@@ -42,6 +61,7 @@ impl<IO, C> Stream<IO, C> {
             session,
             r_buffer: Default::default(),
             w_buffer: Default::default(),
+            extension: None,
         }
     }
 
@@ -56,6 +76,7 @@ impl<IO, C> Stream<IO, C> {
             session,
             r_buffer: ReadBuffer::new_unsafe(),
             w_buffer: WriteBuffer::new_unsafe(),
+            extension: None,
         }
     }
 
@@ -63,12 +84,35 @@ impl<IO, C> Stream<IO, C> {
         (self.io, self.session)
     }
 
+    /// Get a reference to the inner io and session.
+    #[inline]
+    pub fn get_ref(&self) -> (&IO, &C) {
+        (&self.io, &self.session)
+    }
+
+    /// Get a mutable reference to the inner io and session.
+    #[inline]
+    pub fn get_mut(&mut self) -> (&mut IO, &mut C) {
+        (&mut self.io, &mut self.session)
+    }
+
+    /// Returns the value attached by the authorize hook of the acceptor, if
+    /// it is a `T`.
+    pub fn extension<T: Any>(&self) -> Option<&T> {
+        self.extension.as_ref()?.downcast_ref()
+    }
+
+    pub fn extension_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.extension.as_mut()?.downcast_mut()
+    }
+
     pub(crate) fn map_conn<C2, F: FnOnce(C) -> C2>(self, f: F) -> Stream<IO, C2> {
         Stream {
             io: self.io,
             session: f(self.session),
             r_buffer: self.r_buffer,
             w_buffer: self.w_buffer,
+            extension: self.extension,
         }
     }
 }
@@ -0,0 +1,240 @@
+//! Poll-io style interface for Stream.
+//! The Stream keeps its own read and write buffers, so it does not need the
+//! ownership passing workaround of monoio-compat.
+
+use std::{
+    io::{self, Read, Write},
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use monoio::io::{
+    poll_io::{AsyncRead, AsyncWrite, ReadBuf},
+    IntoCompIo, IntoPollIo,
+};
+use rustls::{ConnectionCommon, SideData};
+
+use crate::stream::Stream;
+
+impl<IO: IntoPollIo, C> IntoPollIo for Stream<IO, C> {
+    type PollIo = Stream<IO::PollIo, C>;
+
+    fn try_into_poll_io(self) -> Result<Self::PollIo, (io::Error, Self)> {
+        // unsafe buffers capture the dest of the last std io, which cannot
+        // outlive a poll.
+        if !self.r_buffer.is_safe() || !self.w_buffer.is_safe() {
+            return Err((io::ErrorKind::Unsupported.into(), self));
+        }
+        let Stream {
+            io,
+            session,
+            r_buffer,
+            w_buffer,
+            extension,
+        } = self;
+        match io.try_into_poll_io() {
+            Ok(io) => Ok(Stream {
+                io,
+                session,
+                r_buffer,
+                w_buffer,
+                extension,
+            }),
+            Err((e, io)) => Err((
+                e,
+                Stream {
+                    io,
+                    session,
+                    r_buffer,
+                    w_buffer,
+                    extension,
+                },
+            )),
+        }
+    }
+}
+
+impl<IO: IntoCompIo, C> IntoCompIo for Stream<IO, C> {
+    type CompIo = Stream<IO::CompIo, C>;
+
+    fn try_into_comp_io(self) -> Result<Self::CompIo, (io::Error, Self)> {
+        let Stream {
+            io,
+            session,
+            r_buffer,
+            w_buffer,
+            extension,
+        } = self;
+        match io.try_into_comp_io() {
+            Ok(io) => Ok(Stream {
+                io,
+                session,
+                r_buffer,
+                w_buffer,
+                extension,
+            }),
+            Err((e, io)) => Err((
+                e,
+                Stream {
+                    io,
+                    session,
+                    r_buffer,
+                    w_buffer,
+                    extension,
+                },
+            )),
+        }
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin, C, SD: SideData> Stream<IO, C>
+where
+    C: DerefMut + Deref<Target = ConnectionCommon<SD>>,
+{
+    fn poll_read_io(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let n = loop {
+            match self.session.read_tls(&mut self.r_buffer) {
+                Ok(n) => {
+                    break n;
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    ready!(self.r_buffer.poll_io(&mut self.io, cx))?;
+                    continue;
+                }
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        };
+
+        let state = match self.session.process_new_packets() {
+            Ok(state) => state,
+            Err(err) => {
+                // try to send the alert, but do not wait for it.
+                let _ = self.poll_write_io(cx);
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, err)));
+            }
+        };
+
+        if state.peer_has_closed() && self.session.is_handshaking() {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "tls handshake alert",
+            )));
+        }
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_write_io(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        // drain data left by former calls before taking more from rustls.
+        ready!(self.w_buffer.poll_io(&mut self.io, cx))?;
+        let n = self.session.write_tls(&mut self.w_buffer)?;
+        // the data is already buffered, so pending here does not lose it.
+        if let Poll::Ready(Err(e)) = self.w_buffer.poll_io(&mut self.io, cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_handshake(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.session.is_handshaking() {
+            if self.session.wants_write() {
+                ready!(self.poll_write_io(cx))?;
+            } else if self.session.wants_read() {
+                if ready!(self.poll_read_io(cx))? == 0 {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "tls handshake eof",
+                    )));
+                }
+            } else {
+                break;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_flush_io(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.session.wants_write() {
+            ready!(self.poll_write_io(cx))?;
+        }
+        ready!(self.w_buffer.poll_io(&mut self.io, cx))?;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin, C: Unpin, SD: SideData> AsyncRead for Stream<IO, C>
+where
+    C: DerefMut + Deref<Target = ConnectionCommon<SD>>,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_handshake(cx))?;
+
+        loop {
+            // read from rustls to buffer
+            match this.session.reader().read(buf.initialize_unfilled()) {
+                Ok(n) => {
+                    buf.advance(n);
+                    return Poll::Ready(Ok(()));
+                }
+                // we need more data, read something.
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => (),
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+
+            // now we need data, read something into rustls
+            ready!(this.poll_read_io(cx))?;
+        }
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin, C: Unpin, SD: SideData> AsyncWrite for Stream<IO, C>
+where
+    C: DerefMut + Deref<Target = ConnectionCommon<SD>>,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_handshake(cx))?;
+
+        // flush rustls inner write buffer to make sure there is space for new data
+        ready!(this.poll_flush_io(cx))?;
+
+        // write slice to rustls
+        let n = this.session.writer().write(buf)?;
+
+        // write from rustls to connection, the data has been accepted so we
+        // only report errors here.
+        while this.session.wants_write() {
+            match this.poll_write_io(cx) {
+                Poll::Ready(Ok(0)) | Poll::Pending => break,
+                Poll::Ready(Ok(_)) => (),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            }
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.session.writer().flush()?;
+        ready!(this.poll_flush_io(cx))?;
+        Pin::new(&mut this.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        // send_close_notify is idempotent so it is safe to be called on every poll.
+        this.session.send_close_notify();
+        ready!(this.poll_flush_io(cx))?;
+        Pin::new(&mut this.io).poll_shutdown(cx)
+    }
+}
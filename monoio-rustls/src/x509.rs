@@ -0,0 +1,621 @@
+//! Just enough DER parsing to validate certificate chains and OCSP responses
+//! at load time, and to get the SCTs out of them.
+
+/// Fields of a certificate used by the load time checks.
+pub(crate) struct CertInfo<'a> {
+    /// The value of the serial number.
+    pub(crate) serial: &'a [u8],
+    /// The DER encoded issuer name.
+    pub(crate) issuer: &'a [u8],
+    /// The DER encoded subject name.
+    pub(crate) subject: &'a [u8],
+    /// The DER encoded SubjectPublicKeyInfo.
+    pub(crate) spki: &'a [u8],
+    /// The DER encoded extensions without their SEQUENCE header, empty if
+    /// absent.
+    pub(crate) extensions: &'a [u8],
+    /// Unix time in seconds.
+    #[cfg_attr(not(feature = "pem"), allow(dead_code))]
+    pub(crate) not_before: i64,
+    /// Unix time in seconds.
+    #[cfg_attr(not(feature = "pem"), allow(dead_code))]
+    pub(crate) not_after: i64,
+}
+
+const SEQUENCE: u8 = 0x30;
+const INTEGER: u8 = 0x02;
+const VERSION: u8 = 0xa0;
+const BOOLEAN: u8 = 0x01;
+const ENUMERATED: u8 = 0x0a;
+const BIT_STRING: u8 = 0x03;
+const OCTET_STRING: u8 = 0x04;
+const OID: u8 = 0x06;
+const EXTENSIONS: u8 = 0xa3;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+
+/// The OID of the SCT list extension of certificates.
+const SCT_LIST: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x04, 0x02];
+/// The OID of the SCT list extension of OCSP single responses.
+const OCSP_SCT_LIST: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x04, 0x05];
+
+impl<'a> CertInfo<'a> {
+    pub(crate) fn parse(der: &'a [u8]) -> Option<Self> {
+        let (cert, _) = expect(der, SEQUENCE)?;
+        let (tbs, _) = expect(cert, SEQUENCE)?;
+
+        let mut rest = tbs;
+        if rest.first() == Some(&VERSION) {
+            rest = tlv(rest)?.2;
+        }
+        let (serial, rest) = expect(rest, INTEGER)?;
+        // signature
+        let rest = tlv(rest)?.2;
+
+        let (issuer, rest) = raw(rest, SEQUENCE)?;
+        let (validity, rest) = expect(rest, SEQUENCE)?;
+        let (subject, rest) = raw(rest, SEQUENCE)?;
+        let (spki, mut rest) = raw(rest, SEQUENCE)?;
+        // skip issuerUniqueID and subjectUniqueID
+        let mut extensions: &[u8] = &[];
+        while let Some((tag, value, next)) = tlv(rest) {
+            if tag == EXTENSIONS {
+                extensions = expect(value, SEQUENCE)?.0;
+            }
+            rest = next;
+        }
+
+        let (not_before, validity) = time(validity)?;
+        let (not_after, _) = time(validity)?;
+        Some(Self {
+            serial,
+            issuer,
+            subject,
+            spki,
+            extensions,
+            not_before,
+            not_after,
+        })
+    }
+
+    /// Returns the (tag, value) of each GeneralName in the subjectAltName
+    /// extension.
+    pub(crate) fn subject_alt_names(&self) -> Vec<(u8, &'a [u8])> {
+        const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+        let names = extension(self.extensions, SUBJECT_ALT_NAME)
+            .and_then(|value| Some(expect(value, SEQUENCE)?.0));
+        items(names.unwrap_or_default()).collect()
+    }
+
+    /// Returns the TLS encoded SignedCertificateTimestampList embedded in
+    /// the certificate.
+    pub(crate) fn sct_list(&self) -> Option<&'a [u8]> {
+        let value = extension(self.extensions, SCT_LIST)?;
+        Some(expect(value, OCTET_STRING)?.0)
+    }
+
+    /// Returns the (tag, value) of each subject attribute of the type.
+    pub(crate) fn subject_attributes(&self, oid: &[u8]) -> Vec<(u8, &'a [u8])> {
+        let Some((rdns, _)) = expect(self.subject, SEQUENCE) else {
+            return Vec::new();
+        };
+        items(rdns)
+            .flat_map(|(_, rdn)| items(rdn))
+            .filter_map(|(_, attribute)| {
+                let (ty, rest) = expect(attribute, OID)?;
+                let (tag, value, _) = tlv(rest)?;
+                (ty == oid).then_some((tag, value))
+            })
+            .collect()
+    }
+
+    /// Returns the issuer name without its SEQUENCE header.
+    #[cfg_attr(not(feature = "pem"), allow(dead_code))]
+    pub(crate) fn issuer_value(&self) -> &'a [u8] {
+        expect(self.issuer, SEQUENCE).map_or(&[], |(value, _)| value)
+    }
+}
+
+/// Returns the TBSCertificate without the SCT list extension, which is what
+/// CT logs sign for the precertificate.
+pub(crate) fn precert_tbs(der: &[u8]) -> Option<Vec<u8>> {
+    let (cert, _) = expect(der, SEQUENCE)?;
+    let (mut rest, _) = expect(cert, SEQUENCE)?;
+    let mut tbs = Vec::new();
+    while !rest.is_empty() {
+        let (tag, value, next) = tlv(rest)?;
+        if tag == EXTENSIONS {
+            let (mut extensions, _) = expect(value, SEQUENCE)?;
+            let mut kept = Vec::new();
+            while !extensions.is_empty() {
+                let (extension, next) = raw(extensions, SEQUENCE)?;
+                if expect(expect(extension, SEQUENCE)?.0, OID)?.0 != SCT_LIST {
+                    kept.extend_from_slice(extension);
+                }
+                extensions = next;
+            }
+            tbs.extend(encode(EXTENSIONS, &encode(SEQUENCE, &kept)));
+        } else {
+            tbs.extend_from_slice(&rest[..rest.len() - next.len()]);
+        }
+        rest = next;
+    }
+    Some(encode(SEQUENCE, &tbs))
+}
+
+/// Split a SubjectPublicKeyInfo into the AlgorithmIdentifier without its
+/// SEQUENCE header and the subjectPublicKey.
+pub(crate) fn spki_parts(spki: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (spki, _) = expect(spki, SEQUENCE)?;
+    let (algorithm, rest) = expect(spki, SEQUENCE)?;
+    match expect(rest, BIT_STRING)? {
+        ([0, key @ ..], []) => Some((algorithm, key)),
+        _ => None,
+    }
+}
+
+/// Fields of a successful OCSP response used to staple and refresh it.
+pub(crate) struct OcspInfo<'a> {
+    /// The OID of the hash algorithm of the CertID.
+    pub(crate) hash_algorithm: &'a [u8],
+    /// The hash of the DER encoded issuer name.
+    pub(crate) issuer_name_hash: &'a [u8],
+    /// The hash of the subjectPublicKey of the issuer.
+    pub(crate) issuer_key_hash: &'a [u8],
+    /// The value of the serial number the single response is about.
+    pub(crate) serial: &'a [u8],
+    /// 0 is good, 1 is revoked and 2 is unknown.
+    pub(crate) status: u8,
+    /// Unix time in seconds.
+    pub(crate) this_update: i64,
+    /// Unix time in seconds.
+    pub(crate) next_update: Option<i64>,
+    /// The TLS encoded SignedCertificateTimestampList of the single response.
+    pub(crate) sct_list: Option<&'a [u8]>,
+}
+
+/// The OID of id-pkix-ocsp-basic.
+const OCSP_BASIC: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+
+impl<'a> OcspInfo<'a> {
+    /// Returns the responseStatus on error, or `None` if malformed.
+    pub(crate) fn parse(der: &'a [u8]) -> Result<Self, Option<u8>> {
+        let (response, _) = expect(der, SEQUENCE).ok_or(None)?;
+        let (status, rest) = expect(response, ENUMERATED).ok_or(None)?;
+        match status {
+            [0] => {}
+            [status] => return Err(Some(*status)),
+            _ => return Err(None),
+        }
+        Self::parse_bytes(rest).ok_or(None)
+    }
+
+    fn parse_bytes(input: &'a [u8]) -> Option<Self> {
+        // responseBytes [0] EXPLICIT
+        let (bytes, _) = expect(input, 0xa0)?;
+        let (bytes, _) = expect(bytes, SEQUENCE)?;
+        let (oid, rest) = expect(bytes, OID)?;
+        if oid != OCSP_BASIC {
+            return None;
+        }
+        let (basic, _) = expect(rest, OCTET_STRING)?;
+        let (basic, _) = expect(basic, SEQUENCE)?;
+        let (data, _) = expect(basic, SEQUENCE)?;
+
+        let mut rest = data;
+        if rest.first() == Some(&0xa0) {
+            rest = tlv(rest)?.2;
+        }
+        // responderID and producedAt
+        rest = tlv(rest)?.2;
+        rest = tlv(rest)?.2;
+        let (responses, _) = expect(rest, SEQUENCE)?;
+        // only the first one, we never ask for more.
+        let (single, _) = expect(responses, SEQUENCE)?;
+
+        let (cert_id, rest) = expect(single, SEQUENCE)?;
+        let (algorithm, id) = expect(cert_id, SEQUENCE)?;
+        let (hash_algorithm, _) = expect(algorithm, OID)?;
+        let (issuer_name_hash, id) = expect(id, OCTET_STRING)?;
+        let (issuer_key_hash, id) = expect(id, OCTET_STRING)?;
+        let (serial, _) = expect(id, INTEGER)?;
+
+        let (tag, _, rest) = tlv(rest)?;
+        let status = match tag {
+            0x80 => 0,
+            0xa1 => 1,
+            0x82 => 2,
+            _ => return None,
+        };
+        let (this_update, rest) = time(rest)?;
+        let (next_update, rest) = match expect(rest, 0xa0) {
+            Some((next, rest)) => (Some(time(next)?.0), rest),
+            None => (None, rest),
+        };
+        // singleExtensions [1] EXPLICIT
+        let sct_list = expect(rest, 0xa1)
+            .and_then(|(extensions, _)| expect(extensions, SEQUENCE))
+            .and_then(|(extensions, _)| extension(extensions, OCSP_SCT_LIST))
+            .and_then(|value| Some(expect(value, OCTET_STRING)?.0));
+        Some(Self {
+            hash_algorithm,
+            issuer_name_hash,
+            issuer_key_hash,
+            serial,
+            status,
+            this_update,
+            next_update,
+            sct_list,
+        })
+    }
+}
+
+/// Returns the extnValue of the extension with the OID, given the extensions
+/// without their SEQUENCE header.
+fn extension<'a>(extensions: &'a [u8], oid: &[u8]) -> Option<&'a [u8]> {
+    items(extensions).find_map(|(_, extension)| {
+        let (id, rest) = expect(extension, OID)?;
+        if id != oid {
+            return None;
+        }
+        let rest = match expect(rest, BOOLEAN) {
+            Some((_, rest)) => rest,
+            None => rest,
+        };
+        Some(expect(rest, OCTET_STRING)?.0)
+    })
+}
+
+/// Split the first TLV into (tag, value, rest).
+fn tlv(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&first, mut input) = input.split_first()?;
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || input.len() < n {
+            return None;
+        }
+        let (bytes, rest) = input.split_at(n);
+        input = rest;
+        bytes.iter().fold(0, |len, &b| (len << 8) | b as usize)
+    };
+    if input.len() < len {
+        return None;
+    }
+    let (value, rest) = input.split_at(len);
+    Some((tag, value, rest))
+}
+
+/// Iterate over the (tag, value) of consecutive TLVs.
+fn items(mut input: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    std::iter::from_fn(move || {
+        let (tag, value, rest) = tlv(input)?;
+        input = rest;
+        Some((tag, value))
+    })
+}
+
+/// Returns the value of the first TLV if it has the tag.
+fn expect(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    match tlv(input)? {
+        (t, value, rest) if t == tag => Some((value, rest)),
+        _ => None,
+    }
+}
+
+/// Returns the whole first TLV if it has the tag.
+fn raw(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (_, rest) = expect(input, tag)?;
+    Some((&input[..input.len() - rest.len()], rest))
+}
+
+/// Encode a TLV.
+pub(crate) fn encode(tag: u8, value: &[u8]) -> Vec<u8> {
+    let len = value.len().to_be_bytes();
+    let skip = len.iter().take_while(|&&b| b == 0).count();
+    let mut out = vec![tag];
+    match value.len() {
+        0..=0x7f => out.push(value.len() as u8),
+        _ => {
+            out.push(0x80 | (len.len() - skip) as u8);
+            out.extend_from_slice(&len[skip..]);
+        }
+    }
+    out.extend_from_slice(value);
+    out
+}
+
+fn time(input: &[u8]) -> Option<(i64, &[u8])> {
+    let (tag, value, rest) = tlv(input)?;
+    let (year, value) = match tag {
+        UTC_TIME => {
+            let yy = digits(value.get(..2)?)?;
+            (if yy >= 50 { 1900 + yy } else { 2000 + yy }, &value[2..])
+        }
+        GENERALIZED_TIME => (digits(value.get(..4)?)?, &value[4..]),
+        _ => return None,
+    };
+    // MMDDHHMMSSZ, the only form allowed by RFC 5280.
+    if value.len() != 11 || value[10] != b'Z' {
+        return None;
+    }
+    let month = digits(&value[0..2])?;
+    let day = digits(&value[2..4])?;
+    let hour = digits(&value[4..6])?;
+    let minute = digits(&value[6..8])?;
+    let second = digits(&value[8..10])?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    Some((days * 86400 + hour * 3600 + minute * 60 + second, rest))
+}
+
+fn digits(input: &[u8]) -> Option<i64> {
+    input.iter().try_fold(0, |n, &b| {
+        b.is_ascii_digit().then(|| n * 10 + (b - b'0') as i64)
+    })
+}
+
+/// Days since 1970-01-01 of the proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use rustls::pki_types::{pem::PemObject, CertificateDer};
+
+    use super::*;
+
+    const COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+
+    fn leaf() -> CertificateDer<'static> {
+        CertificateDer::from_pem_slice(include_bytes!("../tests/data/ocsp-leaf.pem")).unwrap()
+    }
+
+    fn ca() -> CertificateDer<'static> {
+        CertificateDer::from_pem_slice(include_bytes!("../tests/data/ocsp-ca.pem")).unwrap()
+    }
+
+    #[test]
+    fn tlv_lengths() {
+        assert_eq!(
+            tlv(&[0x04, 0x01, 0xaa, 0xbb]),
+            Some((0x04, &[0xaa][..], &[0xbb][..]))
+        );
+        assert_eq!(tlv(&[0x04, 0x00]), Some((0x04, &[][..], &[][..])));
+        let long = encode(OCTET_STRING, &[7; 300]);
+        assert_eq!(&long[..4], &[0x04, 0x82, 0x01, 0x2c]);
+        assert_eq!(tlv(&long), Some((OCTET_STRING, &[7; 300][..], &[][..])));
+        assert_eq!(encode(OCTET_STRING, &[7; 0x80])[..3], [0x04, 0x81, 0x80]);
+
+        // truncated header or value.
+        assert_eq!(tlv(&[]), None);
+        assert_eq!(tlv(&[0x04]), None);
+        assert_eq!(tlv(&[0x04, 0x02, 0xaa]), None);
+        assert_eq!(tlv(&[0x04, 0x82, 0x01]), None);
+        // indefinite and oversized lengths.
+        assert_eq!(tlv(&[0x30, 0x80, 0x00, 0x00]), None);
+        assert_eq!(tlv(&[0x30, 0x85, 0, 0, 0, 0, 1, 0]), None);
+        assert_eq!(tlv(&[0x30, 0x84, 0xff, 0xff, 0xff, 0xff, 0]), None);
+
+        assert_eq!(expect(&[0x02, 0x01, 0x05], OCTET_STRING), None);
+        assert_eq!(
+            raw(&[0x02, 0x01, 0x05, 0xff], INTEGER),
+            Some((&[0x02, 0x01, 0x05][..], &[0xff][..]))
+        );
+        assert_eq!(items(&[0x02, 0x01, 0x05, 0x04, 0x00, 0x30]).count(), 2);
+    }
+
+    #[test]
+    fn times() {
+        let utc = |s: &str| time(&encode(UTC_TIME, s.as_bytes())).map(|(t, _)| t);
+        let generalized = |s: &str| time(&encode(GENERALIZED_TIME, s.as_bytes())).map(|(t, _)| t);
+        assert_eq!(utc("700101000000Z"), Some(0));
+        assert_eq!(utc("000301000000Z"), Some(951868800));
+        // two digit years before 50 are in the 21st century.
+        assert_eq!(utc("491231235959Z"), generalized("20491231235959Z"));
+        assert_eq!(utc("500101000000Z"), generalized("19500101000000Z"));
+        assert_eq!(generalized("21260924151010Z"), Some(4945936210));
+
+        assert_eq!(utc("700101000000"), None);
+        assert_eq!(utc("700101000000+0000"), None);
+        assert_eq!(utc("701301000000Z"), None);
+        assert_eq!(utc("700100000000Z"), None);
+        assert_eq!(utc("700101240000Z"), None);
+        assert_eq!(utc("70010100000aZ"), None);
+        assert_eq!(time(&encode(INTEGER, b"700101000000Z")), None);
+    }
+
+    #[test]
+    fn civil_days() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(days_from_civil(2000, 2, 29), 11016);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+    }
+
+    #[test]
+    fn certificate() {
+        let (leaf, ca) = (leaf(), ca());
+        let info = CertInfo::parse(&leaf).unwrap();
+        let ca_info = CertInfo::parse(&ca).unwrap();
+        assert_eq!(info.serial, &[0x12, 0x34]);
+        assert_eq!(info.issuer, ca_info.subject);
+        assert_eq!(info.not_before, 1792336210);
+        assert_eq!(info.not_after, 4945936210);
+        assert_eq!(info.subject_alt_names(), vec![(0x82, &b"localhost"[..])]);
+        assert_eq!(
+            info.subject_attributes(COMMON_NAME),
+            vec![(0x0c, &b"localhost"[..])]
+        );
+        assert_eq!(
+            ca_info.subject_attributes(COMMON_NAME),
+            vec![(0x0c, &b"monoio-tls test CA"[..])]
+        );
+        assert!(info.sct_list().is_none());
+        assert_eq!(
+            info.issuer_value(),
+            expect(ca_info.subject, SEQUENCE).unwrap().0
+        );
+
+        // id-ecPublicKey, prime256v1 and an uncompressed point.
+        let (algorithm, key) = spki_parts(info.spki).unwrap();
+        assert_eq!(
+            expect(algorithm, OID).unwrap().0,
+            &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01]
+        );
+        assert_eq!((key[0], key.len()), (0x04, 65));
+
+        // the certificate has no SCT list, the TBSCertificate is kept as is.
+        let (cert, _) = expect(&leaf, SEQUENCE).unwrap();
+        let (tbs, _) = raw(cert, SEQUENCE).unwrap();
+        assert_eq!(precert_tbs(&leaf).unwrap(), tbs);
+    }
+
+    #[test]
+    fn malformed_certificate() {
+        let leaf = leaf();
+        // every truncation fails.
+        for len in 0..leaf.len() {
+            assert!(
+                CertInfo::parse(&leaf[..len]).is_none(),
+                "truncated to {len}"
+            );
+            assert!(precert_tbs(&leaf[..len]).is_none(), "truncated to {len}");
+        }
+        // no corruption panics.
+        for i in 0..leaf.len() {
+            let mut corrupt = leaf.to_vec();
+            corrupt[i] ^= 0xff;
+            if let Some(info) = CertInfo::parse(&corrupt) {
+                info.subject_alt_names();
+                info.subject_attributes(COMMON_NAME);
+                info.sct_list();
+                spki_parts(info.spki);
+            }
+            precert_tbs(&corrupt);
+        }
+        assert!(CertInfo::parse(&[0x30, 0x84, 0xff, 0xff, 0xff, 0xff]).is_none());
+        assert!(spki_parts(&encode(SEQUENCE, &[])).is_none());
+    }
+
+    #[test]
+    fn sct_list_extension() {
+        // rebuild the leaf with an SCT list extension.
+        let leaf = leaf();
+        let (cert, _) = expect(&leaf, SEQUENCE).unwrap();
+        let (tbs, signature) = expect(cert, SEQUENCE).unwrap();
+        let (tbs_raw, _) = raw(cert, SEQUENCE).unwrap();
+
+        let sct_list = [0x00, 0x03, 0x00, 0x01, 0xaa];
+        let mut oid = encode(OID, SCT_LIST);
+        oid.extend(encode(OCTET_STRING, &encode(OCTET_STRING, &sct_list)));
+        let sct_extension = encode(SEQUENCE, &oid);
+
+        let mut fields = Vec::new();
+        let mut rest = tbs;
+        while !rest.is_empty() {
+            let (tag, value, next) = tlv(rest).unwrap();
+            if tag == EXTENSIONS {
+                let (extensions, _) = expect(value, SEQUENCE).unwrap();
+                let mut extensions = extensions.to_vec();
+                extensions.extend_from_slice(&sct_extension);
+                fields.extend(encode(EXTENSIONS, &encode(SEQUENCE, &extensions)));
+            } else {
+                fields.extend_from_slice(&rest[..rest.len() - next.len()]);
+            }
+            rest = next;
+        }
+        let mut with_sct = encode(SEQUENCE, &fields);
+        with_sct.extend_from_slice(signature);
+        let with_sct = encode(SEQUENCE, &with_sct);
+
+        let info = CertInfo::parse(&with_sct).unwrap();
+        assert_eq!(info.sct_list(), Some(&sct_list[..]));
+        assert_eq!(info.subject_alt_names(), vec![(0x82, &b"localhost"[..])]);
+        // the precertificate TBS is the original one.
+        assert_eq!(precert_tbs(&with_sct).unwrap(), tbs_raw);
+    }
+
+    #[test]
+    fn ocsp_responses() {
+        let good = OcspInfo::parse(include_bytes!("../tests/data/ocsp-good.der")).unwrap();
+        assert_eq!(good.hash_algorithm, [0x2b, 0x0e, 0x03, 0x02, 0x1a]);
+        assert_eq!(
+            good.issuer_name_hash,
+            [
+                0x2f, 0xfe, 0x0b, 0xf1, 0xa2, 0x1d, 0x95, 0x07, 0xff, 0x61, 0x4f, 0x07, 0x74, 0x79,
+                0x43, 0x63, 0xe3, 0x64, 0x9b, 0xc1
+            ]
+        );
+        assert_eq!(
+            good.issuer_key_hash,
+            [
+                0x3c, 0x3d, 0xd4, 0xae, 0x7f, 0xd7, 0x3b, 0x5a, 0x1a, 0x90, 0x7b, 0xb9, 0xf6, 0x43,
+                0xd3, 0xd0, 0xb1, 0xe1, 0xcf, 0x4b
+            ]
+        );
+        assert_eq!(good.serial, &[0x12, 0x34]);
+        assert_eq!(good.status, 0);
+        assert_eq!(good.next_update.unwrap() - good.this_update, 36500 * 86400);
+        assert!(good.sct_list.is_none());
+
+        let sha256 = OcspInfo::parse(include_bytes!("../tests/data/ocsp-good-sha256.der")).unwrap();
+        assert_eq!(
+            sha256.hash_algorithm,
+            [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01]
+        );
+        assert_eq!(sha256.issuer_name_hash.len(), 32);
+        assert_eq!(sha256.issuer_key_hash.len(), 32);
+        assert_eq!(sha256.serial, &[0x12, 0x34]);
+
+        let revoked = OcspInfo::parse(include_bytes!("../tests/data/ocsp-revoked.der")).unwrap();
+        assert_eq!(revoked.serial, &[0x12, 0x34]);
+        assert_eq!(revoked.status, 1);
+
+        // unauthorized(6) has no responseBytes.
+        assert!(matches!(
+            OcspInfo::parse(&[0x30, 0x03, 0x0a, 0x01, 0x06]),
+            Err(Some(6))
+        ));
+        assert!(matches!(
+            OcspInfo::parse(&[0x30, 0x03, 0x0a, 0x01, 0x00]),
+            Err(None)
+        ));
+        assert!(matches!(OcspInfo::parse(&[0x30, 0x00]), Err(None)));
+        assert!(matches!(
+            OcspInfo::parse(&[0x30, 0x04, 0x0a, 0x02, 0x00, 0x00]),
+            Err(None)
+        ));
+    }
+
+    #[test]
+    fn malformed_ocsp_response() {
+        let good = include_bytes!("../tests/data/ocsp-good.der");
+        for len in 0..good.len() {
+            assert!(OcspInfo::parse(&good[..len]).is_err(), "truncated to {len}");
+        }
+        for i in 0..good.len() {
+            let mut corrupt = good.to_vec();
+            corrupt[i] ^= 0xff;
+            let _ = OcspInfo::parse(&corrupt);
+        }
+        // another response type than id-pkix-ocsp-basic.
+        let mut other = good.to_vec();
+        let at = other
+            .windows(OCSP_BASIC.len())
+            .position(|w| w == OCSP_BASIC)
+            .unwrap();
+        other[at + OCSP_BASIC.len() - 1] = 0x02;
+        assert!(matches!(OcspInfo::parse(&other), Err(None)));
+    }
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use monoio_rustls::{AlpnPreset, TlsAcceptor, TlsConnector};
+
+/// Returns the negotiated protocol, or `Err` if the handshake failed.
+async fn negotiate(preset: AlpnPreset, server: &[&[u8]]) -> Result<Option<Vec<u8>>, ()> {
+    let mut config = common::server_config();
+    config.alpn_protocols = server.iter().map(|p| p.to_vec()).collect();
+    let acceptor = TlsAcceptor::from(config);
+    let connector = TlsConnector::from(common::client_config()).alpn_protocols(preset);
+
+    let (client, server) = common::tcp_pair().await;
+    let (client, server) = monoio::join!(
+        connector.connect(common::localhost(), client),
+        acceptor.accept(server)
+    );
+    let (client, server) = (client.map_err(drop)?, server.map_err(drop)?);
+    assert_eq!(client.alpn_protocol(), server.alpn_protocol());
+    Ok(client.alpn_protocol())
+}
+
+#[test]
+fn preset_protocols() {
+    assert_eq!(AlpnPreset::Http11.protocols(), [b"http/1.1"]);
+    assert_eq!(AlpnPreset::H2.protocols(), [b"h2"]);
+    assert_eq!(
+        Vec::<Vec<u8>>::from(AlpnPreset::H2Http11),
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    );
+}
+
+#[monoio::test]
+async fn presets() {
+    let h2 = Ok(Some(b"h2".to_vec()));
+    let http11 = Ok(Some(b"http/1.1".to_vec()));
+    assert_eq!(
+        negotiate(AlpnPreset::H2Http11, &[b"h2", b"http/1.1"]).await,
+        h2
+    );
+    assert_eq!(
+        negotiate(AlpnPreset::H2Http11, &[b"http/1.1"]).await,
+        http11
+    );
+    assert_eq!(
+        negotiate(AlpnPreset::Http11, &[b"h2", b"http/1.1"]).await,
+        http11
+    );
+    assert_eq!(negotiate(AlpnPreset::H2, &[b"h2"]).await, h2);
+    // no overlap fails the handshake.
+    assert_eq!(negotiate(AlpnPreset::H2, &[b"http/1.1"]).await, Err(()));
+    // a server without ALPN selects nothing.
+    assert_eq!(negotiate(AlpnPreset::H2Http11, &[]).await, Ok(None));
+}
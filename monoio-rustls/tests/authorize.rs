@@ -0,0 +1,200 @@
+mod common;
+
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::Arc,
+};
+
+use monoio::io::AsyncReadRent;
+use monoio_rustls::{Deny, PeerIdentity, TlsAcceptor, TlsConnector, TlsError};
+use rcgen::{CertificateParams, DnType, KeyPair, SanType};
+use rustls::{
+    pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+    server::WebPkiClientVerifier,
+    AlertDescription, ClientConfig, ServerConfig,
+};
+
+/// The SPIFFE ID allowed in by the hook.
+const ALLOWED: &str = "spiffe://example.org/allowed";
+
+fn client(spiffe_id: &str) -> TlsConnector {
+    let pki = common::pki();
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params.subject_alt_names = vec![
+        SanType::DnsName("device.test".try_into().unwrap()),
+        SanType::URI(spiffe_id.try_into().unwrap()),
+        SanType::IpAddress(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+        SanType::Rfc822Name("ops@example.org".try_into().unwrap()),
+    ];
+    params.distinguished_name.push(DnType::CommonName, "device");
+    params
+        .distinguished_name
+        .push(DnType::OrganizationName, "monoio");
+    params
+        .distinguished_name
+        .push(DnType::OrganizationalUnitName, "edge");
+    let cert = params.signed_by(&key, &pki.ca, &pki.ca_key).unwrap();
+    let key: PrivateKeyDer = PrivatePkcs8KeyDer::from(key.serialize_der()).into();
+    let mut config = ClientConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(pki.roots())
+        .with_client_auth_cert(vec![cert.der().clone()], key)
+        .unwrap();
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    config.into()
+}
+
+fn mtls_config() -> ServerConfig {
+    let pki = common::pki();
+    let verifier =
+        WebPkiClientVerifier::builder_with_provider(Arc::new(pki.roots()), common::provider())
+            .build()
+            .unwrap();
+    let mut config = ServerConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(pki.chain(), pki.key_der())
+        .unwrap();
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    config
+}
+
+/// Allow the client with the `ALLOWED` SPIFFE ID, attaching its CN.
+fn acceptor(config: ServerConfig) -> TlsAcceptor {
+    TlsAcceptor::from(config).authorize(|peer: &PeerIdentity| {
+        let result = match peer.spiffe_id() {
+            Some(ALLOWED) => Ok(peer.common_names().to_vec()),
+            id => Err(Deny::new(format!("{id:?} is not allowed"))),
+        };
+        async move { result }
+    })
+}
+
+#[monoio::test]
+async fn allowed_with_extension() {
+    let acceptor = acceptor(mtls_config());
+    let (_client, server) = common::tls_pair(&client(ALLOWED), &acceptor).await;
+    assert_eq!(
+        server.extension::<Vec<String>>(),
+        Some(&vec!["device".to_string()])
+    );
+    assert_eq!(server.extension::<String>(), None);
+}
+
+async fn assert_access_denied(config: ServerConfig, connector: TlsConnector) {
+    let acceptor = acceptor(config);
+    let (client, server) = common::tcp_pair().await;
+    let (client, server) = monoio::join!(
+        connector.connect(common::localhost(), client),
+        acceptor.accept(server)
+    );
+    match server {
+        Err(TlsError::Denied(deny)) => {
+            assert_eq!(
+                deny.reason(),
+                "Some(\"spiffe://example.org/other\") is not allowed"
+            );
+        }
+        _ => panic!("the client must be denied"),
+    }
+    // with TLS 1.3 the client completes its handshake and gets the alert on
+    // the first read, with TLS 1.2 it arrives along with the server Finished.
+    let e = match client {
+        Ok(mut client) => {
+            let (res, _) = client.read(vec![0; 16]).await;
+            TlsError::Io(res.unwrap_err())
+        }
+        Err(e) => e,
+    };
+    let TlsError::Io(e) = e else {
+        panic!("unexpected error {e:?}");
+    };
+    assert_eq!(
+        e.get_ref().and_then(|e| e.downcast_ref::<rustls::Error>()),
+        Some(&rustls::Error::AlertReceived(
+            AlertDescription::AccessDenied
+        ))
+    );
+}
+
+#[monoio::test]
+async fn denied_gets_access_denied() {
+    assert_access_denied(mtls_config(), client("spiffe://example.org/other")).await;
+}
+
+#[cfg(feature = "tls12")]
+#[monoio::test]
+async fn denied_gets_access_denied_tls12() {
+    let pki = common::pki();
+    let verifier =
+        WebPkiClientVerifier::builder_with_provider(Arc::new(pki.roots()), common::provider())
+            .build()
+            .unwrap();
+    let config = ServerConfig::builder_with_provider(common::provider())
+        .with_protocol_versions(&[&rustls::version::TLS12])
+        .unwrap()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(pki.chain(), pki.key_der())
+        .unwrap();
+    assert_access_denied(config, client("spiffe://example.org/other")).await;
+}
+
+#[monoio::test]
+async fn peer_identity() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let acceptor = TlsAcceptor::from(mtls_config()).authorize(move |peer: &PeerIdentity| {
+        tx.send(peer.clone()).unwrap();
+        async { Ok(()) }
+    });
+    let (_client, _server) = common::tls_pair(&client(ALLOWED), &acceptor).await;
+    let peer = rx.recv().unwrap();
+
+    assert_eq!(peer.certificates().len(), 1);
+    assert_eq!(peer.end_entity(), peer.certificates().first());
+    assert_eq!(peer.server_name(), Some("localhost"));
+    assert_eq!(peer.alpn_protocol(), Some(&b"h2"[..]));
+    assert_eq!(peer.dns_names(), ["device.test"]);
+    assert_eq!(peer.uris(), [ALLOWED]);
+    assert_eq!(peer.spiffe_id(), Some(ALLOWED));
+    assert_eq!(
+        peer.ip_addresses(),
+        [IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]
+    );
+    assert_eq!(peer.emails(), ["ops@example.org"]);
+    assert_eq!(peer.common_names(), ["device"]);
+    assert_eq!(peer.organizations(), ["monoio"]);
+    assert_eq!(peer.organizational_units(), ["edge"]);
+}
+
+#[monoio::test]
+async fn without_client_certificate() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    let acceptor =
+        TlsAcceptor::from(common::server_config()).authorize(move |peer: &PeerIdentity| {
+            tx.send(peer.clone()).unwrap();
+            async { Err::<(), _>(Deny::new("anonymous")) }
+        });
+    let connector = TlsConnector::from(common::client_config());
+    let (client, server) = common::tcp_pair().await;
+    let (_client, server) = monoio::join!(
+        connector.connect(common::localhost(), client),
+        acceptor.accept(server)
+    );
+    assert!(matches!(server, Err(TlsError::Denied(_))));
+    let peer = rx.recv().unwrap();
+    assert!(peer.certificates().is_empty());
+    assert_eq!(peer.end_entity(), None);
+    assert_eq!(peer.spiffe_id(), None);
+    assert!(peer.common_names().is_empty());
+}
+
+#[test]
+fn denied_error() {
+    let e = TlsError::from(Deny::new("no"));
+    assert_eq!(e.to_string(), "access denied: no");
+    let e = std::io::Error::from(e);
+    assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied);
+}
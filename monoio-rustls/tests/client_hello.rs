@@ -0,0 +1,109 @@
+mod common;
+
+use std::{io, sync::Arc};
+
+use monoio::io::{AsyncReadRentExt, AsyncWriteRentExt};
+use monoio_rustls::{ClientHelloError, PeekedHello, TlsAcceptor, TlsConnector};
+use rustls::ClientConnection;
+
+fn hello_error(e: &io::Error) -> Option<ClientHelloError> {
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    e.get_ref()?.downcast_ref().copied()
+}
+
+fn client_config() -> rustls::ClientConfig {
+    let mut config = common::client_config();
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    config
+}
+
+#[monoio::test]
+async fn route_and_accept() {
+    let acceptor = TlsAcceptor::from(common::server_config());
+    let connector = TlsConnector::from(client_config());
+    let (client, server) = common::tcp_pair().await;
+    let server = async {
+        let hello = PeekedHello::read(server).await.unwrap();
+        assert_eq!(hello.server_name(), Some("localhost"));
+        assert_eq!(
+            hello.alpn_protocols(),
+            [b"h2".to_vec(), b"http/1.1".to_vec()]
+        );
+        assert_eq!(hello.buffered()[0], 22);
+        let mut stream = hello.accept(&acceptor).await.unwrap();
+        let (res, buf) = stream.read_exact(vec![0; 4]).await;
+        res.unwrap();
+        buf
+    };
+    let client = async {
+        let mut stream = connector
+            .connect(common::localhost(), client)
+            .await
+            .unwrap();
+        let (res, _) = stream.write_all(b"ping").await;
+        res.unwrap();
+        stream
+    };
+    let (buf, _client) = monoio::join!(server, client);
+    assert_eq!(buf, b"ping");
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn fragmented_records() {
+    // a real ClientHello, split in records of 50 bytes written one by one.
+    let mut conn = ClientConnection::new(Arc::new(client_config()), common::localhost()).unwrap();
+    let mut record = Vec::new();
+    conn.write_tls(&mut record).unwrap();
+    let message = &record[5..];
+    let records: Vec<Vec<u8>> = message
+        .chunks(50)
+        .map(|chunk| {
+            let mut record = vec![22, 3, 1];
+            record.extend_from_slice(&(chunk.len() as u16).to_be_bytes());
+            record.extend_from_slice(chunk);
+            record
+        })
+        .collect();
+    assert!(records.len() > 2);
+
+    let (mut client, server) = common::tcp_pair().await;
+    let client = async {
+        for record in records.clone() {
+            let (res, _) = client.write_all(record).await;
+            res.unwrap();
+            monoio::time::sleep(std::time::Duration::from_millis(1)).await;
+        }
+        client
+    };
+    let (hello, _client) = monoio::join!(PeekedHello::read(server), client);
+    let hello = hello.unwrap();
+    assert_eq!(hello.server_name(), Some("localhost"));
+    assert_eq!(hello.alpn_protocols().len(), 2);
+    let (_, buffered) = hello.into_parts();
+    assert_eq!(buffered, records.concat());
+}
+
+#[monoio::test]
+async fn rejected() {
+    for (input, error) in [
+        (
+            &b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n"[..],
+            ClientHelloError::NotTls,
+        ),
+        (&[22, 3, 1, 0, 4, 1, 1, 0, 0], ClientHelloError::TooLarge),
+        (&[22, 3, 1, 0, 0], ClientHelloError::Malformed),
+    ] {
+        let (mut client, server) = common::tcp_pair().await;
+        let (res, _) = client.write_all(input.to_vec()).await;
+        res.unwrap();
+        let e = PeekedHello::read(server).await.unwrap_err();
+        assert_eq!(hello_error(&e), Some(error));
+    }
+
+    let (mut client, server) = common::tcp_pair().await;
+    let (res, _) = client.write_all(vec![22, 3, 1, 0, 40, 1]).await;
+    res.unwrap();
+    drop(client);
+    let e = PeekedHello::read(server).await.unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,143 @@
+#![allow(dead_code)]
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+use monoio::net::{TcpListener, TcpStream};
+use monoio_rustls::{ClientTlsStream, ServerTlsStream, TlsAcceptor, TlsConnector};
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+use rustls::{
+    crypto::{ring, CryptoProvider},
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName},
+    ClientConfig, RootCertStore, ServerConfig,
+};
+
+/// A CA and a leaf certificate issued by it.
+pub struct Pki {
+    pub ca: rcgen::Certificate,
+    pub ca_key: KeyPair,
+    pub cert: rcgen::Certificate,
+    pub key: KeyPair,
+}
+
+impl Pki {
+    pub fn ca_der(&self) -> CertificateDer<'static> {
+        self.ca.der().clone()
+    }
+
+    pub fn chain(&self) -> Vec<CertificateDer<'static>> {
+        vec![self.cert.der().clone()]
+    }
+
+    pub fn key_der(&self) -> PrivateKeyDer<'static> {
+        PrivatePkcs8KeyDer::from(self.key.serialize_der()).into()
+    }
+
+    pub fn roots(&self) -> RootCertStore {
+        let mut roots = RootCertStore::empty();
+        roots.add(self.ca_der()).unwrap();
+        roots
+    }
+
+    /// Issue a client certificate with the given SANs.
+    pub fn issue_client(
+        &self,
+        names: &[&str],
+    ) -> (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) {
+        let key = KeyPair::generate().unwrap();
+        let mut params =
+            CertificateParams::new(names.iter().map(|n| n.to_string()).collect::<Vec<_>>())
+                .unwrap();
+        params.distinguished_name.push(DnType::CommonName, "client");
+        let cert = params.signed_by(&key, &self.ca, &self.ca_key).unwrap();
+        (
+            vec![cert.der().clone()],
+            PrivatePkcs8KeyDer::from(key.serialize_der()).into(),
+        )
+    }
+}
+
+pub fn provider() -> Arc<CryptoProvider> {
+    let _ = ring::default_provider().install_default();
+    CryptoProvider::get_default().unwrap().clone()
+}
+
+pub fn pki() -> &'static Pki {
+    static PKI: OnceLock<Pki> = OnceLock::new();
+    PKI.get_or_init(|| generate(CertificateParams::new(vec!["localhost".to_string()]).unwrap()))
+}
+
+/// Generate a new CA and a leaf certificate with `params` issued by it.
+pub fn generate(mut params: CertificateParams) -> Pki {
+    provider();
+    let ca_key = KeyPair::generate().unwrap();
+    let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    ca_params
+        .distinguished_name
+        .push(DnType::CommonName, "monoio-tls test CA");
+    let ca = ca_params.self_signed(&ca_key).unwrap();
+
+    let key = KeyPair::generate().unwrap();
+    params.distinguished_name.push(DnType::CommonName, "leaf");
+    let cert = params.signed_by(&key, &ca, &ca_key).unwrap();
+    Pki {
+        ca,
+        ca_key,
+        cert,
+        key,
+    }
+}
+
+pub fn server_config() -> ServerConfig {
+    let pki = pki();
+    ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(pki.chain(), pki.key_der())
+        .unwrap()
+}
+
+pub fn client_config() -> ClientConfig {
+    ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(pki().roots())
+        .with_no_client_auth()
+}
+
+/// A fresh directory under the system temp dir.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("monoio-rustls-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn localhost() -> ServerName<'static> {
+    ServerName::try_from("localhost").unwrap()
+}
+
+/// Returns both ends of a loopback tcp connection.
+pub async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, server) = monoio::join!(TcpStream::connect(addr), listener.accept());
+    (client.unwrap(), server.unwrap().0)
+}
+
+/// Returns both ends of a tls connection to `localhost` over loopback tcp.
+pub async fn tls_pair(
+    connector: &TlsConnector,
+    acceptor: &TlsAcceptor,
+) -> (ClientTlsStream<TcpStream>, ServerTlsStream<TcpStream>) {
+    let (client, server) = tcp_pair().await;
+    let (client, server) = monoio::join!(
+        connector.connect(localhost(), client),
+        acceptor.accept(server)
+    );
+    (client.unwrap(), server.unwrap())
+}
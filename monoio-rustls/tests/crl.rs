@@ -0,0 +1,264 @@
+#![cfg(feature = "pem")]
+
+mod common;
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use monoio_rustls::{
+    CertRevoked, CrlClientVerifier, CrlError, RevocationReason, TlsAcceptor, TlsConnector, TlsError,
+};
+use rcgen::{
+    date_time_ymd, BasicConstraints, CertificateParams, CertificateRevocationListParams, DnType,
+    IsCa, KeyIdMethod, KeyPair, RevokedCertParams, SerialNumber,
+};
+use rustls::{
+    pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+    ClientConfig, ServerConfig,
+};
+
+/// A directory with the CA bundle as `ca.pem` and an empty `crls` directory.
+fn setup(name: &str) -> PathBuf {
+    let dir = common::temp_dir(name);
+    std::fs::write(dir.join("ca.pem"), common::pki().ca.pem()).unwrap();
+    std::fs::create_dir(dir.join("crls")).unwrap();
+    dir
+}
+
+/// Write a CRL of the test CA revoking the given serials.
+fn write_crl(path: &Path, number: u64, revoked: &[(u8, Option<rcgen::RevocationReason>)]) {
+    let pki = common::pki();
+    let crl = CertificateRevocationListParams {
+        this_update: date_time_ymd(2026, 1, 1),
+        next_update: date_time_ymd(2126, 1, 1),
+        crl_number: SerialNumber::from(number),
+        issuing_distribution_point: None,
+        revoked_certs: revoked
+            .iter()
+            .map(|&(serial, reason_code)| RevokedCertParams {
+                serial_number: SerialNumber::from_slice(&[serial]),
+                revocation_time: date_time_ymd(2026, 1, 1),
+                reason_code,
+                invalidity_date: None,
+            })
+            .collect(),
+        key_identifier_method: KeyIdMethod::Sha256,
+    }
+    .signed_by(&pki.ca, &pki.ca_key)
+    .unwrap();
+    if path.extension().is_some_and(|ext| ext == "der") {
+        std::fs::write(path, crl.der()).unwrap();
+    } else {
+        std::fs::write(path, crl.pem().unwrap()).unwrap();
+    }
+}
+
+/// Issue a client certificate of the test CA with the serial number.
+fn client(serial: u8) -> TlsConnector {
+    let pki = common::pki();
+    client_of(&pki.ca, &pki.ca_key, serial)
+}
+
+fn client_of(ca: &rcgen::Certificate, ca_key: &KeyPair, serial: u8) -> TlsConnector {
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params.serial_number = Some(SerialNumber::from_slice(&[serial]));
+    params.distinguished_name.push(DnType::CommonName, "device");
+    let cert = params.signed_by(&key, ca, ca_key).unwrap();
+    let key: PrivateKeyDer = PrivatePkcs8KeyDer::from(key.serialize_der()).into();
+    ClientConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(common::pki().roots())
+        .with_client_auth_cert(vec![cert.der().clone()], key)
+        .unwrap()
+        .into()
+}
+
+fn acceptor(verifier: Arc<CrlClientVerifier>) -> TlsAcceptor {
+    let pki = common::pki();
+    ServerConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(pki.chain(), pki.key_der())
+        .unwrap()
+        .into()
+}
+
+fn verifier(dir: &Path) -> Result<CrlClientVerifier, CrlError> {
+    CrlClientVerifier::new(dir.join("ca.pem"), dir.join("crls"), common::provider())
+}
+
+/// Returns the server side result of a handshake.
+async fn accept(connector: &TlsConnector, acceptor: &TlsAcceptor) -> Result<(), TlsError> {
+    let (client, server) = common::tcp_pair().await;
+    let (_client, server) = monoio::join!(
+        connector.connect(common::localhost(), client),
+        acceptor.accept(server)
+    );
+    server.map(drop)
+}
+
+#[monoio::test]
+async fn revoked_client_rejected() {
+    let dir = setup("crl-revoked");
+    write_crl(
+        &dir.join("crls/ca.crl"),
+        1,
+        &[(1, Some(rcgen::RevocationReason::KeyCompromise)), (3, None)],
+    );
+    let acceptor = acceptor(Arc::new(verifier(&dir).unwrap()));
+
+    let e = accept(&client(1), &acceptor).await.unwrap_err();
+    assert_eq!(
+        CertRevoked::from_tls_error(&e),
+        Some(&CertRevoked {
+            depth: 0,
+            serial: vec![1],
+            reason: RevocationReason::KeyCompromise,
+        })
+    );
+    // without a reason code the entry is reported as unspecified.
+    let e = accept(&client(3), &acceptor).await.unwrap_err();
+    assert_eq!(
+        CertRevoked::from_tls_error(&e).map(|r| r.reason),
+        Some(RevocationReason::Unspecified)
+    );
+    accept(&client(2), &acceptor).await.unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[monoio::test]
+async fn crl_formats() {
+    let dir = setup("crl-formats");
+    for (file, serial) in [("ca.pem", 1), ("ca.der", 2)] {
+        write_crl(&dir.join("crls").join(file), 1, &[(serial, None)]);
+        let acceptor = acceptor(Arc::new(verifier(&dir).unwrap()));
+        let e = accept(&client(serial), &acceptor).await.unwrap_err();
+        assert_eq!(
+            CertRevoked::from_tls_error(&e).map(|r| r.serial.clone()),
+            Some(vec![serial])
+        );
+        accept(&client(4), &acceptor).await.unwrap();
+        std::fs::remove_file(dir.join("crls").join(file)).unwrap();
+    }
+
+    std::fs::write(dir.join("crls/ca.pem"), "not a crl").unwrap();
+    assert!(matches!(verifier(&dir), Err(CrlError::InvalidCrl(path)) if path.ends_with("ca.pem")));
+    assert!(matches!(
+        CrlClientVerifier::new(dir.join("ca.pem"), dir.join("missing"), common::provider()),
+        Err(CrlError::Io { .. })
+    ));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[monoio::test]
+async fn unknown_revocation_status() {
+    let dir = setup("crl-unknown");
+    // a second CA in the bundle without a CRL.
+    let other_key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "monoio-tls other CA");
+    let other = params.self_signed(&other_key).unwrap();
+    let bundle = format!("{}{}", common::pki().ca.pem(), other.pem());
+    std::fs::write(dir.join("ca.pem"), bundle).unwrap();
+    write_crl(&dir.join("crls/ca.crl"), 1, &[(1, None)]);
+
+    let strict = acceptor(Arc::new(verifier(&dir).unwrap()));
+    accept(&client(2), &strict).await.unwrap();
+    let e = accept(&client_of(&other, &other_key, 2), &strict)
+        .await
+        .unwrap_err();
+    assert!(CertRevoked::from_tls_error(&e).is_none());
+    assert!(
+        format!("{e:?}").contains("UnknownRevocationStatus"),
+        "{e:?}"
+    );
+
+    let lenient = CrlClientVerifier::with_builder(
+        dir.join("ca.pem"),
+        dir.join("crls"),
+        common::provider(),
+        |builder| builder.allow_unknown_revocation_status(),
+    )
+    .unwrap();
+    let lenient = acceptor(Arc::new(lenient));
+    accept(&client_of(&other, &other_key, 2), &lenient)
+        .await
+        .unwrap();
+    assert!(accept(&client(1), &lenient).await.is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[monoio::test]
+async fn empty_crl_dir() {
+    // without any CRL revocation is not checked.
+    let dir = setup("crl-empty");
+    let acceptor = acceptor(Arc::new(verifier(&dir).unwrap()));
+    accept(&client(1), &acceptor).await.unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[monoio::test]
+async fn reload_crls() {
+    let dir = setup("crl-reload");
+    write_crl(&dir.join("crls/ca.crl"), 1, &[]);
+    let verifier = Arc::new(verifier(&dir).unwrap());
+    let acceptor = acceptor(verifier.clone());
+    accept(&client(1), &acceptor).await.unwrap();
+
+    write_crl(&dir.join("crls/ca.crl"), 2, &[(1, None)]);
+    // not picked up until reloaded.
+    accept(&client(1), &acceptor).await.unwrap();
+    verifier.reload().unwrap();
+    assert!(accept(&client(1), &acceptor).await.is_err());
+
+    // an invalid directory keeps the current CRLs.
+    std::fs::write(dir.join("crls/ca.crl"), "not a crl").unwrap();
+    assert!(matches!(verifier.reload(), Err(CrlError::InvalidCrl(_))));
+    assert!(accept(&client(1), &acceptor).await.is_err());
+    accept(&client(2), &acceptor).await.unwrap();
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn watch_crls() {
+    let dir = setup("crl-watch");
+    write_crl(&dir.join("crls/ca.crl"), 1, &[]);
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let verifier = Arc::new(verifier(&dir).unwrap().on_error({
+        let errors = errors.clone();
+        move |e| errors.lock().unwrap().push(e.to_string())
+    }));
+    let acceptor = acceptor(verifier.clone());
+    monoio::spawn(verifier.watch(Duration::from_millis(10)));
+
+    write_crl(&dir.join("crls/ca.crl"), 2, &[(1, None)]);
+    monoio::time::sleep(Duration::from_millis(50)).await;
+    assert!(accept(&client(1), &acceptor).await.is_err());
+    assert!(errors.lock().unwrap().is_empty());
+
+    std::fs::write(dir.join("crls/ca.crl"), "not a crl").unwrap();
+    monoio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!errors.lock().unwrap().is_empty());
+    assert!(accept(&client(1), &acceptor).await.is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn revocation_reasons() {
+    for code in [0, 1, 2, 3, 4, 5, 6, 8, 9, 10] {
+        let reason = RevocationReason::from(code);
+        assert_eq!(reason == RevocationReason::Unspecified, code == 0);
+    }
+    assert_eq!(RevocationReason::from(6), RevocationReason::CertificateHold);
+    assert_eq!(RevocationReason::from(7), RevocationReason::Unspecified);
+    assert_eq!(RevocationReason::from(11), RevocationReason::Unspecified);
+}
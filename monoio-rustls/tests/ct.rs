@@ -0,0 +1,425 @@
+mod common;
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use monoio::io::{AsyncReadRent, AsyncWriteRentExt};
+use monoio_rustls::{
+    CtLog, CtPolicyError, CtVerifier, SctSource, SpkiPinVerifier, TlsAcceptor, TlsConnector,
+};
+use openssl::{
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    sha::sha256,
+    sign::Signer,
+};
+use rcgen::{CertificateParams, CustomExtension, KeyPair, SerialNumber};
+use rustls::{
+    client::{danger::ServerCertVerifier, WebPkiServerVerifier},
+    pki_types::{CertificateDer, PrivatePkcs8KeyDer, SubjectPublicKeyInfoDer},
+    HandshakeKind, ServerConfig,
+};
+
+/// A CT log signing SCTs with its own key.
+struct Log {
+    key: PKey<Private>,
+    spki: Vec<u8>,
+}
+
+impl Log {
+    fn new() -> Self {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let spki = key.public_key_to_der().unwrap();
+        Self { key, spki }
+    }
+
+    fn ct_log(&self, description: &str) -> CtLog {
+        CtLog::new(
+            description,
+            SubjectPublicKeyInfoDer::from(self.spki.clone()),
+        )
+    }
+
+    /// An SCT for the entry(the type and the signed entry) at the time.
+    fn sct(&self, entry: &[u8], timestamp: SystemTime) -> Vec<u8> {
+        let timestamp = timestamp.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        let mut message = vec![0, 0];
+        message.extend_from_slice(&timestamp.to_be_bytes());
+        message.extend_from_slice(entry);
+        message.extend_from_slice(&[0, 0]);
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key).unwrap();
+        let signature = signer.sign_oneshot_to_vec(&message).unwrap();
+
+        let mut sct = vec![0];
+        sct.extend_from_slice(&sha256(&self.spki));
+        sct.extend_from_slice(&timestamp.to_be_bytes());
+        sct.extend_from_slice(&[0, 0, 0x04, 0x03]);
+        sct.extend(tls_vector(&signature));
+        sct
+    }
+}
+
+fn tls_vector(value: &[u8]) -> Vec<u8> {
+    let mut out = (value.len() as u16).to_be_bytes().to_vec();
+    out.extend_from_slice(value);
+    out
+}
+
+fn u24_vector(value: &[u8]) -> Vec<u8> {
+    let mut out = (value.len() as u32).to_be_bytes()[1..].to_vec();
+    out.extend_from_slice(value);
+    out
+}
+
+fn sct_list(scts: &[Vec<u8>]) -> Vec<u8> {
+    tls_vector(
+        &scts
+            .iter()
+            .flat_map(|sct| tls_vector(sct))
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn der(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match value.len() {
+        len @ 0..0x80 => out.push(len as u8),
+        len @ 0x80..0x100 => out.extend_from_slice(&[0x81, len as u8]),
+        len => out.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]),
+    }
+    out.extend_from_slice(value);
+    out
+}
+
+/// Returns the length of the DER header and of the value at the start.
+fn der_len(der: &[u8]) -> (usize, usize) {
+    match der[1] {
+        len @ 0..=0x7f => (2, len as usize),
+        0x81 => (3, der[2] as usize),
+        _ => (4, u16::from_be_bytes([der[2], der[3]]) as usize),
+    }
+}
+
+/// Returns the TBSCertificate of the certificate.
+fn tbs(cert: &[u8]) -> Vec<u8> {
+    let tbs = &cert[der_len(cert).0..];
+    let (header, len) = der_len(tbs);
+    tbs[..header + len].to_vec()
+}
+
+const SCT_LIST: [u64; 10] = [1, 3, 6, 1, 4, 1, 11129, 2, 4, 2];
+
+type Scts<'a> = &'a [(&'a Log, SystemTime)];
+
+/// A server with a certificate of the test CA, with SCTs from the logs
+/// embedded and stapled in an OCSP response. The chain holds the CA, the
+/// embedded SCTs are verified with its key.
+fn server(embedded: Scts<'_>, stapled: Scts<'_>) -> TlsAcceptor {
+    let pki = common::pki();
+    let key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+    params.serial_number = Some(SerialNumber::from_slice(&[0x42]));
+    let precert = params
+        .clone()
+        .signed_by(&key, &pki.ca, &pki.ca_key)
+        .unwrap();
+
+    let cert = if embedded.is_empty() {
+        precert
+    } else {
+        // precert_entry: the hash of the issuer key and the TBSCertificate.
+        let mut entry = vec![0, 1];
+        entry.extend_from_slice(&sha256(&pki.ca_key.public_key_der()));
+        entry.extend(u24_vector(&tbs(precert.der())));
+        let scts = embedded
+            .iter()
+            .map(|(log, timestamp)| log.sct(&entry, *timestamp))
+            .collect::<Vec<_>>();
+        params
+            .custom_extensions
+            .push(CustomExtension::from_oid_content(
+                &SCT_LIST,
+                der(0x04, &sct_list(&scts)),
+            ));
+        params.signed_by(&key, &pki.ca, &pki.ca_key).unwrap()
+    };
+
+    let chain = vec![cert.der().clone(), pki.ca_der()];
+    let key = PrivatePkcs8KeyDer::from(key.serialize_der()).into();
+    let builder = ServerConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth();
+    let config = if stapled.is_empty() {
+        builder.with_single_cert(chain, key)
+    } else {
+        let ocsp = ocsp_response(cert.der(), stapled);
+        builder.with_single_cert_with_ocsp(chain, key, ocsp)
+    }
+    .unwrap();
+    config.into()
+}
+
+/// An OCSP response for the certificate with the SCTs of the logs, only the
+/// parts read by the client are meaningful.
+fn ocsp_response(cert: &CertificateDer<'_>, logs: Scts<'_>) -> Vec<u8> {
+    // x509_entry: the certificate.
+    let mut entry = vec![0, 0];
+    entry.extend(u24_vector(cert));
+    let scts = logs
+        .iter()
+        .map(|(log, timestamp)| log.sct(&entry, *timestamp))
+        .collect::<Vec<_>>();
+
+    const SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+    const OCSP_SCT_LIST: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xd6, 0x79, 0x02, 0x04, 0x05];
+    const OCSP_BASIC: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01];
+    const ECDSA_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+    let time = der(0x18, b"20260101000000Z");
+    let cert_id = der(
+        0x30,
+        &[
+            der(0x30, &[der(0x06, SHA1), vec![0x05, 0x00]].concat()),
+            der(0x04, &[0; 20]),
+            der(0x04, &[0; 20]),
+            der(0x02, &[0x42]),
+        ]
+        .concat(),
+    );
+    let extension = der(
+        0x30,
+        &[
+            der(0x06, OCSP_SCT_LIST),
+            der(0x04, &der(0x04, &sct_list(&scts))),
+        ]
+        .concat(),
+    );
+    let single = der(
+        0x30,
+        &[
+            cert_id,
+            vec![0x80, 0x00],
+            time.clone(),
+            der(0xa1, &der(0x30, &extension)),
+        ]
+        .concat(),
+    );
+    let data = der(
+        0x30,
+        &[der(0xa2, &der(0x04, &[0; 20])), time, der(0x30, &single)].concat(),
+    );
+    let basic = der(
+        0x30,
+        &[data, der(0x30, &der(0x06, ECDSA_SHA256)), der(0x03, &[0])].concat(),
+    );
+    der(
+        0x30,
+        &[
+            der(0x0a, &[0]),
+            der(
+                0xa0,
+                &der(0x30, &[der(0x06, OCSP_BASIC), der(0x04, &basic)].concat()),
+            ),
+        ]
+        .concat(),
+    )
+}
+
+fn webpki() -> Arc<dyn ServerCertVerifier> {
+    WebPkiServerVerifier::builder_with_provider(Arc::new(common::pki().roots()), common::provider())
+        .build()
+        .unwrap()
+}
+
+fn ct_verifier(logs: &[CtLog]) -> CtVerifier {
+    CtVerifier::new(webpki(), logs.to_vec(), common::provider()).unwrap()
+}
+
+fn connector(ct: CtVerifier) -> TlsConnector {
+    TlsConnector::from(common::client_config()).certificate_transparency(Arc::new(ct))
+}
+
+fn past() -> SystemTime {
+    SystemTime::now() - Duration::from_secs(3600)
+}
+
+async fn connect_err(connector: &TlsConnector, acceptor: &TlsAcceptor) -> monoio_rustls::TlsError {
+    let (client, server) = common::tcp_pair().await;
+    let (client, _server) = monoio::join!(
+        connector.connect(common::localhost(), client),
+        acceptor.accept(server)
+    );
+    client.err().unwrap()
+}
+
+#[monoio::test]
+async fn embedded_scts() {
+    let (a, b) = (Log::new(), Log::new());
+    let acceptor = server(&[(&a, past()), (&b, past())], &[]);
+    let connector = connector(ct_verifier(&[a.ct_log("log a"), b.ct_log("log b")]));
+    let (mut client, mut server) = common::tls_pair(&connector, &acceptor).await;
+    // reads the session tickets sent after the handshake.
+    server.write_all(b"ping").await.0.unwrap();
+    let (n, _) = client.read(vec![0; 16]).await;
+    assert_eq!(n.unwrap(), 4);
+
+    let scts = client.scts();
+    assert_eq!(scts.len(), 2);
+    assert!(scts.iter().all(|sct| sct.source == SctSource::Certificate));
+    assert_eq!(scts[0].log_id, sha256(&a.spki));
+    assert_eq!(scts[0].log_description, "log a");
+    assert_eq!(scts[1].log_description, "log b");
+    assert!(scts[0].timestamp <= SystemTime::now());
+
+    // resumed sessions are not verified again.
+    let (client, _server) = common::tls_pair(&connector, &acceptor).await;
+    assert_eq!(
+        client.get_ref().1.handshake_kind(),
+        Some(HandshakeKind::Resumed)
+    );
+    assert!(client.scts().is_empty());
+}
+
+#[monoio::test]
+async fn policy() {
+    let (a, b, unknown) = (Log::new(), Log::new(), Log::new());
+    let logs = [a.ct_log("log a"), b.ct_log("log b")];
+    let future = SystemTime::now() + Duration::from_secs(3600);
+    // the same log twice, an unknown log and a timestamp in the future do
+    // not count.
+    let acceptor = server(
+        &[(&a, past()), (&a, past()), (&unknown, past()), (&b, future)],
+        &[],
+    );
+    let e = connect_err(&connector(ct_verifier(&logs)), &acceptor).await;
+    assert_eq!(
+        CtPolicyError::from_tls_error(&e),
+        Some(&CtPolicyError {
+            logs: 1,
+            required: 2
+        })
+    );
+
+    let ct = ct_verifier(&logs).min_distinct_logs(1);
+    let (client, _server) = common::tls_pair(&connector(ct), &acceptor).await;
+    assert_eq!(client.scts().len(), 2);
+
+    // without SCTs at all.
+    let e = connect_err(&connector(ct_verifier(&logs)), &server(&[], &[])).await;
+    assert_eq!(CtPolicyError::from_tls_error(&e).map(|e| e.logs), Some(0));
+}
+
+#[monoio::test]
+async fn bad_signature() {
+    let (a, b) = (Log::new(), Log::new());
+    let acceptor = server(&[(&a, past()), (&b, past())], &[]);
+    // "log b" has another key than the one signing its SCTs.
+    let logs = [
+        a.ct_log("log a"),
+        CtLog::new("log b", Log::new().spki.into()),
+    ];
+    let ct = ct_verifier(&logs).min_distinct_logs(1);
+    let (client, _server) = common::tls_pair(&connector(ct), &acceptor).await;
+    assert_eq!(client.scts().len(), 1);
+    assert_eq!(client.scts()[0].log_description, "log a");
+}
+
+#[monoio::test]
+async fn ocsp_scts() {
+    let (a, b) = (Log::new(), Log::new());
+    let logs = [a.ct_log("log a"), b.ct_log("log b")];
+
+    let acceptor = server(&[(&a, past())], &[(&b, past())]);
+    let (client, _server) = common::tls_pair(&connector(ct_verifier(&logs)), &acceptor).await;
+    let sources = client
+        .scts()
+        .iter()
+        .map(|sct| sct.source)
+        .collect::<Vec<_>>();
+    assert_eq!(sources, [SctSource::Certificate, SctSource::OcspResponse]);
+
+    let acceptor = server(&[], &[(&a, past()), (&b, past())]);
+    let (client, _server) = common::tls_pair(&connector(ct_verifier(&logs)), &acceptor).await;
+    assert_eq!(client.scts().len(), 2);
+    assert!(client
+        .scts()
+        .iter()
+        .all(|sct| sct.source == SctSource::OcspResponse));
+    // the response is only recorded with capture_ocsp.
+    assert_eq!(client.ocsp_response(), None);
+}
+
+#[monoio::test]
+async fn composed_in_any_order() {
+    let (a, b) = (Log::new(), Log::new());
+    let logs = [a.ct_log("log a"), b.ct_log("log b")];
+    let ct = Arc::new(ct_verifier(&logs));
+    let with_scts = server(&[(&a, past())], &[(&b, past())]);
+    let without_scts = server(&[], &[]);
+
+    let base = || TlsConnector::from(common::client_config());
+    for connector in [
+        base()
+            .certificate_transparency(ct.clone())
+            .capture_ocsp(webpki()),
+        base()
+            .capture_ocsp(webpki())
+            .certificate_transparency(ct.clone()),
+    ] {
+        let (client, _server) = common::tls_pair(&connector, &with_scts).await;
+        assert_eq!(client.scts().len(), 2);
+        assert!(client.ocsp_response().is_some());
+        let e = connect_err(&connector, &without_scts).await;
+        assert!(CtPolicyError::from_tls_error(&e).is_some());
+    }
+
+    // a verifier set afterwards is used below the CT check.
+    let pinned = SpkiPinVerifier::new([], common::provider()).unwrap();
+    let connector = base()
+        .certificate_transparency(ct.clone())
+        .server_cert_verifier(Arc::new(pinned));
+    let e = connect_err(&connector, &with_scts).await;
+    assert!(CtPolicyError::from_tls_error(&e).is_none());
+    let connector = base()
+        .certificate_transparency(ct)
+        .server_cert_verifier(webpki());
+    let (client, _server) = common::tls_pair(&connector, &with_scts).await;
+    assert_eq!(client.scts().len(), 2);
+    let e = connect_err(&connector, &without_scts).await;
+    assert!(CtPolicyError::from_tls_error(&e).is_some());
+}
+
+#[monoio::test]
+async fn recorded_per_connection() {
+    let (a, b) = (Log::new(), Log::new());
+    let logs = [a.ct_log("log a"), b.ct_log("log b")];
+    let embedded = server(&[(&a, past()), (&b, past())], &[]);
+    let stapled = server(&[], &[(&a, past()), (&b, past())]);
+    // sessions are not resumed, every handshake verifies the SCTs.
+    let mut config = common::client_config();
+    config.resumption = rustls::client::Resumption::disabled();
+    let connector =
+        TlsConnector::from(config).certificate_transparency(Arc::new(ct_verifier(&logs)));
+    for _ in 0..4 {
+        let (x, y) = monoio::join!(
+            common::tls_pair(&connector, &embedded),
+            common::tls_pair(&connector, &stapled)
+        );
+        assert!(x
+            .0
+            .scts()
+            .iter()
+            .all(|sct| sct.source == SctSource::Certificate));
+        assert!(y
+            .0
+            .scts()
+            .iter()
+            .all(|sct| sct.source == SctSource::OcspResponse));
+        assert_eq!((x.0.scts().len(), y.0.scts().len()), (2, 2));
+    }
+}
@@ -0,0 +1,255 @@
+mod common;
+
+use std::sync::Arc;
+
+use monoio::io::{AsyncReadRent, AsyncWriteRentExt};
+use monoio_rustls::{DaneError, DaneVerifier, TlsAcceptor, TlsConnector, TlsError, TlsaRecord};
+use openssl::sha::{sha256, sha512};
+use rustls::{
+    client::WebPkiServerVerifier,
+    pki_types::{CertificateDer, ServerName},
+    CertificateError, ClientConfig, HandshakeKind, RootCertStore, ServerConfig,
+};
+
+/// A server sending the leaf and the CA of the test PKI.
+fn acceptor() -> TlsAcceptor {
+    let pki = common::pki();
+    let chain = vec![pki.cert.der().clone(), pki.ca_der()];
+    ServerConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(chain, pki.key_der())
+        .unwrap()
+        .into()
+}
+
+/// A connector trusting no root at all, so only the records authenticate
+/// the server.
+fn connector() -> TlsConnector {
+    ClientConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(RootCertStore::empty())
+        .with_no_client_auth()
+        .into()
+}
+
+fn leaf() -> &'static CertificateDer<'static> {
+    common::pki().cert.der()
+}
+
+fn leaf_spki() -> Vec<u8> {
+    common::pki().key.public_key_der()
+}
+
+async fn connect(
+    name: &str,
+    records: Vec<TlsaRecord>,
+) -> Result<monoio_rustls::ClientTlsStream<monoio::net::TcpStream>, TlsError> {
+    let acceptor = acceptor();
+    let connector = connector();
+    let (client, server) = common::tcp_pair().await;
+    let name = ServerName::try_from(name.to_string()).unwrap();
+    let (client, _server) = monoio::join!(
+        connector.connect_with_tlsa(name, client, records),
+        acceptor.accept(server)
+    );
+    client
+}
+
+fn certificate_error(e: &TlsError) -> Option<CertificateError> {
+    let TlsError::Io(e) = e else { return None };
+    match e.get_ref()?.downcast_ref::<rustls::Error>()? {
+        rustls::Error::InvalidCertificate(e) => Some(e.clone()),
+        _ => None,
+    }
+}
+
+#[monoio::test]
+async fn dane_ee() {
+    // the names and the issuer of the certificate are not checked.
+    for record in [
+        TlsaRecord::new(3, 1, 1, sha256(&leaf_spki())),
+        TlsaRecord::new(3, 1, 0, leaf_spki()),
+        TlsaRecord::new(3, 0, 1, sha256(leaf())),
+        TlsaRecord::new(3, 0, 0, leaf().to_vec()),
+        TlsaRecord::new(3, 1, 2, sha512(&leaf_spki())),
+    ] {
+        connect("example.com", vec![record.clone()])
+            .await
+            .unwrap_or_else(|e| panic!("{record:?}: {e:?}"));
+    }
+
+    let e = connect("localhost", vec![TlsaRecord::new(3, 1, 1, [0; 32])])
+        .await
+        .unwrap_err();
+    assert_eq!(DaneError::from_tls_error(&e), Some(&DaneError::NoMatch));
+}
+
+#[monoio::test]
+async fn dane_ta() {
+    let ca = common::pki().ca_der();
+    let record = TlsaRecord::new(2, 0, 1, sha256(&ca));
+    connect("localhost", vec![record.clone()]).await.unwrap();
+    connect("localhost", vec![TlsaRecord::new(2, 0, 2, sha512(&ca))])
+        .await
+        .unwrap();
+
+    // the chain is verified up to the anchor, including the name.
+    let e = connect("example.com", vec![record]).await.unwrap_err();
+    assert!(matches!(
+        certificate_error(&e),
+        Some(CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. })
+    ));
+
+    // the end-entity certificate is not a trust anchor.
+    let e = connect("localhost", vec![TlsaRecord::new(2, 0, 1, sha256(leaf()))])
+        .await
+        .unwrap_err();
+    assert_eq!(DaneError::from_tls_error(&e), Some(&DaneError::NoMatch));
+}
+
+#[monoio::test]
+async fn any_record_matches() {
+    let records = vec![
+        TlsaRecord::new(3, 1, 1, [0; 32]),
+        TlsaRecord::new(2, 0, 1, [0; 32]),
+        TlsaRecord::new(3, 1, 1, sha256(&leaf_spki())),
+    ];
+    connect("localhost", records).await.unwrap();
+}
+
+#[monoio::test]
+async fn unusable_records() {
+    let spki = leaf_spki();
+    for records in [
+        vec![],
+        // PKIX usages without a PKIX verifier.
+        vec![
+            TlsaRecord::new(1, 1, 1, sha256(&spki)),
+            TlsaRecord::new(0, 0, 1, sha256(&common::pki().ca_der())),
+        ],
+        // an unknown matching type, selector and usage.
+        vec![
+            TlsaRecord::new(3, 1, 3, sha512(&spki)),
+            TlsaRecord::new(3, 2, 1, sha256(&spki)),
+            TlsaRecord::new(4, 1, 1, sha256(&spki)),
+        ],
+    ] {
+        let e = connect("localhost", records).await.unwrap_err();
+        assert_eq!(
+            DaneError::from_tls_error(&e),
+            Some(&DaneError::NoUsableRecords)
+        );
+    }
+}
+
+#[monoio::test]
+async fn pkix_records() {
+    let pki = common::pki();
+    let webpki =
+        WebPkiServerVerifier::builder_with_provider(Arc::new(pki.roots()), common::provider())
+            .build()
+            .unwrap();
+    let acceptor = acceptor();
+    let handshake = |records: Vec<TlsaRecord>, name: &'static str| {
+        let verifier = DaneVerifier::new(records, common::provider())
+            .unwrap()
+            .pkix(webpki.clone());
+        let connector = connector().server_cert_verifier(Arc::new(verifier));
+        let acceptor = acceptor.clone();
+        async move {
+            let (client, server) = common::tcp_pair().await;
+            let name = ServerName::try_from(name).unwrap();
+            let (client, _server) =
+                monoio::join!(connector.connect(name, client), acceptor.accept(server));
+            client.map(|_| ())
+        }
+    };
+
+    let pkix_ee = TlsaRecord::new(1, 1, 1, sha256(&leaf_spki()));
+    let pkix_ta = TlsaRecord::new(0, 0, 1, sha256(&pki.ca_der()));
+    handshake(vec![pkix_ee.clone()], "localhost").await.unwrap();
+    handshake(vec![pkix_ta.clone()], "localhost").await.unwrap();
+
+    // the record matches but PKIX validation fails.
+    let e = handshake(vec![pkix_ee], "example.com").await.unwrap_err();
+    assert!(certificate_error(&e).is_some());
+    assert_eq!(DaneError::from_tls_error(&e), None);
+
+    // the record does not match a certificate valid for PKIX.
+    let e = handshake(vec![TlsaRecord::new(0, 0, 1, sha256(leaf()))], "localhost")
+        .await
+        .unwrap_err();
+    assert_eq!(DaneError::from_tls_error(&e), Some(&DaneError::NoMatch));
+}
+
+#[monoio::test]
+async fn not_resumed() {
+    let acceptor = acceptor();
+    let connector = connector();
+    let record = TlsaRecord::new(3, 1, 1, sha256(&leaf_spki()));
+    for _ in 0..2 {
+        let (client, server) = common::tcp_pair().await;
+        let (client, server) = monoio::join!(
+            connector.connect_with_tlsa(common::localhost(), client, [record.clone()]),
+            acceptor.accept(server)
+        );
+        let (mut client, mut server) = (client.unwrap(), server.unwrap());
+        // reads the session tickets sent after the handshake.
+        server.write_all(b"ping").await.0.unwrap();
+        let (n, _) = client.read(vec![0; 16]).await;
+        assert_eq!(n.unwrap(), 4);
+        assert_eq!(
+            client.get_ref().1.handshake_kind(),
+            Some(HandshakeKind::Full)
+        );
+    }
+}
+
+#[monoio::test]
+async fn anchor_outside_path() {
+    let pki = common::pki();
+    // a valid chain carrying the CA of another PKI as an extra certificate.
+    let other = common::generate(rcgen::CertificateParams::new(vec!["localhost".into()]).unwrap());
+    let acceptor = TlsAcceptor::from(
+        ServerConfig::builder_with_provider(common::provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![pki.cert.der().clone(), pki.ca_der(), other.ca_der()],
+                pki.key_der(),
+            )
+            .unwrap(),
+    );
+    let webpki =
+        WebPkiServerVerifier::builder_with_provider(Arc::new(pki.roots()), common::provider())
+            .build()
+            .unwrap();
+    let other_ca = sha256(&other.ca_der());
+
+    for record in [
+        TlsaRecord::new(2, 0, 1, other_ca),
+        TlsaRecord::new(0, 0, 1, other_ca),
+    ] {
+        let verifier = DaneVerifier::new([record.clone()], common::provider())
+            .unwrap()
+            .pkix(webpki.clone());
+        let connector = connector().server_cert_verifier(Arc::new(verifier));
+        let (client, server) = common::tcp_pair().await;
+        let (client, _server) = monoio::join!(
+            connector.connect(common::localhost(), client),
+            acceptor.accept(server)
+        );
+        let e = client.map(|_| ()).unwrap_err();
+        assert!(
+            matches!(
+                certificate_error(&e),
+                Some(CertificateError::UnknownIssuer | CertificateError::BadSignature)
+            ),
+            "{record:?}: {e:?}"
+        );
+    }
+}
@@ -0,0 +1,38 @@
+#![cfg(feature = "dangerous")]
+
+mod common;
+
+use monoio_rustls::{TlsAcceptor, TlsConnector};
+use rcgen::CertificateParams;
+use rustls::{pki_types::ServerName, ClientConfig};
+
+#[monoio::test]
+async fn accept_invalid_certs() {
+    let acceptor = TlsAcceptor::from(common::server_config());
+    // trusting another CA, the server is rejected until the verifier is
+    // replaced.
+    let other = common::generate(CertificateParams::new(vec!["localhost".into()]).unwrap());
+    let config = ClientConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(other.roots())
+        .with_no_client_auth();
+    let connector = TlsConnector::from(config);
+
+    for (connector, name, ok) in [
+        (connector.clone(), "localhost", false),
+        (
+            connector.clone().danger_accept_invalid_certs(),
+            "localhost",
+            true,
+        ),
+        (connector.danger_accept_invalid_certs(), "other.test", true),
+    ] {
+        let (client, server) = common::tcp_pair().await;
+        let name = ServerName::try_from(name).unwrap();
+        let (client, server) =
+            monoio::join!(connector.connect(name, client), acceptor.accept(server));
+        assert_eq!(client.is_ok(), ok);
+        assert_eq!(server.is_ok(), ok);
+    }
+}
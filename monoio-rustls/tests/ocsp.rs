@@ -0,0 +1,374 @@
+mod common;
+
+use std::{
+    cell::Cell,
+    io,
+    net::SocketAddr,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use monoio::{
+    io::{AsyncReadRent, AsyncWriteRentExt},
+    net::{TcpListener, TcpStream},
+};
+use monoio_rustls::{OcspCertStatus, OcspError, OcspStapler, TlsAcceptor, TlsConnector};
+use openssl::{
+    asn1::{Asn1Integer, Asn1Time},
+    bn::BigNum,
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::PKey,
+    x509::X509,
+};
+use rustls::{
+    client::WebPkiServerVerifier,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer},
+    sign::CertifiedKey,
+    ClientConfig, RootCertStore, ServerConfig,
+};
+
+const GOOD: &[u8] = include_bytes!("data/ocsp-good.der");
+const GOOD_SHA256: &[u8] = include_bytes!("data/ocsp-good-sha256.der");
+const REVOKED: &[u8] = include_bytes!("data/ocsp-revoked.der");
+
+fn pem_cert(pem: &[u8]) -> CertificateDer<'static> {
+    CertificateDer::from_pem_slice(pem).unwrap()
+}
+
+fn leaf() -> CertificateDer<'static> {
+    pem_cert(include_bytes!("data/ocsp-leaf.pem"))
+}
+
+fn ca() -> CertificateDer<'static> {
+    pem_cert(include_bytes!("data/ocsp-ca.pem"))
+}
+
+/// The key of the fixtures with the chain.
+fn certified_key(chain: Vec<CertificateDer<'static>>) -> Arc<CertifiedKey> {
+    let key = PrivateKeyDer::from_pem_slice(include_bytes!("data/ocsp-leaf.key")).unwrap();
+    let key = common::provider()
+        .key_provider
+        .load_private_key(key)
+        .unwrap();
+    Arc::new(CertifiedKey::new(chain, key))
+}
+
+/// A stapler returning the response.
+fn stapler(chain: Vec<CertificateDer<'static>>, response: &'static [u8]) -> OcspStapler {
+    OcspStapler::with_fetcher(certified_key(chain), move |_| async move {
+        Ok(response.to_vec())
+    })
+}
+
+fn acceptor(stapler: Arc<OcspStapler>) -> TlsAcceptor {
+    ServerConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_cert_resolver(stapler)
+        .into()
+}
+
+fn connector() -> TlsConnector {
+    let mut roots = RootCertStore::empty();
+    roots.add(ca()).unwrap();
+    let roots = Arc::new(roots);
+    let verifier = WebPkiServerVerifier::builder_with_provider(roots.clone(), common::provider())
+        .build()
+        .unwrap();
+    let config = ClientConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    TlsConnector::from(config).capture_ocsp(verifier)
+}
+
+/// An OCSP responder stand-in answering every POSTed request with the
+/// response over HTTP. Returns its address and the count of requests.
+fn responder(response: &'static [u8]) -> (SocketAddr, Rc<Cell<usize>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Rc::new(Cell::new(0));
+    let count = requests.clone();
+    monoio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                let (n, buf) = stream.read(vec![0; 1024]).await;
+                assert_ne!(n.unwrap(), 0);
+                request.extend_from_slice(&buf);
+            }
+            let request = String::from_utf8(request).unwrap();
+            assert!(request.starts_with("POST / HTTP/1.1\r\n"));
+            assert!(request.contains("Content-Type: application/ocsp-request\r\n"));
+            count.set(count.get() + 1);
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/ocsp-response\r\nContent-Length: \
+                 {}\r\nConnection: close\r\n\r\n",
+                response.len()
+            );
+            let (res, _) = stream.write_all([head.as_bytes(), response].concat()).await;
+            res.unwrap();
+        }
+    });
+    (addr, requests)
+}
+
+/// Fetch the response of the responder, as a fetcher of `OcspStapler` would.
+async fn fetch(addr: SocketAddr) -> io::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let request =
+        "POST / HTTP/1.1\r\nHost: ocsp.test\r\nContent-Type: application/ocsp-request\r\n\r\n";
+    stream.write_all(request.as_bytes().to_vec()).await.0?;
+    let mut response = Vec::new();
+    loop {
+        let (n, buf) = stream.read(vec![0; 1024]).await;
+        if n? == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf);
+    }
+    let at = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or(io::ErrorKind::InvalidData)?;
+    if !response.starts_with(b"HTTP/1.1 200 ") {
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+    Ok(response.split_off(at + 4))
+}
+
+#[monoio::test]
+async fn stapled_from_responder() {
+    let (addr, requests) = responder(GOOD);
+    let stapler = Arc::new(OcspStapler::with_fetcher(
+        certified_key(vec![leaf(), ca()]),
+        move |chain| {
+            assert_eq!(chain.len(), 2);
+            fetch(addr)
+        },
+    ));
+    let acceptor = acceptor(stapler.clone());
+
+    // nothing is stapled before the first refresh.
+    assert!(stapler.response().is_none());
+    assert!(stapler.current().ocsp.is_none());
+    let (client, _server) = common::tls_pair(&connector(), &acceptor).await;
+    assert_eq!(client.ocsp_response(), None);
+
+    let response = stapler.refresh().await.unwrap();
+    assert_eq!(requests.get(), 1);
+    assert_eq!(response.der, GOOD);
+    assert_eq!(response.status, OcspCertStatus::Good);
+    assert_eq!(
+        response
+            .next_update
+            .unwrap()
+            .duration_since(response.this_update)
+            .unwrap(),
+        Duration::from_secs(36500 * 86400)
+    );
+    assert_eq!(stapler.response(), Some(response));
+    assert_eq!(stapler.current().ocsp.as_deref(), Some(GOOD));
+
+    let (client, _server) = common::tls_pair(&connector(), &acceptor).await;
+    assert_eq!(client.ocsp_response(), Some(GOOD));
+    // without capture_ocsp nothing is recorded.
+    let mut roots = RootCertStore::empty();
+    roots.add(ca()).unwrap();
+    let plain = ClientConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let (client, _server) = common::tls_pair(&plain.into(), &acceptor).await;
+    assert_eq!(client.ocsp_response(), None);
+}
+
+#[monoio::test]
+async fn recorded_per_connection() {
+    // two servers with the same certificate, only one of them staples.
+    let stapling = Arc::new(stapler(vec![leaf(), ca()], GOOD));
+    stapling.refresh().await.unwrap();
+    let stapling = acceptor(stapling);
+    let plain = acceptor(Arc::new(stapler(vec![leaf(), ca()], GOOD)));
+
+    let connector = connector();
+    for _ in 0..4 {
+        let (a, b) = monoio::join!(
+            common::tls_pair(&connector, &stapling),
+            common::tls_pair(&connector, &plain)
+        );
+        assert_eq!(a.0.ocsp_response(), Some(GOOD));
+        assert_eq!(b.0.ocsp_response(), None);
+    }
+}
+
+#[monoio::test]
+async fn response_checks() {
+    let chain = vec![leaf(), ca()];
+    let response = stapler(chain.clone(), REVOKED).refresh().await.unwrap();
+    assert_eq!(response.status, OcspCertStatus::Revoked);
+    let response = stapler(chain.clone(), GOOD_SHA256).refresh().await.unwrap();
+    assert_eq!(response.status, OcspCertStatus::Good);
+
+    // the issuer must follow the leaf.
+    assert!(matches!(
+        stapler(vec![leaf()], GOOD).refresh().await,
+        Err(OcspError::MissingIssuer)
+    ));
+    // another certificate.
+    let pki = common::pki();
+    assert!(matches!(
+        stapler(vec![pki.cert.der().clone(), pki.ca_der()], GOOD)
+            .refresh()
+            .await,
+        Err(OcspError::CertMismatch)
+    ));
+    // same serial and issuer name, but another issuer key.
+    for response in [GOOD, GOOD_SHA256] {
+        assert!(matches!(
+            stapler(impostor(), response).refresh().await,
+            Err(OcspError::CertMismatch)
+        ));
+    }
+
+    // unauthorized(6)
+    let unauthorized = stapler(chain.clone(), &[0x30, 0x03, 0x0a, 0x01, 0x06]);
+    assert!(matches!(
+        unauthorized.refresh().await,
+        Err(OcspError::Unsuccessful(6))
+    ));
+    assert!(matches!(
+        stapler(chain.clone(), b"not ocsp").refresh().await,
+        Err(OcspError::Malformed)
+    ));
+    assert!(matches!(
+        stapler(vec![CertificateDer::from(vec![0x30, 0x00]), ca()], GOOD)
+            .refresh()
+            .await,
+        Err(OcspError::InvalidCertificate)
+    ));
+}
+
+/// A CA with the name of the fixture CA but another key, and a leaf with the
+/// serial number of the fixture leaf issued by it.
+fn impostor() -> Vec<CertificateDer<'static>> {
+    let fixture = X509::from_der(&ca()).unwrap();
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+    let build = |serial: u32| {
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        let serial = Asn1Integer::from_bn(&BigNum::from_u32(serial).unwrap()).unwrap();
+        builder.set_serial_number(&serial).unwrap();
+        builder.set_issuer_name(fixture.subject_name()).unwrap();
+        builder.set_subject_name(fixture.subject_name()).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        CertificateDer::from(builder.build().to_der().unwrap())
+    };
+    vec![build(0x1234), build(1)]
+}
+
+#[monoio::test]
+async fn from_file() {
+    let dir = common::temp_dir("ocsp-from-file");
+    let path = dir.join("response.der");
+    std::fs::write(&path, GOOD).unwrap();
+    let stapler = OcspStapler::from_file(certified_key(vec![leaf(), ca()]), &path);
+    assert_eq!(stapler.refresh().await.unwrap().der, GOOD);
+
+    // a failed refresh keeps the current response.
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(stapler.refresh().await, Err(OcspError::Io(_))));
+    assert_eq!(stapler.current().ocsp.as_deref(), Some(GOOD));
+    std::fs::write(&path, REVOKED).unwrap();
+    let response = stapler.refresh().await.unwrap();
+    assert_eq!(response.status, OcspCertStatus::Revoked);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn run_refreshes() {
+    let (addr, requests) = responder(GOOD);
+    let stapler = Arc::new(OcspStapler::with_fetcher(
+        certified_key(vec![leaf(), ca()]),
+        move |_| fetch(addr),
+    ));
+    monoio::spawn(stapler.clone().run());
+    monoio::time::sleep(Duration::from_millis(100)).await;
+    // the next refresh is halfway to nextUpdate, in 50 years.
+    assert_eq!(requests.get(), 1);
+    assert_eq!(stapler.current().ocsp.as_deref(), Some(GOOD));
+
+    let errors = Arc::new(AtomicUsize::new(0));
+    let failing = OcspStapler::with_fetcher(certified_key(vec![leaf(), ca()]), |_| async {
+        Err(io::ErrorKind::ConnectionRefused.into())
+    })
+    .retry_interval(Duration::from_millis(10))
+    .on_error({
+        let errors = errors.clone();
+        move |e| {
+            assert!(matches!(e, OcspError::Io(_)));
+            errors.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    monoio::spawn(Arc::new(failing).run());
+    monoio::time::sleep(Duration::from_millis(100)).await;
+    assert!(errors.load(Ordering::Relaxed) >= 2);
+}
+
+/// The good response with other thisUpdate and nextUpdate times, its
+/// signature is not checked by the stapler.
+fn good_at(this_update: &[u8; 15], next_update: &[u8; 15]) -> Vec<u8> {
+    let mut der = GOOD.to_vec();
+    // producedAt, thisUpdate and nextUpdate.
+    let times: Vec<usize> = (0..der.len() - 17)
+        .filter(|&i| der[i..i + 2] == [0x18, 0x0f])
+        .collect();
+    der[times[1] + 2..times[1] + 17].copy_from_slice(this_update);
+    der[times[2] + 2..times[2] + 17].copy_from_slice(next_update);
+    der
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn run_waits_after_stale_response() {
+    // halfway through the validity period has passed already, and the other
+    // response expired.
+    let due = good_at(b"19500101000000Z", b"20500101000000Z");
+    let expired = good_at(b"19500101000000Z", b"20000101000000Z");
+    for (der, stapled) in [(due, true), (expired, false)] {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let stapler = Arc::new(
+            OcspStapler::with_fetcher(certified_key(vec![leaf(), ca()]), {
+                let fetches = fetches.clone();
+                move |_| {
+                    fetches.fetch_add(1, Ordering::Relaxed);
+                    let der = der.clone();
+                    async move { Ok(der) }
+                }
+            })
+            .retry_interval(Duration::from_millis(40)),
+        );
+        monoio::spawn(stapler.clone().run());
+        monoio::time::sleep(Duration::from_millis(100)).await;
+        let fetches = fetches.load(Ordering::Relaxed);
+        assert!((2..=4).contains(&fetches), "{fetches} fetches");
+        assert_eq!(stapler.response().is_some(), stapled);
+    }
+}
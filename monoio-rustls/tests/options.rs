@@ -0,0 +1,228 @@
+mod common;
+
+use std::sync::Arc;
+
+use monoio::{
+    io::{AsyncReadRent, AsyncWriteRentExt},
+    net::TcpStream,
+};
+use monoio_rustls::{
+    ClientTlsStream, ConnectOptions, ServerTlsStream, TlsAcceptor, TlsConnector, TlsError,
+};
+use rustls::{
+    client::{Resumption, WebPkiServerVerifier},
+    pki_types::ServerName,
+    server::WebPkiClientVerifier,
+    sign::CertifiedKey,
+    HandshakeKind, ServerConfig,
+};
+
+/// A server offering `h2` and `http/1.1`, with optional client
+/// certificates of the test CA.
+fn acceptor() -> TlsAcceptor {
+    let pki = common::pki();
+    let verifier =
+        WebPkiClientVerifier::builder_with_provider(Arc::new(pki.roots()), common::provider())
+            .allow_unauthenticated()
+            .build()
+            .unwrap();
+    let mut config = ServerConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(pki.chain(), pki.key_der())
+        .unwrap();
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    config.into()
+}
+
+fn connector() -> TlsConnector {
+    let verifier = WebPkiServerVerifier::builder_with_provider(
+        Arc::new(common::pki().roots()),
+        common::provider(),
+    )
+    .build()
+    .unwrap();
+    TlsConnector::from(common::client_config()).server_cert_verifier(verifier)
+}
+
+fn client_cert() -> Arc<CertifiedKey> {
+    let (chain, key) = common::pki().issue_client(&["client.example"]);
+    Arc::new(CertifiedKey::from_der(chain, key, &common::provider()).unwrap())
+}
+
+/// Connect with the options, then exchange data so that the client reads
+/// the session tickets sent after the handshake.
+async fn connect(
+    connector: &TlsConnector,
+    acceptor: &TlsAcceptor,
+    name: &str,
+    options: Option<&ConnectOptions>,
+) -> Result<(ClientTlsStream<TcpStream>, ServerTlsStream<TcpStream>), TlsError> {
+    let name = ServerName::try_from(name.to_string()).unwrap();
+    let (client, server) = common::tcp_pair().await;
+    let (client, server) = monoio::join!(
+        async {
+            match options {
+                Some(options) => connector.connect_with(name, client, options).await,
+                None => connector.connect(name, client).await,
+            }
+        },
+        acceptor.accept(server)
+    );
+    let mut client = client?;
+    let mut server = server.unwrap();
+    server.write_all(b"ping").await.0.unwrap();
+    let (n, _) = client.read(vec![0; 16]).await;
+    assert_eq!(n.unwrap(), 4);
+    Ok((client, server))
+}
+
+fn handshake_kind(client: &ClientTlsStream<TcpStream>) -> Option<HandshakeKind> {
+    client.get_ref().1.handshake_kind()
+}
+
+#[monoio::test]
+async fn alpn_and_sni() {
+    let (connector, acceptor) = (connector(), acceptor());
+    let options = ConnectOptions::new().alpn_protocols(vec![b"http/1.1".to_vec()]);
+    let (client, server) = connect(&connector, &acceptor, "localhost", Some(&options))
+        .await
+        .unwrap();
+    assert_eq!(client.alpn_protocol(), Some(b"http/1.1".to_vec()));
+    assert_eq!(server.get_ref().1.server_name(), Some("localhost"));
+
+    let options = ConnectOptions::new().sni(false);
+    let (client, server) = connect(&connector, &acceptor, "localhost", Some(&options))
+        .await
+        .unwrap();
+    assert_eq!(client.alpn_protocol(), None);
+    assert_eq!(server.get_ref().1.server_name(), None);
+}
+
+#[monoio::test]
+async fn verify_name() {
+    let (connector, acceptor) = (connector(), acceptor());
+    let options = ConnectOptions::new().verify_name(common::localhost());
+    let (_client, server) = connect(&connector, &acceptor, "sni.example", Some(&options))
+        .await
+        .unwrap();
+    assert_eq!(server.get_ref().1.server_name(), Some("sni.example"));
+    assert!(connect(&connector, &acceptor, "sni.example", None)
+        .await
+        .is_err());
+
+    // the verifier of the config is unknown to the connector.
+    let connector = TlsConnector::from(common::client_config());
+    let e = connect(&connector, &acceptor, "sni.example", Some(&options))
+        .await
+        .unwrap_err();
+    assert!(matches!(e, TlsError::UnknownVerifier));
+}
+
+#[monoio::test]
+async fn client_certificate() {
+    let (connector, acceptor) = (connector(), acceptor());
+    let options = ConnectOptions::new().client_cert(client_cert());
+    let (_client, server) = connect(&connector, &acceptor, "localhost", Some(&options))
+        .await
+        .unwrap();
+    assert!(server.get_ref().1.peer_certificates().is_some());
+
+    let (_client, server) = connect(&connector, &acceptor, "localhost", None)
+        .await
+        .unwrap();
+    assert!(server.get_ref().1.peer_certificates().is_none());
+}
+
+#[monoio::test]
+async fn resumption_per_options() {
+    let (connector, acceptor) = (connector(), acceptor());
+    let options = ConnectOptions::new().client_cert(client_cert());
+    let (client, _server) = connect(&connector, &acceptor, "localhost", Some(&options))
+        .await
+        .unwrap();
+    assert_eq!(handshake_kind(&client), Some(HandshakeKind::Full));
+
+    // the session established with the client certificate is not resumed
+    // without it.
+    let (client, server) = connect(&connector, &acceptor, "localhost", None)
+        .await
+        .unwrap();
+    assert_eq!(handshake_kind(&client), Some(HandshakeKind::Full));
+    assert!(server.get_ref().1.peer_certificates().is_none());
+    let other = ConnectOptions::new().alpn_protocols(vec![b"h2".to_vec()]);
+    let (client, _server) = connect(&connector, &acceptor, "localhost", Some(&other))
+        .await
+        .unwrap();
+    assert_eq!(handshake_kind(&client), Some(HandshakeKind::Full));
+
+    // while each of them resumes its own, clones share the derived configs.
+    let (client, server) = connect(&connector.clone(), &acceptor, "localhost", Some(&options))
+        .await
+        .unwrap();
+    assert_eq!(handshake_kind(&client), Some(HandshakeKind::Resumed));
+    assert!(server.get_ref().1.peer_certificates().is_some());
+    let (client, _server) = connect(&connector, &acceptor, "localhost", None)
+        .await
+        .unwrap();
+    assert_eq!(handshake_kind(&client), Some(HandshakeKind::Resumed));
+}
+
+#[monoio::test]
+async fn resumption_disabled() {
+    let acceptor = acceptor();
+    let mut config = common::client_config();
+    config.resumption = Resumption::disabled();
+    let connector = TlsConnector::from(config).server_cert_verifier(
+        WebPkiServerVerifier::builder_with_provider(
+            Arc::new(common::pki().roots()),
+            common::provider(),
+        )
+        .build()
+        .unwrap(),
+    );
+    let options = ConnectOptions::new().alpn_protocols(vec![b"h2".to_vec()]);
+    for _ in 0..2 {
+        let (client, _server) = connect(&connector, &acceptor, "localhost", Some(&options))
+            .await
+            .unwrap();
+        assert_eq!(handshake_kind(&client), Some(HandshakeKind::Full));
+    }
+}
+
+#[cfg(feature = "tls12")]
+#[monoio::test]
+async fn tls12_resumption() {
+    use rustls::{
+        client::Tls12Resumption, crypto::ring::Ticketer, server::NoServerSessionStorage,
+        version::TLS12, ClientConfig,
+    };
+
+    // a server resuming TLS 1.2 sessions with tickets only.
+    let mut config = common::server_config();
+    config.session_storage = Arc::new(NoServerSessionStorage {});
+    config.ticketer = Ticketer::new().unwrap();
+    let acceptor = TlsAcceptor::from(config);
+    let options = ConnectOptions::new().alpn_protocols(vec![b"h2".to_vec()]);
+    for (mode, resumed) in [
+        (Tls12Resumption::SessionIdOrTickets, HandshakeKind::Resumed),
+        (Tls12Resumption::SessionIdOnly, HandshakeKind::Full),
+    ] {
+        let mut config = ClientConfig::builder_with_provider(common::provider())
+            .with_protocol_versions(&[&TLS12])
+            .unwrap()
+            .with_root_certificates(common::pki().roots())
+            .with_no_client_auth();
+        config.resumption = Resumption::in_memory_sessions(8).tls12_resumption(mode);
+        let connector = TlsConnector::from(config);
+        let (client, _server) = connect(&connector, &acceptor, "localhost", Some(&options))
+            .await
+            .unwrap();
+        assert_eq!(handshake_kind(&client), Some(HandshakeKind::Full));
+        let (client, _server) = connect(&connector, &acceptor, "localhost", Some(&options))
+            .await
+            .unwrap();
+        assert_eq!(handshake_kind(&client), Some(resumed), "{mode:?}");
+    }
+}
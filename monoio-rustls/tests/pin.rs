@@ -0,0 +1,250 @@
+mod common;
+
+use std::sync::Arc;
+
+use monoio_rustls::{SpkiPinVerifier, TlsAcceptor, TlsConnector, TlsError};
+use openssl::{sha::sha256, x509::X509};
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+use rustls::{
+    pki_types::{CertificateDer, PrivatePkcs8KeyDer, ServerName},
+    CertificateError, ServerConfig,
+};
+
+/// The pin computed the way `openssl pkey -pubin -outform der | openssl dgst
+/// -sha256` does.
+fn pin_of(cert: &CertificateDer<'_>) -> [u8; 32] {
+    let key = X509::from_der(cert).unwrap().public_key().unwrap();
+    sha256(&key.public_key_to_der().unwrap())
+}
+
+fn connector(verifier: SpkiPinVerifier) -> TlsConnector {
+    TlsConnector::from(common::client_config()).server_cert_verifier(Arc::new(verifier))
+}
+
+/// Connect with the server name and return the certificate error, if any.
+async fn connect(
+    connector: &TlsConnector,
+    acceptor: &TlsAcceptor,
+    name: &str,
+) -> Result<(), Option<CertificateError>> {
+    let (client, server) = common::tcp_pair().await;
+    let name = ServerName::try_from(name.to_string()).unwrap();
+    let (client, _server) = monoio::join!(connector.connect(name, client), acceptor.accept(server));
+    match client {
+        Ok(_) => Ok(()),
+        Err(e) => Err(certificate_error(e)),
+    }
+}
+
+fn certificate_error(e: TlsError) -> Option<CertificateError> {
+    let e = match e {
+        TlsError::Rustls(e) => e,
+        TlsError::Io(e) => e
+            .into_inner()?
+            .downcast::<rustls::Error>()
+            .ok()
+            .map(|e| *e)?,
+        TlsError::Denied(_) | TlsError::UnknownVerifier => return None,
+    };
+    match e {
+        rustls::Error::InvalidCertificate(e) => Some(e),
+        _ => None,
+    }
+}
+
+/// A server with the chain leaf <- intermediate <- the test CA.
+fn intermediate_server() -> (TlsAcceptor, CertificateDer<'static>) {
+    let pki = common::pki();
+    let inter_key = KeyPair::generate().unwrap();
+    let mut params = CertificateParams::new(Vec::new()).unwrap();
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "monoio-tls intermediate");
+    let inter = params.signed_by(&inter_key, &pki.ca, &pki.ca_key).unwrap();
+    let key = KeyPair::generate().unwrap();
+    let leaf = CertificateParams::new(vec!["localhost".to_string()])
+        .unwrap()
+        .signed_by(&key, &inter, &inter_key)
+        .unwrap();
+    let config = ServerConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![leaf.der().clone(), inter.der().clone()],
+            PrivatePkcs8KeyDer::from(key.serialize_der()).into(),
+        )
+        .unwrap();
+    (config.into(), inter.der().clone())
+}
+
+#[test]
+fn spki_pin() {
+    let pki = common::pki();
+    let verifier = SpkiPinVerifier::new([], common::provider()).unwrap();
+    assert_eq!(
+        verifier.spki_pin(pki.cert.der()),
+        Some(pin_of(pki.cert.der()))
+    );
+    assert_ne!(
+        verifier.spki_pin(pki.cert.der()),
+        verifier.spki_pin(&pki.ca_der())
+    );
+    assert_eq!(
+        verifier.spki_pin(&CertificateDer::from(vec![0x30, 0x00])),
+        None
+    );
+}
+
+#[monoio::test]
+async fn pin_only() {
+    let pki = common::pki();
+    let acceptor = TlsAcceptor::from(common::server_config());
+    let pinned =
+        || SpkiPinVerifier::new([[0; 32], pin_of(pki.cert.der())], common::provider()).unwrap();
+
+    assert_eq!(
+        connect(&connector(pinned()), &acceptor, "localhost").await,
+        Ok(())
+    );
+    // neither the name nor the issuer is checked, the pin identifies the server.
+    assert_eq!(
+        connect(&connector(pinned()), &acceptor, "other.test").await,
+        Ok(())
+    );
+
+    // the CA key is not pinned, only the leaf one is matched.
+    let ca_pinned = SpkiPinVerifier::new([pin_of(&pki.ca_der())], common::provider()).unwrap();
+    assert_eq!(
+        connect(&connector(ca_pinned), &acceptor, "localhost").await,
+        Err(Some(CertificateError::ApplicationVerificationFailure))
+    );
+    let unpinned = SpkiPinVerifier::new([], common::provider()).unwrap();
+    assert_eq!(
+        connect(&connector(unpinned), &acceptor, "localhost").await,
+        Err(Some(CertificateError::ApplicationVerificationFailure))
+    );
+}
+
+#[monoio::test]
+async fn pin_with_webpki() {
+    let pki = common::pki();
+    let acceptor = TlsAcceptor::from(common::server_config());
+    let leaf_pin = pin_of(pki.cert.der());
+    let webpki = |pins: Vec<[u8; 32]>, roots| {
+        SpkiPinVerifier::new(pins, common::provider())
+            .unwrap()
+            .webpki(roots)
+            .unwrap()
+    };
+
+    assert_eq!(
+        connect(
+            &connector(webpki(vec![leaf_pin], pki.roots())),
+            &acceptor,
+            "localhost"
+        )
+        .await,
+        Ok(())
+    );
+    // the name is checked now.
+    assert!(matches!(
+        connect(
+            &connector(webpki(vec![leaf_pin], pki.roots())),
+            &acceptor,
+            "other.test"
+        )
+        .await,
+        Err(Some(
+            CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. }
+        ))
+    ));
+    // a pinned key does not make an untrusted chain valid, the other CA has
+    // the same name so the issuer signature fails.
+    let other = common::generate(CertificateParams::new(vec!["localhost".into()]).unwrap());
+    assert!(matches!(
+        connect(
+            &connector(webpki(vec![leaf_pin], other.roots())),
+            &acceptor,
+            "localhost"
+        )
+        .await,
+        Err(Some(
+            CertificateError::BadSignature | CertificateError::UnknownIssuer
+        ))
+    ));
+    // a valid chain is not enough without a pin.
+    assert_eq!(
+        connect(
+            &connector(webpki(vec![], pki.roots())),
+            &acceptor,
+            "localhost"
+        )
+        .await,
+        Err(Some(CertificateError::ApplicationVerificationFailure))
+    );
+}
+
+#[monoio::test]
+async fn pin_intermediate() {
+    let (acceptor, inter) = intermediate_server();
+    let pins = vec![pin_of(&inter)];
+
+    let verifier = SpkiPinVerifier::new(pins.clone(), common::provider())
+        .unwrap()
+        .webpki(common::pki().roots())
+        .unwrap();
+    assert_eq!(
+        connect(&connector(verifier), &acceptor, "localhost").await,
+        Ok(())
+    );
+
+    // without a chain verifier only the leaf is matched.
+    let verifier = SpkiPinVerifier::new(pins, common::provider()).unwrap();
+    assert_eq!(
+        connect(&connector(verifier), &acceptor, "localhost").await,
+        Err(Some(CertificateError::ApplicationVerificationFailure))
+    );
+}
+
+#[monoio::test]
+async fn pin_root() {
+    let pki = common::pki();
+    let acceptor = TlsAcceptor::from(common::server_config());
+    // the root is not sent by the server, it is part of the validated path.
+    let verifier = SpkiPinVerifier::new([pin_of(&pki.ca_der())], common::provider())
+        .unwrap()
+        .webpki(pki.roots())
+        .unwrap();
+    assert_eq!(
+        connect(&connector(verifier), &acceptor, "localhost").await,
+        Ok(())
+    );
+}
+
+#[monoio::test]
+async fn pin_outside_path() {
+    let pki = common::pki();
+    // a trusted chain carrying the pinned CA of another PKI as an extra
+    // intermediate.
+    let pinned = common::generate(CertificateParams::new(vec!["localhost".into()]).unwrap());
+    let config = ServerConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![pki.cert.der().clone(), pinned.ca_der()], pki.key_der())
+        .unwrap();
+    let acceptor = TlsAcceptor::from(config);
+
+    let mut roots = pki.roots();
+    roots.add(pinned.ca_der()).unwrap();
+    let verifier = SpkiPinVerifier::new([pin_of(&pinned.ca_der())], common::provider())
+        .unwrap()
+        .webpki(roots)
+        .unwrap();
+    assert_eq!(
+        connect(&connector(verifier), &acceptor, "localhost").await,
+        Err(Some(CertificateError::ApplicationVerificationFailure))
+    );
+}
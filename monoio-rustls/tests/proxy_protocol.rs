@@ -0,0 +1,96 @@
+mod common;
+
+use std::io;
+
+use monoio::io::{AsyncReadRent, AsyncWriteRentExt};
+use monoio_rustls::{ProxiedIo, ProxyHeaderError, ProxyProtocolAcceptor, TlsConnector, TlsError};
+
+fn header_error(e: &io::Error) -> Option<ProxyHeaderError> {
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    e.get_ref()?.downcast_ref().copied()
+}
+
+#[monoio::test]
+async fn accept_after_header() {
+    let acceptor = ProxyProtocolAcceptor::new(common::server_config().into());
+    let connector = TlsConnector::from(common::client_config());
+    let (mut client, server) = common::tcp_pair().await;
+    let header = b"PROXY TCP6 2001:db8::1 2001:db8::2 40000 443\r\n";
+    let (res, _) = client.write_all(header.to_vec()).await;
+    res.unwrap();
+
+    let (client, server) = monoio::join!(
+        connector.connect(common::localhost(), client),
+        acceptor.accept(server)
+    );
+    let (mut client, server) = (client.unwrap(), server.unwrap());
+    assert_eq!(server.proxy_header().version(), 1);
+    assert_eq!(
+        server.source_addr(),
+        Some("[2001:db8::1]:40000".parse().unwrap())
+    );
+    assert_eq!(
+        server.proxy_header().destination(),
+        Some("[2001:db8::2]:443".parse().unwrap())
+    );
+    let (res, _) = client.write_all(b"ping").await;
+    res.unwrap();
+}
+
+#[monoio::test]
+async fn bytes_after_header() {
+    let (mut client, server) = common::tcp_pair().await;
+    // the header and the first bytes of the stream in one segment.
+    let mut v2 = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0c".to_vec();
+    v2.extend_from_slice(&[192, 0, 2, 1, 198, 51, 100, 1, 0xdc, 0x04, 0x01, 0xbb]);
+    v2.extend_from_slice(b"hello");
+    let (res, _) = client.write_all(v2).await;
+    res.unwrap();
+
+    let mut io = ProxiedIo::read_header(server).await.unwrap();
+    assert_eq!(io.header().version(), 2);
+    assert_eq!(
+        io.header().source(),
+        Some("192.0.2.1:56324".parse().unwrap())
+    );
+    let (res, buf) = io.read(Vec::with_capacity(16)).await;
+    assert_eq!(res.unwrap(), 5);
+    assert_eq!(buf, b"hello");
+}
+
+#[monoio::test]
+async fn rejected() {
+    let acceptor = ProxyProtocolAcceptor::new(common::server_config().into());
+    let connector = TlsConnector::from(common::client_config());
+
+    // a client speaking TLS directly.
+    let (client, server) = common::tcp_pair().await;
+    let (_client, server) = monoio::join!(
+        connector.connect(common::localhost(), client),
+        acceptor.accept(server)
+    );
+    let Err(TlsError::Io(e)) = server else {
+        panic!("accepted without header")
+    };
+    assert_eq!(header_error(&e), Some(ProxyHeaderError::Missing));
+
+    // a relayed UDP datagram.
+    let (mut client, server) = common::tcp_pair().await;
+    let mut v2 = b"\r\n\r\n\0\r\nQUIT\n\x21\x12\x00\x0c".to_vec();
+    v2.extend_from_slice(&[0; 12]);
+    let (res, _) = client.write_all(v2).await;
+    res.unwrap();
+    let e = ProxiedIo::read_header(server).await.unwrap_err();
+    assert_eq!(
+        header_error(&e),
+        Some(ProxyHeaderError::UnsupportedTransport(2))
+    );
+
+    // the connection closed in the middle of the header.
+    let (mut client, server) = common::tcp_pair().await;
+    let (res, _) = client.write_all(b"PROXY TCP4 192.0.2.1".to_vec()).await;
+    res.unwrap();
+    drop(client);
+    let e = ProxiedIo::read_header(server).await.unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,151 @@
+#![cfg(feature = "pem")]
+
+mod common;
+
+use std::{
+    fs::File,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use monoio_rustls::{PemError, ReloadableCertResolver, TlsAcceptor, TlsConnector};
+use rcgen::CertificateParams;
+use rustls::{pki_types::CertificateDer, ClientConfig, ServerConfig};
+
+fn write_pair(dir: &Path, pki: &common::Pki) {
+    std::fs::write(dir.join("cert.pem"), pki.cert.pem()).unwrap();
+    std::fs::write(dir.join("key.pem"), pki.key.serialize_pem()).unwrap();
+}
+
+fn resolver(dir: &Path) -> Result<ReloadableCertResolver, PemError> {
+    ReloadableCertResolver::new(
+        dir.join("cert.pem"),
+        dir.join("key.pem"),
+        common::provider(),
+    )
+}
+
+fn acceptor(resolver: Arc<ReloadableCertResolver>) -> TlsAcceptor {
+    ServerConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_cert_resolver(resolver)
+        .into()
+}
+
+fn connector(pki: &common::Pki) -> TlsConnector {
+    ClientConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(pki.roots())
+        .with_no_client_auth()
+        .into()
+}
+
+fn localhost() -> common::Pki {
+    common::generate(CertificateParams::new(vec!["localhost".to_string()]).unwrap())
+}
+
+async fn served_cert(pki: &common::Pki, acceptor: &TlsAcceptor) -> CertificateDer<'static> {
+    let (client, _server) = common::tls_pair(&connector(pki), acceptor).await;
+    client.get_ref().1.peer_certificates().unwrap()[0].clone()
+}
+
+#[monoio::test]
+async fn reload_swaps_pair() {
+    let dir = common::temp_dir("reload-swaps-pair");
+    let (old, new) = (localhost(), localhost());
+    write_pair(&dir, &old);
+    let resolver = Arc::new(resolver(&dir).unwrap());
+    let acceptor = acceptor(resolver.clone());
+    assert_eq!(&served_cert(&old, &acceptor).await, old.cert.der());
+
+    write_pair(&dir, &new);
+    resolver.reload().unwrap();
+    assert_eq!(resolver.current().cert[0], *new.cert.der());
+    assert_eq!(&served_cert(&new, &acceptor).await, new.cert.der());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn invalid_pair_keeps_current() {
+    let dir = common::temp_dir("invalid-pair");
+    let (old, new) = (localhost(), localhost());
+    write_pair(&dir, &old);
+    let resolver = resolver(&dir).unwrap();
+
+    // only the certificate has been replaced.
+    std::fs::write(dir.join("cert.pem"), new.cert.pem()).unwrap();
+    assert!(matches!(resolver.reload(), Err(PemError::KeyMismatch)));
+    assert_eq!(resolver.current().cert[0], *old.cert.der());
+
+    std::fs::remove_file(dir.join("key.pem")).unwrap();
+    assert!(matches!(resolver.reload(), Err(PemError::Pem { .. })));
+    std::fs::write(dir.join("cert.pem"), "").unwrap();
+    assert!(matches!(resolver.reload(), Err(PemError::NoCertificate(_))));
+    assert_eq!(resolver.current().cert[0], *old.cert.der());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn new_fails_on_invalid_pair() {
+    let dir = common::temp_dir("new-invalid-pair");
+    assert!(matches!(resolver(&dir), Err(PemError::Pem { .. })));
+    let (one, two) = (localhost(), localhost());
+    std::fs::write(dir.join("cert.pem"), one.cert.pem()).unwrap();
+    std::fs::write(dir.join("key.pem"), two.key.serialize_pem()).unwrap();
+    assert!(matches!(resolver(&dir), Err(PemError::KeyMismatch)));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[allow(deprecated)]
+#[test]
+fn cert_reload_error_alias() {
+    let e: monoio_rustls::CertReloadError = PemError::KeyMismatch;
+    assert!(matches!(e, PemError::KeyMismatch));
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn watch_retries_failed_reload() {
+    let dir = common::temp_dir("watch-retries");
+    let (old, new) = (localhost(), localhost());
+    write_pair(&dir, &old);
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors_cb = errors.clone();
+    let resolver = Arc::new(
+        resolver(&dir)
+            .unwrap()
+            .on_error(move |e| errors_cb.lock().unwrap().push(e.to_string())),
+    );
+    monoio::spawn(resolver.clone().watch(Duration::from_millis(10)));
+    // let the watcher take its first look at the files.
+    monoio::time::sleep(Duration::from_millis(30)).await;
+
+    // the certificate is written first, the reload fails and is reported once.
+    std::fs::write(dir.join("cert.pem"), new.cert.pem()).unwrap();
+    let key_modified = File::open(dir.join("key.pem"))
+        .unwrap()
+        .metadata()
+        .unwrap()
+        .modified()
+        .unwrap();
+    monoio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(resolver.current().cert[0], *old.cert.der());
+    assert_eq!(errors.lock().unwrap().len(), 1);
+
+    // the key is replaced keeping its modification time, so only a retry
+    // can pick it up.
+    std::fs::write(dir.join("key.pem"), new.key.serialize_pem()).unwrap();
+    File::options()
+        .write(true)
+        .open(dir.join("key.pem"))
+        .unwrap()
+        .set_modified(key_modified)
+        .unwrap();
+    monoio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(resolver.current().cert[0], *new.cert.der());
+    assert_eq!(errors.lock().unwrap().len(), 1);
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,74 @@
+#![cfg(any(
+    feature = "webpki-roots",
+    feature = "native-roots",
+    feature = "platform-verifier"
+))]
+
+mod common;
+
+use monoio_rustls::{RootsError, TlsAcceptor, TlsConnector};
+
+async fn connect(connector: &TlsConnector, acceptor: &TlsAcceptor) -> bool {
+    let (client, server) = common::tcp_pair().await;
+    let (client, _server) = monoio::join!(
+        connector.connect(common::localhost(), client),
+        acceptor.accept(server)
+    );
+    client.is_ok()
+}
+
+// The crypto provider and the environment are process wide, so the cases run
+// in order in one test.
+#[monoio::test]
+async fn well_known_roots() {
+    #[cfg(feature = "webpki-roots")]
+    assert!(matches!(
+        TlsConnector::with_webpki_roots(),
+        Err(RootsError::NoCryptoProvider)
+    ));
+    #[cfg(feature = "native-roots")]
+    assert!(matches!(
+        TlsConnector::with_native_roots(),
+        Err(RootsError::NoCryptoProvider)
+    ));
+    #[cfg(feature = "platform-verifier")]
+    assert!(matches!(
+        TlsConnector::with_platform_verifier(),
+        Err(RootsError::NoCryptoProvider)
+    ));
+
+    common::provider();
+    let acceptor = TlsAcceptor::from(common::server_config());
+    let dir = common::temp_dir("roots");
+    let ca = dir.join("ca.pem");
+    std::fs::write(&ca, common::pki().ca.pem()).unwrap();
+    let empty = dir.join("empty");
+    std::fs::create_dir(&empty).unwrap();
+
+    // the test CA is not a well-known root.
+    #[cfg(feature = "webpki-roots")]
+    assert!(!connect(&TlsConnector::with_webpki_roots().unwrap(), &acceptor).await);
+
+    // the system store is replaced by the test CA.
+    std::env::set_var("SSL_CERT_FILE", &ca);
+    std::env::set_var("SSL_CERT_DIR", &empty);
+    #[cfg(feature = "native-roots")]
+    assert!(connect(&TlsConnector::with_native_roots().unwrap(), &acceptor).await);
+    #[cfg(feature = "platform-verifier")]
+    assert!(connect(&TlsConnector::with_platform_verifier().unwrap(), &acceptor).await);
+
+    #[cfg(feature = "native-roots")]
+    {
+        let no_certs = dir.join("no-certs.pem");
+        std::fs::write(&no_certs, "").unwrap();
+        std::env::set_var("SSL_CERT_FILE", &no_certs);
+        assert!(matches!(
+            TlsConnector::with_native_roots(),
+            Err(RootsError::NoNativeRoots(_))
+        ));
+    }
+
+    std::env::remove_var("SSL_CERT_FILE");
+    std::env::remove_var("SSL_CERT_DIR");
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,232 @@
+mod common;
+
+use std::sync::Arc;
+
+use monoio_rustls::{SniCertStore, TlsAcceptor, TlsConnector};
+use rcgen::{CertificateParams, KeyPair, PKCS_RSA_SHA256};
+use rustls::{
+    crypto::{CryptoProvider, WebPkiSupportedAlgorithms},
+    pki_types::{CertificateDer, ServerName},
+    sign::CertifiedKey,
+    ClientConfig, ServerConfig, SignatureAlgorithm,
+};
+
+/// Issue a certificate for `names` by the test CA, with an ECDSA or RSA key.
+fn issue(names: &[&str], rsa: bool) -> Arc<CertifiedKey> {
+    let pki = common::pki();
+    let key = if rsa {
+        let rsa = openssl::rsa::Rsa::generate(2048).unwrap();
+        let der = openssl::pkey::PKey::from_rsa(rsa)
+            .unwrap()
+            .private_key_to_pkcs8()
+            .unwrap();
+        KeyPair::from_pkcs8_der_and_sign_algo(&der.into(), &PKCS_RSA_SHA256).unwrap()
+    } else {
+        KeyPair::generate().unwrap()
+    };
+    let params =
+        CertificateParams::new(names.iter().map(|n| n.to_string()).collect::<Vec<_>>()).unwrap();
+    let cert = params.signed_by(&key, &pki.ca, &pki.ca_key).unwrap();
+    let key = rustls::pki_types::PrivatePkcs8KeyDer::from(key.serialize_der()).into();
+    Arc::new(CertifiedKey::from_der(vec![cert.der().clone()], key, &common::provider()).unwrap())
+}
+
+fn acceptor(store: Arc<SniCertStore>) -> TlsAcceptor {
+    ServerConfig::builder_with_provider(common::provider())
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_cert_resolver(store)
+        .into()
+}
+
+/// A client offering only the signature schemes `keep` accepts.
+fn client_config(keep: impl Fn(SignatureAlgorithm) -> bool) -> ClientConfig {
+    let mut provider = CryptoProvider::clone(&common::provider());
+    let algorithms = provider.signature_verification_algorithms;
+    let mapping: Vec<_> = algorithms
+        .mapping
+        .iter()
+        .filter(|(_, algs)| {
+            algs.iter()
+                .all(|a| keep(algorithm_of(a.public_key_alg_id())))
+        })
+        .cloned()
+        .collect();
+    provider.signature_verification_algorithms = WebPkiSupportedAlgorithms {
+        all: algorithms.all,
+        mapping: Vec::leak(mapping),
+    };
+    ClientConfig::builder_with_provider(Arc::new(provider))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(common::pki().roots())
+        .with_no_client_auth()
+}
+
+fn algorithm_of(id: rustls::pki_types::AlgorithmIdentifier) -> SignatureAlgorithm {
+    use rustls::pki_types::alg_id;
+    if id == alg_id::RSA_ENCRYPTION {
+        SignatureAlgorithm::RSA
+    } else if id == alg_id::ED25519 {
+        SignatureAlgorithm::ED25519
+    } else {
+        SignatureAlgorithm::ECDSA
+    }
+}
+
+/// Connect with the server name(or no SNI) and return the served leaf.
+async fn served(
+    config: ClientConfig,
+    name: Option<&str>,
+    acceptor: &TlsAcceptor,
+) -> Option<CertificateDer<'static>> {
+    let mut config = config;
+    config.enable_sni = name.is_some();
+    let connector = TlsConnector::from(config);
+    let name = ServerName::try_from(name.unwrap_or("localhost").to_string()).unwrap();
+    let (client, server) = common::tcp_pair().await;
+    let (client, server) = monoio::join!(connector.connect(name, client), acceptor.accept(server));
+    let (client, _server) = (client.ok()?, server.ok()?);
+    let certs = client.get_ref().1.peer_certificates()?;
+    Some(certs[0].clone())
+}
+
+#[test]
+fn lookup() {
+    let store = SniCertStore::new();
+    assert!(store.is_empty());
+    let exact = issue(&["a.example.com"], false);
+    let wildcard = issue(&["*.example.com"], false);
+    store.insert("A.Example.COM.", exact.clone()).unwrap();
+    store.insert("*.example.com", wildcard.clone()).unwrap();
+    assert_eq!(store.len(), 2);
+
+    let found = |name| store.lookup(name).map(|keys| keys[0].cert[0].clone());
+    // exact names win, lookups are case insensitive.
+    assert_eq!(found("a.example.com"), Some(exact.cert[0].clone()));
+    assert_eq!(found("A.EXAMPLE.com."), Some(exact.cert[0].clone()));
+    assert_eq!(found("b.example.com"), Some(wildcard.cert[0].clone()));
+    // a wildcard matches exactly one label.
+    assert_eq!(found("example.com"), None);
+    assert_eq!(found("c.b.example.com"), None);
+
+    assert!(store.remove("*.EXAMPLE.com").is_some());
+    assert_eq!(found("b.example.com"), None);
+    assert!(store.remove("*.example.com").is_none());
+    assert!(store.remove("a.example.com").is_some());
+    assert!(store.is_empty());
+
+    assert!(store.insert("not a name", exact.clone()).is_err());
+    assert!(store.insert("*.*.example.com", exact).is_err());
+    assert!(store.set_default(Some("bad name!")).is_err());
+}
+
+#[test]
+fn insert_replaces_same_algorithm() {
+    let store = SniCertStore::new();
+    let (first, second, rsa) = (
+        issue(&["localhost"], false),
+        issue(&["localhost"], false),
+        issue(&["localhost"], true),
+    );
+    store.insert("localhost", rsa.clone()).unwrap();
+    store.insert("localhost", first).unwrap();
+    store.insert("localhost", second.clone()).unwrap();
+
+    let keys = store.lookup("localhost").unwrap();
+    assert_eq!(keys.len(), 2);
+    // non-RSA keys come first.
+    assert_eq!(keys[0].cert, second.cert);
+    assert_eq!(keys[1].cert, rsa.cert);
+}
+
+#[monoio::test]
+async fn resolve_by_sni() {
+    let store = Arc::new(SniCertStore::new());
+    let (exact, wildcard) = (
+        issue(&["a.example.com"], false),
+        issue(&["*.example.com"], false),
+    );
+    store.insert("a.example.com", exact.clone()).unwrap();
+    store.insert("*.example.com", wildcard.clone()).unwrap();
+    let acceptor = acceptor(store.clone());
+    let config = || client_config(|_| true);
+
+    assert_eq!(
+        served(config(), Some("a.example.com"), &acceptor).await,
+        Some(exact.cert[0].clone())
+    );
+    assert_eq!(
+        served(config(), Some("b.example.com"), &acceptor).await,
+        Some(wildcard.cert[0].clone())
+    );
+    assert_eq!(
+        served(config(), Some("unknown.test"), &acceptor).await,
+        None
+    );
+
+    // names added at runtime are served by the same acceptor.
+    let added = issue(&["unknown.test"], false);
+    store.insert("unknown.test", added.clone()).unwrap();
+    assert_eq!(
+        served(config(), Some("unknown.test"), &acceptor).await,
+        Some(added.cert[0].clone())
+    );
+    store.remove("unknown.test");
+    assert_eq!(
+        served(config(), Some("unknown.test"), &acceptor).await,
+        None
+    );
+}
+
+#[monoio::test]
+async fn default_without_sni() {
+    let store = Arc::new(SniCertStore::new());
+    let localhost = issue(&["localhost"], false);
+    store.insert("localhost", localhost.clone()).unwrap();
+    let acceptor = acceptor(store.clone());
+    let config = || client_config(|_| true);
+
+    assert_eq!(served(config(), None, &acceptor).await, None);
+    store.set_default(Some("localhost")).unwrap();
+    assert_eq!(
+        served(config(), None, &acceptor).await,
+        Some(localhost.cert[0].clone())
+    );
+    store.set_default(None).unwrap();
+    assert_eq!(served(config(), None, &acceptor).await, None);
+}
+
+#[monoio::test]
+async fn choose_by_signature_schemes() {
+    let store = Arc::new(SniCertStore::new());
+    let (ecdsa, rsa) = (issue(&["localhost"], false), issue(&["localhost"], true));
+    store.insert("localhost", ecdsa.clone()).unwrap();
+    store.insert("localhost", rsa.clone()).unwrap();
+    let acceptor = acceptor(store.clone());
+
+    let any = || client_config(|_| true);
+    let rsa_only = || client_config(|alg| alg == SignatureAlgorithm::RSA);
+    let ecdsa_only = || client_config(|alg| alg == SignatureAlgorithm::ECDSA);
+    assert_eq!(
+        served(any(), Some("localhost"), &acceptor).await,
+        Some(ecdsa.cert[0].clone())
+    );
+    assert_eq!(
+        served(rsa_only(), Some("localhost"), &acceptor).await,
+        Some(rsa.cert[0].clone())
+    );
+    assert_eq!(
+        served(ecdsa_only(), Some("localhost"), &acceptor).await,
+        Some(ecdsa.cert[0].clone())
+    );
+
+    // no key usable with the client's schemes.
+    store.remove("localhost");
+    store.insert("localhost", rsa.clone()).unwrap();
+    assert_eq!(
+        served(ecdsa_only(), Some("localhost"), &acceptor).await,
+        None
+    );
+}
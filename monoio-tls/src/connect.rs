@@ -0,0 +1,51 @@
+use std::{future::Future, net::SocketAddr};
+
+use monoio::net::TcpStream;
+use monoio_io_wrapper::{connect_addrs, connect_host};
+
+use crate::{TlsConnect, TlsError};
+
+/// Connecting by host name, for every [`TlsConnect`] backend.
+pub trait TlsConnectExt: TlsConnect<TcpStream> {
+    /// Resolve `host:port`, connect to it with Happy Eyeballs(RFC 8305) and
+    /// then do the handshake with the host as server name. The host is a DNS
+    /// name or an IP address, in brackets for IPv6.
+    ///
+    /// The name is resolved with the blocking resolver of std. With the
+    /// `sync` feature it runs on the thread pool of `monoio::spawn_blocking`,
+    /// which the runtime must have attached, otherwise every task of the
+    /// runtime is blocked until it returns. Use
+    /// [`connect_addrs`](Self::connect_addrs) with the addresses of an async
+    /// resolver to avoid both, it also tells how the addresses are tried.
+    ///
+    /// The connectors of monoio-rustls and monoio-native-tls have the same
+    /// method, returning their own error, which takes precedence when they
+    /// are used directly.
+    fn connect_host(&self, addr: &str) -> impl Future<Output = Result<Self::Stream, TlsError>> {
+        async move {
+            let (stream, host) = connect_host(addr).await?;
+            self.connect(host, stream).await
+        }
+    }
+
+    /// Connect to one of the addresses with Happy Eyeballs and then do the
+    /// handshake, verifying the peer against `server_name`.
+    ///
+    /// Addresses are tried in order, alternating between IPv6 and IPv4; a
+    /// new attempt is started every 250ms or as soon as the previous one
+    /// fails, and the first established connection wins. The runtime must
+    /// have timer enabled.
+    fn connect_addrs(
+        &self,
+        addrs: impl IntoIterator<Item = SocketAddr>,
+        server_name: &str,
+    ) -> impl Future<Output = Result<Self::Stream, TlsError>> {
+        let addrs: Vec<_> = addrs.into_iter().collect();
+        async move {
+            let stream = connect_addrs(addrs).await?;
+            self.connect(server_name, stream).await
+        }
+    }
+}
+
+impl<C: TlsConnect<TcpStream>> TlsConnectExt for C {}
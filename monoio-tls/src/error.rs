@@ -0,0 +1,53 @@
+use std::io;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TlsError {
+    #[error("io error")]
+    Io(#[from] std::io::Error),
+    #[error("invalid server name: {0}")]
+    InvalidServerName(String),
+    #[cfg(feature = "rustls")]
+    #[error("rustls error")]
+    Rustls(#[from] rustls::Error),
+    #[cfg(feature = "native-tls")]
+    #[error("native-tls error")]
+    NativeTls(#[from] native_tls::Error),
+}
+
+#[cfg(feature = "rustls")]
+impl From<monoio_rustls::TlsError> for TlsError {
+    fn from(e: monoio_rustls::TlsError) -> Self {
+        match e {
+            monoio_rustls::TlsError::Io(e) => TlsError::Io(e),
+            monoio_rustls::TlsError::Rustls(e) => TlsError::Rustls(e),
+            e @ (monoio_rustls::TlsError::Denied(_) | monoio_rustls::TlsError::UnknownVerifier) => {
+                TlsError::Io(e.into())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "native-tls")]
+impl From<monoio_native_tls::TlsError> for TlsError {
+    fn from(e: monoio_native_tls::TlsError) -> Self {
+        match e {
+            monoio_native_tls::TlsError::Io(e) => TlsError::Io(e),
+            monoio_native_tls::TlsError::NativeTls(e) => TlsError::NativeTls(e),
+        }
+    }
+}
+
+impl From<TlsError> for io::Error {
+    fn from(e: TlsError) -> Self {
+        match e {
+            TlsError::Io(e) => e,
+            TlsError::InvalidServerName(_) => io::Error::new(io::ErrorKind::InvalidInput, e),
+            #[cfg(feature = "rustls")]
+            TlsError::Rustls(e) => io::Error::other(e),
+            #[cfg(feature = "native-tls")]
+            TlsError::NativeTls(e) => io::Error::other(e),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+/// Negotiated protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TlsVersion {
+    Tls10,
+    Tls11,
+    Tls12,
+    Tls13,
+    /// A version unknown to this crate, with its wire value.
+    Unknown(u16),
+}
+
+impl From<u16> for TlsVersion {
+    fn from(v: u16) -> Self {
+        match v {
+            0x0301 => TlsVersion::Tls10,
+            0x0302 => TlsVersion::Tls11,
+            0x0303 => TlsVersion::Tls12,
+            0x0304 => TlsVersion::Tls13,
+            v => TlsVersion::Unknown(v),
+        }
+    }
+}
+
+/// Connection information shared by all backends.
+/// Fields the backend cannot provide are left empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsInfo {
+    /// Negotiated protocol version.
+    pub version: Option<TlsVersion>,
+    /// Negotiated cipher suite name, e.g. `TLS13_AES_128_GCM_SHA256`.
+    pub cipher: Option<String>,
+    /// Negotiated ALPN protocol.
+    pub alpn: Option<Vec<u8>>,
+    /// DER encoded peer certificates, the end-entity certificate comes first.
+    pub peer_certificates: Vec<Vec<u8>>,
+}
+
+/// Streams that can report their TLS connection information.
+pub trait HasTlsInfo {
+    fn tls_info(&self) -> TlsInfo;
+}
@@ -0,0 +1,48 @@
+//! Backend-agnostic TLS for Monoio.
+//!
+//! Libraries can be written against `TlsConnect` and `TlsAccept`, and the
+//! backend(rustls or native-tls) is chosen by cargo features of this crate.
+
+mod connect;
+mod error;
+mod info;
+#[cfg(feature = "native-tls")]
+mod native;
+mod proxy;
+#[cfg(feature = "rustls")]
+mod rustls;
+
+use std::future::Future;
+
+pub use connect::TlsConnectExt;
+pub use error::TlsError;
+pub use info::{HasTlsInfo, TlsInfo, TlsVersion};
+use monoio::io::{AsyncReadRent, AsyncWriteRent};
+#[cfg(feature = "native-tls")]
+pub use monoio_native_tls;
+#[cfg(feature = "rustls")]
+pub use monoio_rustls;
+pub use proxy::{Proxy, ProxyConnector, ProxyError, Socks5Reply};
+
+/// Client side of a TLS backend.
+pub trait TlsConnect<IO: AsyncReadRent + AsyncWriteRent> {
+    /// The stream returned after a successful handshake.
+    type Stream: AsyncReadRent + AsyncWriteRent + HasTlsInfo;
+
+    /// Do the client handshake over the given io, verifying the peer against
+    /// `server_name`(a DNS name or an IP address).
+    fn connect(
+        &self,
+        server_name: &str,
+        io: IO,
+    ) -> impl Future<Output = Result<Self::Stream, TlsError>>;
+}
+
+/// Server side of a TLS backend.
+pub trait TlsAccept<IO: AsyncReadRent + AsyncWriteRent> {
+    /// The stream returned after a successful handshake.
+    type Stream: AsyncReadRent + AsyncWriteRent + HasTlsInfo;
+
+    /// Do the server handshake over the given io.
+    fn accept(&self, io: IO) -> impl Future<Output = Result<Self::Stream, TlsError>>;
+}
@@ -0,0 +1,47 @@
+use monoio::io::{AsyncReadRent, AsyncWriteRent};
+use monoio_native_tls::{ReloadableTlsAcceptor, TlsAcceptor, TlsConnector, TlsStream};
+
+use crate::{HasTlsInfo, TlsAccept, TlsConnect, TlsError, TlsInfo};
+
+impl<IO: AsyncReadRent + AsyncWriteRent> TlsConnect<IO> for TlsConnector {
+    type Stream = TlsStream<IO>;
+
+    async fn connect(&self, server_name: &str, io: IO) -> Result<Self::Stream, TlsError> {
+        Ok(TlsConnector::connect(self, server_name, io).await?)
+    }
+}
+
+impl<IO: AsyncReadRent + AsyncWriteRent> TlsAccept<IO> for TlsAcceptor {
+    type Stream = TlsStream<IO>;
+
+    async fn accept(&self, io: IO) -> Result<Self::Stream, TlsError> {
+        Ok(TlsAcceptor::accept(self, io).await?)
+    }
+}
+
+impl<IO: AsyncReadRent + AsyncWriteRent> TlsAccept<IO> for ReloadableTlsAcceptor {
+    type Stream = TlsStream<IO>;
+
+    async fn accept(&self, io: IO) -> Result<Self::Stream, TlsError> {
+        Ok(ReloadableTlsAcceptor::accept(self, io).await?)
+    }
+}
+
+impl<IO> HasTlsInfo for TlsStream<IO> {
+    // native-tls does not expose the negotiated version and cipher, and only
+    // the end-entity certificate of the peer.
+    fn tls_info(&self) -> TlsInfo {
+        let peer_certificates = self
+            .peer_certificate()
+            .ok()
+            .flatten()
+            .and_then(|cert| cert.to_der().ok())
+            .into_iter()
+            .collect();
+        TlsInfo {
+            alpn: self.alpn_protocol(),
+            peer_certificates,
+            ..Default::default()
+        }
+    }
+}
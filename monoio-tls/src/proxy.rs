@@ -0,0 +1,465 @@
+use std::{fmt, io, net::IpAddr};
+
+use monoio::{
+    io::{AsyncReadRent, AsyncReadRentExt, AsyncWriteRent, AsyncWriteRentExt},
+    net::TcpStream,
+};
+use monoio_io_wrapper::{connect_host, split_host_port};
+use thiserror::Error;
+
+use crate::{TlsConnect, TlsError};
+
+/// Maximum size of the response header of an HTTP proxy.
+const MAX_HEADER: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    Http,
+    Socks5,
+}
+
+/// A proxy tunneling connections to the target host, with HTTP CONNECT or
+/// SOCKS5. The target name is resolved by the proxy.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Proxy {
+    protocol: Protocol,
+    addr: String,
+    auth: Option<(String, String)>,
+}
+
+impl Proxy {
+    /// An HTTP proxy at `host:port`, tunneling with the CONNECT method.
+    pub fn http(addr: impl Into<String>) -> Self {
+        Self {
+            protocol: Protocol::Http,
+            addr: addr.into(),
+            auth: None,
+        }
+    }
+
+    /// A SOCKS5 proxy at `host:port`.
+    pub fn socks5(addr: impl Into<String>) -> Self {
+        Self {
+            protocol: Protocol::Socks5,
+            addr: addr.into(),
+            auth: None,
+        }
+    }
+
+    /// Authenticate to the proxy, with basic authentication for HTTP and
+    /// username/password authentication(RFC 1929) for SOCKS5.
+    pub fn auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.auth = Some((username.into(), password.into()));
+        self
+    }
+
+    /// The address of the proxy.
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    /// Open a tunnel to `target`(`host:port`, with IPv6 addresses in
+    /// brackets) over a connection to the proxy, which is returned once the
+    /// tunnel is established.
+    ///
+    /// An IPv6 zone(`[fe80::1%eth0]:443`) is sent escaped to HTTP proxies,
+    /// SOCKS5 has no way to carry it.
+    pub async fn tunnel<IO>(&self, mut io: IO, target: &str) -> Result<IO, ProxyError>
+    where
+        IO: AsyncReadRent + AsyncWriteRent,
+    {
+        let (host, port) = split_host_port(target)?;
+        match self.protocol {
+            Protocol::Http => self.http_connect(&mut io, host, port).await?,
+            Protocol::Socks5 => self.socks5_connect(&mut io, host, port).await?,
+        }
+        Ok(io)
+    }
+
+    async fn http_connect<IO>(&self, io: &mut IO, host: &str, port: u16) -> Result<(), ProxyError>
+    where
+        IO: AsyncReadRent + AsyncWriteRent,
+    {
+        // An IPv6 address, maybe with a zone whose `%` is escaped as `%25`
+        // in URIs(RFC 6874).
+        let authority = if host.contains(':') {
+            format!("[{}]:{port}", host.replace('%', "%25"))
+        } else {
+            format!("{host}:{port}")
+        };
+        let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+        if let Some((username, password)) = &self.auth {
+            let credentials = base64(format!("{username}:{password}").as_bytes());
+            request.push_str(&format!("Proxy-Authorization: Basic {credentials}\r\n"));
+        }
+        request.push_str("\r\n");
+        write_all(io, request.into_bytes()).await?;
+
+        // The status is checked as soon as the status line is read, the rest
+        // of an error response is not waited for.
+        let mut header = Vec::new();
+        let mut status = None;
+        let end = loop {
+            if status.is_none() {
+                if let Some(pos) = header.windows(2).position(|w| w == b"\r\n") {
+                    let code = status_code(&header[..pos]).ok_or(ProxyError::InvalidResponse)?;
+                    match code {
+                        200..=299 => status = Some(code),
+                        407 => return Err(ProxyError::AuthFailed),
+                        code => return Err(ProxyError::HttpStatus(code)),
+                    }
+                }
+            }
+            if let Some(pos) = header.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+            if header.len() >= MAX_HEADER {
+                return Err(ProxyError::InvalidResponse);
+            }
+            let (res, buf) = io.read(Vec::with_capacity(1024)).await;
+            if res? == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            header.extend_from_slice(&buf);
+        };
+
+        // The tunnel carries TLS, where the client speaks first, so nothing
+        // may follow the header of a successful response.
+        if end < header.len() {
+            return Err(ProxyError::InvalidResponse);
+        }
+        Ok(())
+    }
+
+    async fn socks5_connect<IO>(&self, io: &mut IO, host: &str, port: u16) -> Result<(), ProxyError>
+    where
+        IO: AsyncReadRent + AsyncWriteRent,
+    {
+        // the target is checked before talking to the proxy.
+        let mut connect = vec![5, 1, 0];
+        match host.parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => {
+                connect.push(1);
+                connect.extend_from_slice(&ip.octets());
+            }
+            Ok(IpAddr::V6(ip)) => {
+                connect.push(4);
+                connect.extend_from_slice(&ip.octets());
+            }
+            // SOCKS5 has no address type for an IPv6 zone.
+            Err(_) if host.contains(':') => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "socks5 cannot carry an IPv6 zone",
+                )
+                .into());
+            }
+            Err(_) => {
+                let len = u8::try_from(host.len()).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "host name too long")
+                })?;
+                connect.push(3);
+                connect.push(len);
+                connect.extend_from_slice(host.as_bytes());
+            }
+        }
+        connect.extend_from_slice(&port.to_be_bytes());
+
+        let greeting = match self.auth {
+            Some(_) => vec![5, 2, 0, 2],
+            None => vec![5, 1, 0],
+        };
+        write_all(io, greeting).await?;
+        let reply = read_exact(io, 2).await?;
+        if reply[0] != 5 {
+            return Err(ProxyError::InvalidResponse);
+        }
+        match (reply[1], &self.auth) {
+            (0, _) => {}
+            (2, Some((username, password))) => {
+                let too_long =
+                    || io::Error::new(io::ErrorKind::InvalidInput, "socks5 credentials too long");
+                let mut request = vec![1];
+                for field in [username, password] {
+                    request.push(u8::try_from(field.len()).map_err(|_| too_long())?);
+                    request.extend_from_slice(field.as_bytes());
+                }
+                write_all(io, request).await?;
+                let reply = read_exact(io, 2).await?;
+                if reply[0] != 1 {
+                    return Err(ProxyError::InvalidResponse);
+                }
+                if reply[1] != 0 {
+                    return Err(ProxyError::AuthFailed);
+                }
+            }
+            (0xff, _) => return Err(ProxyError::NoAcceptableAuth),
+            _ => return Err(ProxyError::InvalidResponse),
+        }
+
+        write_all(io, connect).await?;
+
+        // VER REP RSV ATYP BND.ADDR BND.PORT
+        let reply = read_exact(io, 4).await?;
+        if reply[0] != 5 {
+            return Err(ProxyError::InvalidResponse);
+        }
+        if reply[1] != 0 {
+            return Err(ProxyError::Socks5(reply[1].into()));
+        }
+        let addr_len = match reply[3] {
+            1 => 4,
+            4 => 16,
+            3 => read_exact(io, 1).await?[0] as usize,
+            _ => return Err(ProxyError::InvalidResponse),
+        };
+        read_exact(io, addr_len + 2).await?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Proxy")
+            .field("protocol", &self.protocol)
+            .field("addr", &self.addr)
+            .field(
+                "username",
+                &self.auth.as_ref().map(|(username, _)| username),
+            )
+            .finish()
+    }
+}
+
+/// Connects to TLS servers through a [`Proxy`].
+#[derive(Debug, Clone)]
+pub struct ProxyConnector<C> {
+    proxy: Proxy,
+    connector: C,
+}
+
+impl<C> ProxyConnector<C> {
+    /// Connect through the proxy and then to the target with `connector`.
+    pub fn new(proxy: Proxy, connector: C) -> Self {
+        Self { proxy, connector }
+    }
+
+    pub fn proxy(&self) -> &Proxy {
+        &self.proxy
+    }
+
+    /// Open a tunnel to `target`(`host:port`) and do the TLS handshake with
+    /// the target over it, the host being the server name.
+    ///
+    /// The proxy is connected with Happy Eyeballs, so the runtime must have
+    /// timer enabled.
+    pub async fn connect(&self, target: &str) -> Result<C::Stream, ProxyError>
+    where
+        C: TlsConnect<TcpStream>,
+    {
+        let (stream, _) = connect_host(&self.proxy.addr).await?;
+        self.connect_tunnel(stream, target).await
+    }
+
+    /// Like [`connect`](Self::connect), but the connection to the proxy is
+    /// itself TLS, established with `proxy_connector`. The TLS session with
+    /// the target runs inside it.
+    pub async fn connect_over_tls<P>(
+        &self,
+        proxy_connector: &P,
+        target: &str,
+    ) -> Result<C::Stream, ProxyError>
+    where
+        P: TlsConnect<TcpStream>,
+        C: TlsConnect<P::Stream>,
+    {
+        let (stream, host) = connect_host(&self.proxy.addr).await?;
+        let stream = proxy_connector
+            .connect(host, stream)
+            .await
+            .map_err(ProxyError::ProxyTls)?;
+        self.connect_tunnel(stream, target).await
+    }
+
+    async fn connect_tunnel<IO>(&self, io: IO, target: &str) -> Result<C::Stream, ProxyError>
+    where
+        IO: AsyncReadRent + AsyncWriteRent,
+        C: TlsConnect<IO>,
+    {
+        let io = self.proxy.tunnel(io, target).await?;
+        let (host, _) = split_host_port(target)?;
+        Ok(self.connector.connect(host, io).await?)
+    }
+}
+
+/// Errors of connections through a [`Proxy`].
+#[derive(Error, Debug)]
+pub enum ProxyError {
+    #[error("io error")]
+    Io(#[from] io::Error),
+    /// The TLS handshake with the proxy failed.
+    #[error("proxy tls error")]
+    ProxyTls(#[source] TlsError),
+    /// The TLS handshake with the target failed.
+    #[error("tls error")]
+    Tls(#[from] TlsError),
+    /// The proxy rejected the credentials, or requires some.
+    #[error("proxy authentication failed")]
+    AuthFailed,
+    /// The SOCKS5 proxy accepts none of the offered authentication methods.
+    #[error("no acceptable socks5 authentication method")]
+    NoAcceptableAuth,
+    /// The HTTP proxy answered CONNECT with a non-2xx status other than 407.
+    #[error("http proxy responded with status {0}")]
+    HttpStatus(u16),
+    /// The SOCKS5 proxy failed to connect to the target.
+    #[error("socks5 proxy failed: {0}")]
+    Socks5(Socks5Reply),
+    /// The proxy sent something that is not a valid reply.
+    #[error("invalid proxy response")]
+    InvalidResponse,
+}
+
+impl From<ProxyError> for io::Error {
+    fn from(e: ProxyError) -> Self {
+        match e {
+            ProxyError::Io(e) => e,
+            ProxyError::ProxyTls(e) | ProxyError::Tls(e) => e.into(),
+            ProxyError::AuthFailed => io::Error::new(io::ErrorKind::PermissionDenied, e),
+            ProxyError::Socks5(Socks5Reply::ConnectionRefused) => {
+                io::Error::new(io::ErrorKind::ConnectionRefused, e)
+            }
+            e => io::Error::other(e),
+        }
+    }
+}
+
+/// A SOCKS5 reply code other than succeeded, see RFC 1928 section 6.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Socks5Reply {
+    GeneralFailure,
+    NotAllowed,
+    NetworkUnreachable,
+    HostUnreachable,
+    ConnectionRefused,
+    TtlExpired,
+    CommandNotSupported,
+    AddressTypeNotSupported,
+    Unknown(u8),
+}
+
+impl From<u8> for Socks5Reply {
+    fn from(code: u8) -> Self {
+        match code {
+            1 => Socks5Reply::GeneralFailure,
+            2 => Socks5Reply::NotAllowed,
+            3 => Socks5Reply::NetworkUnreachable,
+            4 => Socks5Reply::HostUnreachable,
+            5 => Socks5Reply::ConnectionRefused,
+            6 => Socks5Reply::TtlExpired,
+            7 => Socks5Reply::CommandNotSupported,
+            8 => Socks5Reply::AddressTypeNotSupported,
+            code => Socks5Reply::Unknown(code),
+        }
+    }
+}
+
+impl fmt::Display for Socks5Reply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Socks5Reply::GeneralFailure => f.write_str("general failure"),
+            Socks5Reply::NotAllowed => f.write_str("connection not allowed by ruleset"),
+            Socks5Reply::NetworkUnreachable => f.write_str("network unreachable"),
+            Socks5Reply::HostUnreachable => f.write_str("host unreachable"),
+            Socks5Reply::ConnectionRefused => f.write_str("connection refused"),
+            Socks5Reply::TtlExpired => f.write_str("TTL expired"),
+            Socks5Reply::CommandNotSupported => f.write_str("command not supported"),
+            Socks5Reply::AddressTypeNotSupported => f.write_str("address type not supported"),
+            Socks5Reply::Unknown(code) => write!(f, "reply code {code}"),
+        }
+    }
+}
+
+async fn write_all<IO: AsyncWriteRent>(io: &mut IO, buf: Vec<u8>) -> io::Result<()> {
+    let (res, _) = io.write_all(buf).await;
+    res?;
+    Ok(())
+}
+
+async fn read_exact<IO: AsyncReadRent>(io: &mut IO, len: usize) -> io::Result<Vec<u8>> {
+    let (res, buf) = io.read_exact(vec![0; len]).await;
+    res?;
+    Ok(buf)
+}
+
+/// Parse the status code of an `HTTP/1.x NNN reason` status line.
+fn status_code(line: &[u8]) -> Option<u16> {
+    let rest = line.strip_prefix(b"HTTP/1.")?;
+    let code = rest.get(1..5).filter(|s| s[0] == b' ')?;
+    if !code[1..].iter().all(u8::is_ascii_digit) || !matches!(rest.get(5), None | Some(b' ')) {
+        return None;
+    }
+    Some(code[1..].iter().fold(0, |n, d| n * 10 + (d - b'0') as u16))
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let n = chunk.iter().fold(0u32, |n, b| n << 8 | *b as u32) << (8 * (3 - chunk.len()));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_line() {
+        assert_eq!(
+            status_code(b"HTTP/1.1 200 Connection established"),
+            Some(200)
+        );
+        assert_eq!(status_code(b"HTTP/1.0 407"), Some(407));
+        for line in [
+            &b"HTTP/2 200 OK"[..],
+            b"HTTP/1.1 2000 OK",
+            b"HTTP/1.1 20 OK",
+            b"HTTP/1.1  200 OK",
+            b"HTTP/1.1 +20 OK",
+            b"SSH-2.0-OpenSSH",
+            b"",
+        ] {
+            assert_eq!(status_code(line), None, "{line:?}");
+        }
+    }
+
+    #[test]
+    fn base64_padding() {
+        // RFC 4648 section 10.
+        for (input, output) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(base64(input.as_bytes()), output);
+        }
+        assert_eq!(base64(&[0xfb, 0xff, 0xbf]), "+/+/");
+        assert_eq!(
+            base64(b"Aladdin:open sesame"),
+            "QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+    }
+}
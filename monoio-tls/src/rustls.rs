@@ -0,0 +1,59 @@
+use monoio::io::{AsyncReadRent, AsyncWriteRent};
+use monoio_rustls::{ClientTlsStream, ServerTlsStream, TlsAcceptor, TlsConnector, TlsStream};
+use rustls::{pki_types::ServerName, Connection, ConnectionCommon};
+
+use crate::{HasTlsInfo, TlsAccept, TlsConnect, TlsError, TlsInfo};
+
+impl<IO: AsyncReadRent + AsyncWriteRent> TlsConnect<IO> for TlsConnector {
+    type Stream = ClientTlsStream<IO>;
+
+    async fn connect(&self, server_name: &str, io: IO) -> Result<Self::Stream, TlsError> {
+        let domain = ServerName::try_from(server_name.to_owned())
+            .map_err(|_| TlsError::InvalidServerName(server_name.to_owned()))?;
+        Ok(TlsConnector::connect(self, domain, io).await?)
+    }
+}
+
+impl<IO: AsyncReadRent + AsyncWriteRent> TlsAccept<IO> for TlsAcceptor {
+    type Stream = ServerTlsStream<IO>;
+
+    async fn accept(&self, io: IO) -> Result<Self::Stream, TlsError> {
+        Ok(TlsAcceptor::accept(self, io).await?)
+    }
+}
+
+fn tls_info<SD>(conn: &ConnectionCommon<SD>) -> TlsInfo {
+    TlsInfo {
+        version: conn.protocol_version().map(|v| u16::from(v).into()),
+        cipher: conn
+            .negotiated_cipher_suite()
+            .and_then(|s| s.suite().as_str())
+            .map(ToOwned::to_owned),
+        alpn: conn.alpn_protocol().map(|p| p.to_vec()),
+        peer_certificates: conn
+            .peer_certificates()
+            .map(|certs| certs.iter().map(|c| c.to_vec()).collect())
+            .unwrap_or_default(),
+    }
+}
+
+impl<IO> HasTlsInfo for ClientTlsStream<IO> {
+    fn tls_info(&self) -> TlsInfo {
+        tls_info(self.get_ref().1)
+    }
+}
+
+impl<IO> HasTlsInfo for ServerTlsStream<IO> {
+    fn tls_info(&self) -> TlsInfo {
+        tls_info(self.get_ref().1)
+    }
+}
+
+impl<IO> HasTlsInfo for TlsStream<IO> {
+    fn tls_info(&self) -> TlsInfo {
+        match self.get_ref().1 {
+            Connection::Client(conn) => tls_info(conn),
+            Connection::Server(conn) => tls_info(conn),
+        }
+    }
+}
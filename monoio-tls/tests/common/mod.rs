@@ -0,0 +1,115 @@
+#![allow(dead_code)]
+
+use std::sync::OnceLock;
+
+use monoio::net::{TcpListener, TcpStream};
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+
+/// A CA and a `localhost` server certificate issued by it.
+pub struct Pki {
+    pub ca: rcgen::Certificate,
+    pub cert: rcgen::Certificate,
+    pub key: KeyPair,
+}
+
+pub fn pki() -> &'static Pki {
+    static PKI: OnceLock<Pki> = OnceLock::new();
+    PKI.get_or_init(|| {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "monoio-tls test CA");
+        let ca = params.self_signed(&ca_key).unwrap();
+
+        let key = KeyPair::generate().unwrap();
+        let mut params =
+            CertificateParams::new(vec!["localhost".to_string(), "127.0.0.1".to_string()]).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "localhost");
+        let cert = params.signed_by(&key, &ca, &ca_key).unwrap();
+        Pki { ca, cert, key }
+    })
+}
+
+/// Returns both ends of a loopback tcp connection.
+pub async fn tcp_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, server) = monoio::join!(TcpStream::connect(addr), listener.accept());
+    (client.unwrap(), server.unwrap().0)
+}
+
+#[cfg(feature = "rustls")]
+pub mod rustls {
+    use std::sync::Arc;
+
+    use ::rustls::{
+        crypto::{ring, CryptoProvider},
+        pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer},
+        ClientConfig, RootCertStore, ServerConfig,
+    };
+    use monoio_tls::monoio_rustls::{TlsAcceptor, TlsConnector};
+
+    use super::*;
+
+    pub fn provider() -> Arc<CryptoProvider> {
+        let _ = ring::default_provider().install_default();
+        CryptoProvider::get_default().unwrap().clone()
+    }
+
+    pub fn acceptor(alpn: &[&[u8]]) -> TlsAcceptor {
+        let pki = pki();
+        let key = PrivateKeyDer::from(PrivatePkcs8KeyDer::from(pki.key.serialize_der()));
+        let mut config = ServerConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![pki.cert.der().clone()], key)
+            .unwrap();
+        config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+        config.into()
+    }
+
+    pub fn connector(alpn: &[&[u8]]) -> TlsConnector {
+        let mut roots = RootCertStore::empty();
+        roots.add(pki().ca.der().clone()).unwrap();
+        let mut config = ClientConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+        config.into()
+    }
+}
+
+#[cfg(feature = "native-tls")]
+pub mod native {
+    use monoio_tls::monoio_native_tls::{TlsAcceptor, TlsConnector};
+
+    use super::*;
+
+    pub fn acceptor() -> TlsAcceptor {
+        let pki = pki();
+        let identity = native_tls::Identity::from_pkcs8(
+            pki.cert.pem().as_bytes(),
+            pki.key.serialize_pem().as_bytes(),
+        )
+        .unwrap();
+        native_tls::TlsAcceptor::new(identity).unwrap().into()
+    }
+
+    pub fn connector(alpn: &[&str]) -> TlsConnector {
+        let ca = native_tls::Certificate::from_pem(pki().ca.pem().as_bytes()).unwrap();
+        native_tls::TlsConnector::builder()
+            .add_root_certificate(ca)
+            .disable_built_in_roots(true)
+            .request_alpns(alpn)
+            .build()
+            .unwrap()
+            .into()
+    }
+}
@@ -0,0 +1,108 @@
+#![cfg(feature = "rustls")]
+
+mod common;
+
+use std::{io, net::SocketAddr};
+
+use monoio::{
+    io::{AsyncReadRentExt, AsyncWriteRent, AsyncWriteRentExt},
+    net::TcpListener,
+};
+use monoio_tls::{TlsConnectExt, TlsError};
+
+/// Serve TLS on a loopback port, echoing 4 bytes per connection.
+fn server() -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let acceptor = common::rustls::acceptor(&[]);
+    monoio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+            monoio::spawn(async move {
+                let mut stream = acceptor.accept(stream).await.unwrap();
+                let (res, buf) = stream.read_exact(vec![0; 4]).await;
+                res.unwrap();
+                let (res, _) = stream.write_all(buf).await;
+                res.unwrap();
+                stream.flush().await.unwrap();
+            });
+        }
+    });
+    addr
+}
+
+/// A loopback address nobody listens on. The listener of std is closed
+/// as soon as dropped.
+fn closed() -> SocketAddr {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap()
+}
+
+async fn echo<S>(mut stream: S)
+where
+    S: monoio::io::AsyncReadRent + AsyncWriteRent,
+{
+    let (res, _) = stream.write_all(b"ping").await;
+    res.unwrap();
+    stream.flush().await.unwrap();
+    let (res, buf) = stream.read_exact(vec![0; 4]).await;
+    res.unwrap();
+    assert_eq!(buf, b"ping");
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn connect_host() {
+    let port = server().port();
+    let connector = common::rustls::connector(&[]);
+    // localhost may resolve to ::1 first, where nobody listens. Resolving it
+    // with the `sync` feature needs a thread pool.
+    let mut addrs = vec![format!("127.0.0.1:{port}")];
+    if cfg!(not(feature = "sync")) {
+        addrs.push(format!("localhost:{port}"));
+    }
+    // the inherent method of the connector would take precedence.
+    for addr in addrs {
+        let stream = TlsConnectExt::connect_host(&connector, &addr)
+            .await
+            .unwrap();
+        echo(stream).await;
+    }
+
+    for addr in ["localhost", "localhost:https", "::1:443"] {
+        let e = TlsConnectExt::connect_host(&connector, addr)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&e, TlsError::Io(e) if e.kind() == io::ErrorKind::InvalidInput),
+            "{addr}: {e:?}"
+        );
+    }
+}
+
+#[monoio::test(timer_enabled = true)]
+async fn connect_addrs() {
+    let addr = server();
+    let connector = common::rustls::connector(&[]);
+    let stream = connector
+        .connect_addrs([closed(), closed(), addr], "localhost")
+        .await
+        .unwrap();
+    echo(stream).await;
+
+    // the last error is returned when every address fails.
+    let e = connector
+        .connect_addrs([closed(), closed()], "localhost")
+        .await
+        .unwrap_err();
+    assert!(matches!(&e, TlsError::Io(e) if e.kind() == io::ErrorKind::ConnectionRefused));
+    let e = connector.connect_addrs([], "localhost").await.unwrap_err();
+    assert!(matches!(&e, TlsError::Io(e) if e.kind() == io::ErrorKind::NotFound));
+
+    // the server name is still verified.
+    let e = connector
+        .connect_addrs([addr], "example.com")
+        .await
+        .unwrap_err();
+    assert!(matches!(e, TlsError::Rustls(_) | TlsError::Io(_)));
+}
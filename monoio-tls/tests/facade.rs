@@ -0,0 +1,142 @@
+mod common;
+
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+use monoio::{
+    io::{AsyncReadRentExt, AsyncWriteRent, AsyncWriteRentExt},
+    net::TcpStream,
+};
+use monoio_tls::TlsVersion;
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+use monoio_tls::{HasTlsInfo, TlsAccept, TlsConnect, TlsError, TlsInfo};
+
+/// Connect `connector` to `acceptor`, exchange a message and return the
+/// connection info seen by the client and the server.
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+async fn exchange<C, A>(connector: &C, acceptor: &A, server_name: &str) -> (TlsInfo, TlsInfo)
+where
+    C: TlsConnect<TcpStream>,
+    A: TlsAccept<TcpStream>,
+{
+    let (client, server) = common::tcp_pair().await;
+    let (client, server) = monoio::join!(
+        connector.connect(server_name, client),
+        acceptor.accept(server)
+    );
+    let (mut client, mut server) = (client.unwrap(), server.unwrap());
+
+    let (res, _) = client.write_all(b"ping").await;
+    res.unwrap();
+    client.flush().await.unwrap();
+    let (res, buf) = server.read_exact(vec![0; 4]).await;
+    res.unwrap();
+    assert_eq!(buf, b"ping");
+    (client.tls_info(), server.tls_info())
+}
+
+#[test]
+fn tls_version_from_wire() {
+    assert_eq!(TlsVersion::from(0x0303), TlsVersion::Tls12);
+    assert_eq!(TlsVersion::from(0x0304), TlsVersion::Tls13);
+    assert_eq!(TlsVersion::from(0x7f1c), TlsVersion::Unknown(0x7f1c));
+}
+
+#[cfg(feature = "rustls")]
+#[monoio::test]
+async fn rustls_backend() {
+    let connector = common::rustls::connector(&[b"h2", b"http/1.1"]);
+    let acceptor = common::rustls::acceptor(&[b"http/1.1"]);
+    let (client, server) = exchange(&connector, &acceptor, "localhost").await;
+
+    assert_eq!(client.version, Some(TlsVersion::Tls13));
+    assert!(client.cipher.as_deref().unwrap().starts_with("TLS13_"));
+    assert_eq!(client.alpn.as_deref(), Some(&b"http/1.1"[..]));
+    assert_eq!(
+        client.peer_certificates,
+        vec![common::pki().cert.der().to_vec()]
+    );
+    assert_eq!(server.version, client.version);
+    assert_eq!(server.cipher, client.cipher);
+    assert_eq!(server.alpn, client.alpn);
+    assert!(server.peer_certificates.is_empty());
+}
+
+#[cfg(feature = "rustls")]
+#[monoio::test]
+async fn rustls_ip_server_name() {
+    let connector = common::rustls::connector(&[]);
+    let acceptor = common::rustls::acceptor(&[]);
+    let (client, _) = exchange(&connector, &acceptor, "127.0.0.1").await;
+    assert_eq!(client.alpn, None);
+}
+
+#[cfg(feature = "rustls")]
+#[monoio::test]
+async fn rustls_invalid_server_name() {
+    let connector = common::rustls::connector(&[]);
+    let (client, _server) = common::tcp_pair().await;
+    let err = TlsConnect::connect(&connector, "not a name", client)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, TlsError::InvalidServerName(name) if name == "not a name"));
+}
+
+#[cfg(feature = "rustls")]
+#[monoio::test]
+async fn rustls_verification_error() {
+    let connector = common::rustls::connector(&[]);
+    let acceptor = common::rustls::acceptor(&[]);
+    let (client, server) = common::tcp_pair().await;
+    let (client, _) = monoio::join!(
+        TlsConnect::connect(&connector, "example.com", client),
+        acceptor.accept(server)
+    );
+    // rustls reports handshake failures through io errors.
+    let TlsError::Io(e) = client.unwrap_err() else {
+        panic!("expect an io error");
+    };
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+    assert!(e.get_ref().unwrap().is::<rustls::Error>());
+}
+
+#[cfg(feature = "native-tls")]
+#[monoio::test]
+async fn native_tls_backend() {
+    let connector = common::native::connector(&["h2"]);
+    let acceptor = common::native::acceptor();
+    let (client, server) = exchange(&connector, &acceptor, "localhost").await;
+
+    assert_eq!(client.alpn, None);
+    assert_eq!(
+        client.peer_certificates,
+        vec![common::pki().cert.der().to_vec()]
+    );
+    assert!(server.peer_certificates.is_empty());
+}
+
+#[cfg(feature = "native-tls")]
+#[monoio::test]
+async fn native_tls_verification_error() {
+    let connector = common::native::connector(&[]);
+    let acceptor = common::native::acceptor();
+    let (client, server) = common::tcp_pair().await;
+    let (client, _) = monoio::join!(
+        TlsConnect::connect(&connector, "example.com", client),
+        acceptor.accept(server)
+    );
+    assert!(matches!(client.unwrap_err(), TlsError::NativeTls(_)));
+}
+
+#[cfg(all(feature = "rustls", feature = "native-tls"))]
+#[monoio::test]
+async fn mixed_backends() {
+    let connector = common::native::connector(&[]);
+    let acceptor = common::rustls::acceptor(&[]);
+    let (client, server) = exchange(&connector, &acceptor, "localhost").await;
+    assert_eq!(client.peer_certificates.len(), 1);
+    assert!(server.version.is_some());
+
+    let connector = common::rustls::connector(&[]);
+    let acceptor = common::native::acceptor();
+    let (client, _) = exchange(&connector, &acceptor, "localhost").await;
+    assert_eq!(client.peer_certificates.len(), 1);
+}